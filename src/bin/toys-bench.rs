@@ -0,0 +1,200 @@
+//! # toys-bench
+//!
+//! 基于[`toys::networks::http::bench`]的HTTP压测命令行工具:
+//! ```text
+//! toys-bench -c 32 -d 10s http://127.0.0.1:8080/health
+//! toys-bench -c 8 -n 1000 -X POST -H "Content-Type: application/json" -b @body.json --json http://127.0.0.1:8080/users
+//! ```
+
+use std::process::ExitCode;
+use std::time::Duration;
+use toys::networks::http::bench::Bench;
+use toys::networks::http::{AsyncHttpClient, Method};
+
+const USAGE: &str = "Usage: toys-bench [OPTIONS] <URL>
+
+Options:
+  -c, --concurrency <N>    number of concurrent requests [default: 10]
+  -n, --requests <N>       total number of requests
+  -d, --duration <TIME>    duration of the test, e.g. 30s, 500ms, 2m [default: 10s]
+  -X, --method <METHOD>    request method [default: GET]
+  -H, --header <HEADER>    request header in \"Name: value\" form, can be repeated
+  -b, --body <DATA>        request body, @path reads the body from a file
+  -t, --timeout <TIME>     timeout of each request
+      --json               print the report as json
+  -h, --help               print this help";
+
+// 命令行参数
+#[derive(Debug, PartialEq)]
+struct Options {
+    url: String,
+    concurrency: usize,
+    requests: Option<u64>,
+    duration: Duration,
+    method: Method,
+    headers: Vec<(String, String)>,
+    body: Option<String>,
+    timeout: Option<Duration>,
+    json: bool,
+}
+
+// 解析命令行参数,返回None表示需要输出帮助信息
+fn parse_args<I: IntoIterator<Item = String>>(args: I) -> Result<Option<Options>, String> {
+    let mut args = args.into_iter();
+    let mut url = None;
+    let mut options = Options {
+        url: String::new(),
+        concurrency: 10,
+        requests: None,
+        duration: Duration::from_secs(10),
+        method: Method::GET,
+        headers: Vec::new(),
+        body: None,
+        timeout: None,
+        json: false,
+    };
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| args.next().ok_or_else(|| format!("missing value for {}", name));
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "--json" => options.json = true,
+            "-c" | "--concurrency" => {
+                options.concurrency = value(&arg)?.parse().map_err(|_| format!("invalid value for {}", arg))?;
+            }
+            "-n" | "--requests" => {
+                options.requests = Some(value(&arg)?.parse().map_err(|_| format!("invalid value for {}", arg))?);
+            }
+            "-d" | "--duration" => options.duration = parse_duration(&value(&arg)?)?,
+            "-t" | "--timeout" => options.timeout = Some(parse_duration(&value(&arg)?)?),
+            "-X" | "--method" => {
+                options.method = value(&arg)?.to_uppercase().parse().map_err(|_| format!("invalid value for {}", arg))?;
+            }
+            "-H" | "--header" => {
+                let header = value(&arg)?;
+                let (name, value) = header.split_once(':').ok_or_else(|| format!("invalid header: {}", header))?;
+                options.headers.push((name.trim().to_string(), value.trim().to_string()));
+            }
+            "-b" | "--body" => {
+                let body = value(&arg)?;
+                options.body = Some(match body.strip_prefix('@') {
+                    Some(path) => std::fs::read_to_string(path).map_err(|e| format!("failed to read {}: {}", path, e))?,
+                    None => body,
+                });
+            }
+            _ if arg.starts_with('-') => return Err(format!("unknown option: {}", arg)),
+            _ if url.is_none() => url = Some(arg),
+            _ => return Err(format!("unexpected argument: {}", arg)),
+        }
+    }
+    options.url = url.ok_or("missing url")?;
+    Ok(Some(options))
+}
+
+// 解析`500ms`、`30s`、`2m`、`1h`形式的时长,不带单位时按秒处理
+fn parse_duration(value: &str) -> Result<Duration, String> {
+    let invalid = || format!("invalid duration: {}", value);
+    let split = value.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(value.len());
+    let number: f64 = value[..split].parse().map_err(|_| invalid())?;
+    let seconds = match &value[split..] {
+        "ms" => number / 1000.0,
+        "" | "s" => number,
+        "m" => number * 60.0,
+        "h" => number * 3600.0,
+        _ => return Err(invalid()),
+    };
+    Duration::try_from_secs_f64(seconds).map_err(|_| invalid())
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let options = match parse_args(std::env::args().skip(1)) {
+        Ok(Some(options)) => options,
+        Ok(None) => {
+            println!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        }
+        Err(e) => {
+            eprintln!("error: {}\n\n{}", e, USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let client = AsyncHttpClient::new();
+    let mut request = client.request(options.method.clone(), &options.url);
+    for (name, value) in &options.headers {
+        request = request.header(name, value);
+    }
+    if let Some(body) = options.body.clone() {
+        request = request.body(body);
+    }
+    if let Some(timeout) = options.timeout {
+        request = request.timeout(timeout);
+    }
+    let bench = Bench::new(options.concurrency);
+    let bench = match options.requests {
+        Some(requests) => bench.requests(requests),
+        None => bench.duration(options.duration),
+    };
+    if !options.json {
+        match options.requests {
+            Some(requests) => println!("Running {} requests to {} with {} connections", requests, options.url, options.concurrency),
+            None => println!("Running {:?} test @ {} with {} connections", options.duration, options.url, options.concurrency),
+        }
+    }
+
+    let report = match bench.run(request).await {
+        Ok(report) => report,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    if options.json {
+        match toys::data::json::to_json_pretty(&report) {
+            Ok(json) => println!("{}", json),
+            Err(e) => {
+                eprintln!("error: {}", e);
+                return ExitCode::FAILURE;
+            }
+        }
+    } else {
+        println!("{}", report);
+    }
+    ExitCode::SUCCESS
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use toys::networks::http::Method;
+    use crate::{parse_args, parse_duration};
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(str::to_string).collect()
+    }
+
+    /// 测试命令行参数解析
+    #[test]
+    fn test_parse_args() {
+        let options = parse_args(args("-c 4 -n 100 -X post -H X-Token:abc -b {} -t 2s --json http://localhost/")).unwrap().unwrap();
+        assert_eq!((options.concurrency, options.requests, options.method), (4, Some(100), Method::POST));
+        assert_eq!(options.headers, vec![("X-Token".to_string(), "abc".to_string())]);
+        assert_eq!((options.body.as_deref(), options.timeout, options.json), (Some("{}"), Some(Duration::from_secs(2)), true));
+        assert_eq!(options.url, "http://localhost/");
+
+        assert!(parse_args(args("--help")).unwrap().is_none());
+        assert_eq!(parse_args(args("-c")).unwrap_err(), "missing value for -c");
+        assert_eq!(parse_args(args("-c 4")).unwrap_err(), "missing url");
+        assert_eq!(parse_args(args("--verbose http://localhost/")).unwrap_err(), "unknown option: --verbose");
+    }
+
+    /// 测试时长解析
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("500ms"), Ok(Duration::from_millis(500)));
+        assert_eq!(parse_duration("1.5"), Ok(Duration::from_millis(1500)));
+        assert_eq!(parse_duration("2m"), Ok(Duration::from_secs(120)));
+        assert!(parse_duration("10x").is_err());
+        assert!(parse_duration("s").is_err());
+    }
+}
@@ -4,7 +4,7 @@ use serde::{Deserialize, Serialize};
 use serde::de::DeserializeOwned;
 
 /// 将一个结构体对象序列化为Json字符串
-/// T 必须实现了Serialize Deserialize 两个特征
+/// T 必须实现了Serialize特征
 /// # Examples
 /// ```
 /// use toys::data::json::{Student, to_json_str};
@@ -12,11 +12,23 @@ use serde::de::DeserializeOwned;
 /// let json: String = to_json_str(&stu).unwrap();
 /// println!("{}",json)
 /// ```
-pub fn to_json_str<'a,T: Serialize + Deserialize<'a>>(value: &T) -> Result<String,std::io::Error>
+pub fn to_json_str<T: Serialize + ?Sized>(value: &T) -> Result<String,std::io::Error>
 {
     Ok(serde_json::to_string(value)?)
 }
 
+/// 将一个结构体对象序列化为带缩进的Json字符串,便于阅读与比较差异
+/// # Examples
+/// ```
+/// use toys::data::json::{Student, to_json_pretty};
+/// let json = to_json_pretty(&Student::default()).unwrap();
+/// assert!(json.starts_with("{\n  \"name\": \"满城雪\""));
+/// ```
+pub fn to_json_pretty<T: Serialize + ?Sized>(value: &T) -> Result<String,std::io::Error>
+{
+    Ok(serde_json::to_string_pretty(value)?)
+}
+
 /// 将一个Json字符串反序列化为一个结构体对象
 /// # Examples
 /// ```
@@ -38,8 +50,8 @@ pub fn from_json_str<T: DeserializeOwned>(json: &str) -> Result<T, serde_json::E
 /// let ass_val = vec![123, 34, 110, 97, 109, 101, 34, 58, 34, 230, 187, 161, 229, 159, 142, 233, 155, 170, 34, 44, 34, 97, 103, 101, 34, 58, 50, 51, 44, 34, 97, 100, 100, 114, 101, 115, 115, 34, 58, 34, 229, 185, 191, 229, 183, 158, 34, 44, 34, 108, 111, 99, 107, 101, 100, 34, 58, 116, 114, 117, 101, 44, 34, 115, 101, 120, 34, 58, 34, 231, 148, 183, 34, 125];
 /// assert_eq!(bytes,ass_val)
 /// ```
-pub fn to_json_bytes<'a,T>(value: &T) -> Result<Vec<u8>,std::io::Error>
-    where T: Serialize + Deserialize<'a>
+pub fn to_json_bytes<T>(value: &T) -> Result<Vec<u8>,std::io::Error>
+    where T: Serialize + ?Sized
 {
     Ok(serde_json::to_vec(value)?)
 }
@@ -52,8 +64,8 @@ pub fn to_json_bytes<'a,T>(value: &T) -> Result<Vec<u8>,std::io::Error>
 /// let student: Student = from_json_bytes(&json_bytes).unwrap();
 /// assert_eq!(student,Student::default())
 /// ```
-pub fn from_json_bytes<T: DeserializeOwned>(bytes: &Vec<u8>) -> Result<T,serde_json::Error>{
-    Ok(serde_json::from_slice(bytes)?)
+pub fn from_json_bytes<T: DeserializeOwned>(bytes: &[u8]) -> Result<T,serde_json::Error>{
+    serde_json::from_slice(bytes)
 }
 
 
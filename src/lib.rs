@@ -2,6 +2,8 @@
 //! `toys`  These things work as well as toys  <br>
 //! 提供了一些常用的函数库.
 
+// 使过程宏生成的`::toys::`路径在本crate内部同样可用
+extern crate self as toys;
 
 // 将string.rs文件所有资源作为一个mod,并且导出
 #[cfg(feature = "strings")]
@@ -19,8 +21,8 @@ mod tests {
 
     #[test]
     fn test_is_blank() {
-        assert_eq!(is_blank("".to_string()),true);
-        assert_eq!(is_blank(" ".to_string()),false);
+        assert!(is_blank("".to_string()));
+        assert!(!is_blank(" ".to_string()));
     }
 
     #[test]
@@ -0,0 +1,215 @@
+//! # 批量请求
+//!
+//! 以受限的并发数执行大量请求,可按主机限制请求速率,
+//! 结果按完成顺序或输入顺序返回,单个请求失败不会影响其他请求.
+
+use std::time::Duration;
+use futures_util::stream::{self, BoxStream};
+use futures_util::StreamExt;
+use crate::networks::http::client::spawn;
+use crate::networks::http::error::HttpResult;
+use crate::networks::http::limiter::RateLimiter;
+use crate::networks::http::request::{AsyncRequestBuilder, RequestBuilder};
+use crate::networks::http::response::HttpResponse;
+
+/// 批量请求中单个请求的结果
+#[derive(Debug)]
+pub struct BatchItem {
+    // 请求在输入中的位置,从0开始
+    pub index: usize,
+    // 请求结果
+    pub result: HttpResult<HttpResponse>,
+}
+
+/// 批量请求执行器
+///
+/// # Examples
+/// ```no_run
+/// use std::time::Duration;
+/// use futures_util::StreamExt;
+/// use toys::networks::http::AsyncHttpClient;
+/// use toys::networks::http::batch::Batch;
+/// # async fn run() {
+/// let client = AsyncHttpClient::new();
+/// let requests = (1..=100).map(move |id| client.get(&format!("https://example.com/users/{}", id)));
+/// let mut results = Batch::new(8).per_host_rate(20, Duration::from_secs(1)).stream(requests);
+/// while let Some(item) = results.next().await {
+///     println!("#{} {:?}", item.index, item.result.map(|r| r.status));
+/// }
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Batch {
+    // 同时进行的最大请求数
+    concurrency: usize,
+    // 每个主机在时间窗口内允许的请求数
+    host_rate: Option<(u32, Duration)>,
+    // 是否按输入顺序返回结果
+    ordered: bool,
+}
+
+impl Default for Batch {
+    fn default() -> Self {
+        Batch::new(8)
+    }
+}
+
+impl Batch {
+    /// 创建最多同时执行`concurrency`个请求的执行器,结果默认按完成顺序返回
+    pub fn new(concurrency: usize) -> Self {
+        Batch { concurrency: concurrency.max(1), host_rate: None, ordered: false }
+    }
+
+    /// 限制每个主机在`per`时间内最多发起`requests`个请求,请求会被均匀地错开
+    pub fn per_host_rate(mut self, requests: u32, per: Duration) -> Self {
+        self.host_rate = Some((requests.max(1), per));
+        self
+    }
+
+    /// 设置是否按输入顺序返回结果
+    pub fn ordered(mut self, ordered: bool) -> Self {
+        self.ordered = ordered;
+        self
+    }
+
+    /// 执行请求,以异步数据流的方式返回结果
+    pub fn stream<I>(&self, requests: I) -> BoxStream<'static, BatchItem>
+        where I: IntoIterator<Item = AsyncRequestBuilder>,
+              I::IntoIter: Send + 'static
+    {
+        let limiter = self.host_rate.map(|(requests, per)| RateLimiter::per_host(requests, per).burst(1));
+        let tasks = stream::iter(requests.into_iter().enumerate()).map(move |(index, builder)| {
+            let limiter = limiter.clone();
+            async move {
+                let (client, request) = builder.into_parts();
+                let result = match request {
+                    Ok(request) => match &limiter {
+                        Some(limiter) => match limiter.acquire(&request.url).await {
+                            Ok(()) => client.execute(request).await,
+                            Err(e) => Err(e),
+                        },
+                        None => client.execute(request).await,
+                    },
+                    Err(e) => Err(e),
+                };
+                BatchItem { index, result }
+            }
+        });
+        if self.ordered {
+            tasks.buffered(self.concurrency).boxed()
+        } else {
+            tasks.buffer_unordered(self.concurrency).boxed()
+        }
+    }
+
+    /// 执行全部请求并收集结果
+    pub async fn collect<I>(&self, requests: I) -> Vec<BatchItem>
+        where I: IntoIterator<Item = AsyncRequestBuilder>,
+              I::IntoIter: Send + 'static
+    {
+        self.stream(requests).collect().await
+    }
+
+    /// 使用同步请求构建器执行请求,返回的迭代器在结果就绪时依次产出
+    pub fn iter<I>(&self, requests: I) -> impl Iterator<Item = BatchItem>
+        where I: IntoIterator<Item = RequestBuilder>,
+              I::IntoIter: Send + 'static
+    {
+        let mut results = self.stream(requests.into_iter().map(RequestBuilder::into_async));
+        let (tx, rx) = std::sync::mpsc::channel();
+        spawn(async move {
+            while let Some(item) = results.next().await {
+                if tx.send(item).is_err() {
+                    break;
+                }
+            }
+        });
+        rx.into_iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::{Duration, Instant};
+    use bytes::Bytes;
+    use crate::networks::http::{AsyncHttpClient, HttpClient, HttpResponse, HttpResult, Middleware, Next};
+    use crate::networks::http::batch::Batch;
+    use crate::networks::http::middleware::BoxFuture;
+    use crate::networks::http::request::Request;
+
+    // 模拟耗时请求并统计最大并发数的中间件,延迟时间取自路径
+    #[derive(Default)]
+    struct SlowEcho {
+        in_flight: AtomicUsize,
+        max_in_flight: Arc<AtomicUsize>,
+    }
+
+    impl Middleware for SlowEcho {
+        fn handle<'a>(&'a self, request: Request, _next: Next<'a>) -> BoxFuture<'a, HttpResult<HttpResponse>> {
+            Box::pin(async move {
+                let current = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                self.max_in_flight.fetch_max(current, Ordering::SeqCst);
+                let delay: u64 = request.url.path().trim_start_matches('/').parse().unwrap_or(0);
+                tokio::time::sleep(Duration::from_millis(delay)).await;
+                self.in_flight.fetch_sub(1, Ordering::SeqCst);
+                Ok(HttpResponse {
+                    status: reqwest::StatusCode::OK,
+                    headers: Default::default(),
+                    url: request.url.clone(),
+                    version: reqwest::Version::HTTP_11,
+                    elapsed: Default::default(),
+                    body: Bytes::from(request.url.path().to_string()),
+                })
+            })
+        }
+    }
+
+    fn client(max: Arc<AtomicUsize>) -> AsyncHttpClient {
+        AsyncHttpClient::builder()
+            .middleware(SlowEcho { in_flight: AtomicUsize::new(0), max_in_flight: max })
+            .build_async()
+            .unwrap()
+    }
+
+    /// 测试并发限制与按输入顺序返回结果
+    #[tokio::test]
+    async fn test_concurrency_and_order() {
+        let max = Arc::new(AtomicUsize::new(0));
+        let client = client(max.clone());
+        let delays = [60, 10, 40, 20, 30, 50];
+        let requests: Vec<_> = delays.iter().map(|d| client.get(&format!("http://localhost/{}", d))).collect();
+        let items = Batch::new(3).ordered(true).collect(requests).await;
+        assert_eq!(max.load(Ordering::SeqCst), 3);
+        let indexes: Vec<usize> = items.iter().map(|item| item.index).collect();
+        assert_eq!(indexes, vec![0, 1, 2, 3, 4, 5]);
+        assert_eq!(items[2].result.as_ref().unwrap().body, "/40");
+
+        let requests: Vec<_> = delays.iter().map(|d| client.get(&format!("http://localhost/{}", d))).collect();
+        let items = Batch::new(6).collect(requests).await;
+        assert_eq!(items[0].index, 1);
+    }
+
+    /// 测试单个请求失败与按主机限速
+    #[test]
+    fn test_errors_and_host_rate() {
+        let client = HttpClient::builder()
+            .middleware(SlowEcho::default())
+            .build()
+            .unwrap();
+        let requests = vec![
+            client.get("http://a.local/0"),
+            client.get("not a url"),
+            client.get("http://a.local/0"),
+            client.get("http://b.local/0"),
+            client.get("http://a.local/0"),
+        ];
+        let start = Instant::now();
+        let items: Vec<_> = Batch::new(5).per_host_rate(10, Duration::from_millis(500)).iter(requests).collect();
+        assert_eq!(items.len(), 5);
+        assert_eq!(items.iter().filter(|item| item.result.is_err()).count(), 1);
+        // a.local的三个请求间隔50ms
+        assert!(start.elapsed() >= Duration::from_millis(100));
+    }
+}
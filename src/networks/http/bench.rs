@@ -0,0 +1,363 @@
+//! # HTTP压测
+//!
+//! 以固定的并发数重复发送同一个请求,直到达到请求总数或持续时间,
+//! 统计吞吐量、延迟分位数、状态码分布与错误类型,结果可以序列化为Json.
+//! 命令行工具`toys-bench`基于本模块实现.
+
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use serde::{Serialize, Serializer};
+use crate::networks::http::client::{block_on, AsyncHttpClient};
+use crate::networks::http::error::{HttpError, HttpResult};
+use crate::networks::http::request::{AsyncRequestBuilder, Request, RequestBuilder};
+
+/// 压测的结束条件
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    /// 发送指定数量的请求
+    Requests(u64),
+    /// 持续发送请求直到超过指定时间,已发出的请求会等待完成
+    Duration(Duration),
+}
+
+/// 压测执行器
+///
+/// # Examples
+/// ```no_run
+/// use std::time::Duration;
+/// use toys::networks::http::AsyncHttpClient;
+/// use toys::networks::http::bench::Bench;
+/// # async fn run() -> toys::networks::http::HttpResult<()> {
+/// let client = AsyncHttpClient::new();
+/// let report = Bench::new(32).duration(Duration::from_secs(10)).run(client.get("http://127.0.0.1:8080/health")).await?;
+/// println!("{}", report);
+/// println!("{}", toys::data::json::to_json_pretty(&report)?);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Bench {
+    // 同时进行的请求数
+    concurrency: usize,
+    // 结束条件
+    limit: Limit,
+}
+
+impl Bench {
+    /// 创建并发数为`concurrency`的执行器,默认发送1000个请求
+    pub fn new(concurrency: usize) -> Self {
+        Bench { concurrency: concurrency.max(1), limit: Limit::Requests(1000) }
+    }
+
+    /// 发送`requests`个请求后结束
+    pub fn requests(mut self, requests: u64) -> Self {
+        self.limit = Limit::Requests(requests);
+        self
+    }
+
+    /// 持续发送请求`duration`后结束
+    pub fn duration(mut self, duration: Duration) -> Self {
+        self.limit = Limit::Duration(duration);
+        self
+    }
+
+    /// 执行压测,请求构建过程中的错误会直接返回.
+    /// 每个并发请求是独立的tokio任务,在多线程运行时中可以利用多个CPU核心
+    pub async fn run(&self, request: AsyncRequestBuilder) -> HttpResult<Report> {
+        let (client, request) = request.into_parts();
+        let request = request?;
+        let issued = Arc::new(AtomicU64::new(0));
+        let start = Instant::now();
+        let deadline = match self.limit {
+            Limit::Duration(duration) => Some(start + duration),
+            Limit::Requests(_) => None,
+        };
+        let workers: Vec<_> = (0..self.concurrency).map(|_| {
+            let (client, request, issued, limit) = (client.clone(), request.clone(), issued.clone(), self.limit);
+            tokio::spawn(async move {
+                let mut samples = Samples::default();
+                loop {
+                    let more = match limit {
+                        Limit::Requests(total) => issued.fetch_add(1, Ordering::Relaxed) < total,
+                        Limit::Duration(_) => deadline.is_some_and(|deadline| Instant::now() < deadline),
+                    };
+                    if !more {
+                        return samples;
+                    }
+                    samples.record(&client, request.clone()).await;
+                }
+            })
+        }).collect();
+
+        let mut samples = Samples::default();
+        for worker in workers {
+            let worker = worker.await.map_err(|e| HttpError::Builder(format!("bench worker failed: {}", e)))?;
+            samples.merge(worker);
+        }
+        Ok(samples.report(self.concurrency, start.elapsed()))
+    }
+
+    /// 在共享的后台运行时中执行压测,供同步代码使用
+    pub fn run_blocking(&self, request: RequestBuilder) -> HttpResult<Report> {
+        let bench = self.clone();
+        let request = request.into_async();
+        block_on(async move { bench.run(request).await })
+    }
+}
+
+// 单个任务收集的请求结果
+#[derive(Debug, Default)]
+struct Samples {
+    latencies: Vec<Duration>,
+    successes: u64,
+    bytes: u64,
+    status_codes: BTreeMap<u16, u64>,
+    errors: BTreeMap<String, u64>,
+}
+
+impl Samples {
+    // 发送一次请求并记录结果,2xx与3xx响应视为成功
+    async fn record(&mut self, client: &AsyncHttpClient, request: Request) {
+        let start = Instant::now();
+        let result = client.execute(request).await;
+        self.latencies.push(start.elapsed());
+        match result {
+            Ok(response) => {
+                self.bytes += response.body.len() as u64;
+                *self.status_codes.entry(response.status.as_u16()).or_default() += 1;
+                if response.status.is_success() || response.status.is_redirection() {
+                    self.successes += 1;
+                } else {
+                    *self.errors.entry(format!("status {}", response.status.as_u16())).or_default() += 1;
+                }
+            }
+            Err(error) => *self.errors.entry(error_kind(&error)).or_default() += 1,
+        }
+    }
+
+    fn merge(&mut self, other: Samples) {
+        self.latencies.extend(other.latencies);
+        self.successes += other.successes;
+        self.bytes += other.bytes;
+        for (status, count) in other.status_codes {
+            *self.status_codes.entry(status).or_default() += count;
+        }
+        for (kind, count) in other.errors {
+            *self.errors.entry(kind).or_default() += count;
+        }
+    }
+
+    fn report(mut self, concurrency: usize, elapsed: Duration) -> Report {
+        self.latencies.sort_unstable();
+        let requests = self.latencies.len() as u64;
+        let total: Duration = self.latencies.iter().sum();
+        let latency = Latency {
+            min: self.latencies.first().copied().unwrap_or_default(),
+            mean: if requests == 0 { Duration::ZERO } else { total.div_f64(requests as f64) },
+            p50: percentile(&self.latencies, 50.0),
+            p90: percentile(&self.latencies, 90.0),
+            p99: percentile(&self.latencies, 99.0),
+            max: self.latencies.last().copied().unwrap_or_default(),
+        };
+        let seconds = elapsed.as_secs_f64();
+        Report {
+            concurrency,
+            requests,
+            successes: self.successes,
+            failures: requests - self.successes,
+            elapsed,
+            throughput: if seconds > 0.0 { requests as f64 / seconds } else { 0.0 },
+            bytes: self.bytes,
+            latency,
+            status_codes: self.status_codes,
+            errors: self.errors,
+        }
+    }
+}
+
+// 按错误类型归类,避免错误信息中的地址等内容导致分类过多
+fn error_kind(error: &HttpError) -> String {
+    let kind = match error {
+        _ if error.is_timeout() => "timeout",
+        HttpError::Request(e) if e.is_connect() => "connect",
+        HttpError::Request(_) => "request",
+        HttpError::Status { status, .. } => return format!("status {}", status.as_u16()),
+        HttpError::Builder(_) | HttpError::InvalidUrl(_) | HttpError::Encode(_) => "invalid request",
+        HttpError::Io(_) => "io",
+        HttpError::Checksum { .. } | HttpError::Decode { .. } => "decode",
+        HttpError::RateLimited(_) => "rate limited",
+        HttpError::CircuitOpen(_) => "circuit open",
+        HttpError::Cassette(_) => "cassette",
+        HttpError::TooManyRedirects(_) => "too many redirects",
+        HttpError::Timeout(_) | HttpError::ReadTimeout(_) => "timeout",
+    };
+    kind.to_string()
+}
+
+// 最近秩法计算分位数,`sorted`需已排序
+fn percentile(sorted: &[Duration], percent: f64) -> Duration {
+    if sorted.is_empty() {
+        return Duration::ZERO;
+    }
+    let rank = (percent / 100.0 * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+/// 延迟统计,包含失败的请求,序列化为毫秒
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Latency {
+    #[serde(serialize_with = "millis")]
+    pub min: Duration,
+    #[serde(serialize_with = "millis")]
+    pub mean: Duration,
+    #[serde(serialize_with = "millis")]
+    pub p50: Duration,
+    #[serde(serialize_with = "millis")]
+    pub p90: Duration,
+    #[serde(serialize_with = "millis")]
+    pub p99: Duration,
+    #[serde(serialize_with = "millis")]
+    pub max: Duration,
+}
+
+/// 压测结果
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Report {
+    // 并发数
+    pub concurrency: usize,
+    // 完成的请求总数
+    pub requests: u64,
+    // 成功(2xx与3xx)的请求数
+    pub successes: u64,
+    // 失败的请求数
+    pub failures: u64,
+    // 总耗时,序列化为毫秒
+    #[serde(serialize_with = "millis")]
+    pub elapsed: Duration,
+    // 每秒完成的请求数
+    pub throughput: f64,
+    // 接收的响应体字节数
+    pub bytes: u64,
+    // 延迟统计
+    pub latency: Latency,
+    // 各状态码的响应数
+    pub status_codes: BTreeMap<u16, u64>,
+    // 各类错误的数量,错误状态码记为`status <code>`
+    pub errors: BTreeMap<String, u64>,
+}
+
+impl Display for Report {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{} requests in {:.2?} with {} connections, {} bytes read", self.requests, self.elapsed, self.concurrency, self.bytes)?;
+        writeln!(f, "Requests/sec: {:.2}", self.throughput)?;
+        let latency = &self.latency;
+        writeln!(f, "Latency: min {:.2?}, mean {:.2?}, p50 {:.2?}, p90 {:.2?}, p99 {:.2?}, max {:.2?}",
+                 latency.min, latency.mean, latency.p50, latency.p90, latency.p99, latency.max)?;
+        let codes: Vec<String> = self.status_codes.iter().map(|(status, count)| format!("{}: {}", status, count)).collect();
+        writeln!(f, "Status codes: {}", if codes.is_empty() { "-".to_string() } else { codes.join(", ") })?;
+        write!(f, "Failures: {}", self.failures)?;
+        for (kind, count) in &self.errors {
+            write!(f, "\n  {}: {}", kind, count)?;
+        }
+        Ok(())
+    }
+}
+
+// 将时长序列化为毫秒
+fn millis<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_f64(duration.as_secs_f64() * 1000.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+    use bytes::Bytes;
+    use reqwest::StatusCode;
+    use crate::networks::http::{AsyncHttpClient, HttpClient, HttpError, HttpResponse, HttpResult, Middleware, Next};
+    use crate::networks::http::bench::{percentile, Bench};
+    use crate::networks::http::middleware::BoxFuture;
+    use crate::networks::http::request::Request;
+
+    // 每10个请求中有1个返回503、1个超时,其余返回200,同时统计最大并发数
+    #[derive(Default)]
+    struct Flaky {
+        count: AtomicUsize,
+        in_flight: AtomicUsize,
+        max_in_flight: Arc<AtomicUsize>,
+    }
+
+    impl Middleware for Flaky {
+        fn handle<'a>(&'a self, request: Request, _next: Next<'a>) -> BoxFuture<'a, HttpResult<HttpResponse>> {
+            Box::pin(async move {
+                let current = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                self.max_in_flight.fetch_max(current, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(5)).await;
+                self.in_flight.fetch_sub(1, Ordering::SeqCst);
+                let status = match self.count.fetch_add(1, Ordering::SeqCst) % 10 {
+                    0 => StatusCode::SERVICE_UNAVAILABLE,
+                    1 => return Err(HttpError::Timeout(Duration::from_secs(1))),
+                    _ => StatusCode::OK,
+                };
+                Ok(HttpResponse {
+                    status,
+                    headers: Default::default(),
+                    url: request.url.clone(),
+                    version: reqwest::Version::HTTP_11,
+                    elapsed: Default::default(),
+                    body: Bytes::from_static(b"ok"),
+                })
+            })
+        }
+    }
+
+    /// 测试按请求数压测的统计结果与Json输出
+    #[tokio::test]
+    async fn test_requests_limit() {
+        let max = Arc::new(AtomicUsize::new(0));
+        let client = AsyncHttpClient::builder()
+            .middleware(Flaky { max_in_flight: max.clone(), ..Default::default() })
+            .build_async()
+            .unwrap();
+        let report = Bench::new(4).requests(100).run(client.get("http://localhost/")).await.unwrap();
+        assert_eq!(max.load(Ordering::SeqCst), 4);
+        assert_eq!((report.requests, report.successes, report.failures, report.bytes), (100, 80, 20, 180));
+        assert_eq!(report.status_codes.get(&200), Some(&80));
+        assert_eq!(report.errors.get("status 503"), Some(&10));
+        assert_eq!(report.errors.get("timeout"), Some(&10));
+        let latency = report.latency;
+        assert!(latency.min >= Duration::from_millis(5) && latency.min <= latency.p50);
+        assert!(latency.p50 <= latency.p90 && latency.p90 <= latency.p99 && latency.p99 <= latency.max);
+        assert!(report.throughput > 0.0);
+
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["status_codes"]["503"], 10);
+        assert!(json["latency"]["p99"].as_f64().unwrap() >= 5.0);
+        assert!(report.to_string().contains("100 requests in"));
+    }
+
+    /// 测试按持续时间压测、同步接口以及构建错误
+    #[test]
+    fn test_duration_limit() {
+        let client = HttpClient::builder().middleware(Flaky::default()).build().unwrap();
+        let report = Bench::new(2).duration(Duration::from_millis(200)).run_blocking(client.get("http://localhost/")).unwrap();
+        assert!(report.requests >= 10, "{}", report);
+        assert!(report.elapsed >= Duration::from_millis(200));
+        assert!(Bench::new(2).run_blocking(client.get("not a url")).is_err());
+    }
+
+    /// 测试分位数计算
+    #[test]
+    fn test_percentile() {
+        let sorted: Vec<Duration> = (1..=10).map(Duration::from_millis).collect();
+        assert_eq!(percentile(&sorted, 50.0), Duration::from_millis(5));
+        assert_eq!(percentile(&sorted, 90.0), Duration::from_millis(9));
+        assert_eq!(percentile(&sorted, 99.0), Duration::from_millis(10));
+        assert_eq!(percentile(&sorted[..1], 50.0), Duration::from_millis(1));
+        assert_eq!(percentile(&[], 99.0), Duration::ZERO);
+    }
+}
@@ -0,0 +1,365 @@
+//! # 熔断器
+//!
+//! 连续失败达到阈值后熔断器打开,在冷却时间内直接拒绝请求;
+//! 冷却结束后进入半开状态放行少量试探请求,成功则关闭,失败则重新打开.
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use crate::networks::http::error::{HttpError, HttpResult};
+use crate::networks::http::middleware::{BoxFuture, Middleware, Next};
+use crate::networks::http::request::Request;
+use crate::networks::http::response::HttpResponse;
+
+/// 熔断器状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CircuitState {
+    /// 正常放行请求
+    #[default]
+    Closed,
+    /// 拒绝所有请求
+    Open,
+    /// 放行少量试探请求
+    HalfOpen,
+}
+
+/// 熔断器统计信息
+#[derive(Debug, Clone, Default)]
+pub struct CircuitStats {
+    // 当前状态
+    pub state: CircuitState,
+    // 连续失败次数
+    pub consecutive_failures: u32,
+    // 累计成功次数
+    pub successes: u64,
+    // 累计失败次数
+    pub failures: u64,
+    // 被拒绝的请求数
+    pub rejected: u64,
+    // 最近一次打开的时间
+    pub opened_at: Option<Instant>,
+}
+
+// 判断一次请求是否失败
+type FailurePredicate = Arc<dyn Fn(&HttpResult<HttpResponse>) -> bool + Send + Sync>;
+// 状态变化回调: 原状态、新状态
+type StateListener = Arc<dyn Fn(CircuitState, CircuitState) + Send + Sync>;
+
+/// 熔断器中间件,克隆后共享同一个状态
+///
+/// # Examples
+/// ```
+/// use std::time::Duration;
+/// use toys::networks::http::AsyncHttpClient;
+/// use toys::networks::http::breaker::{CircuitBreaker, CircuitState};
+/// let breaker = CircuitBreaker::new(5, Duration::from_secs(30))
+///     .on_state_change(|from, to| println!("circuit {:?} -> {:?}", from, to));
+/// let client = AsyncHttpClient::builder().middleware(breaker.clone()).build_async().unwrap();
+/// assert_eq!(breaker.state(), CircuitState::Closed);
+/// ```
+#[derive(Clone)]
+pub struct CircuitBreaker {
+    // 打开熔断器所需的连续失败次数
+    failure_threshold: u32,
+    // 打开后到进入半开状态的冷却时间
+    cool_down: Duration,
+    // 半开状态下同时放行的试探请求数
+    half_open_requests: u32,
+    is_failure: FailurePredicate,
+    listener: Option<StateListener>,
+    state: Arc<Mutex<BreakerState>>,
+}
+
+#[derive(Debug, Default)]
+struct BreakerState {
+    stats: CircuitStats,
+    // 半开状态下正在进行的试探请求数
+    trials: u32,
+}
+
+impl CircuitBreaker {
+    /// 创建连续失败`failure_threshold`次后打开、冷却`cool_down`后尝试恢复的熔断器
+    ///
+    /// 默认将连接失败、超时与5xx响应视为失败
+    pub fn new(failure_threshold: u32, cool_down: Duration) -> Self {
+        CircuitBreaker {
+            failure_threshold: failure_threshold.max(1),
+            cool_down,
+            half_open_requests: 1,
+            is_failure: Arc::new(default_failure),
+            listener: None,
+            state: Arc::new(Mutex::new(BreakerState::default())),
+        }
+    }
+
+    /// 设置半开状态下同时放行的试探请求数
+    pub fn half_open_requests(mut self, requests: u32) -> Self {
+        self.half_open_requests = requests.max(1);
+        self
+    }
+
+    /// 自定义失败判断
+    pub fn failure_when<F>(mut self, predicate: F) -> Self
+        where F: Fn(&HttpResult<HttpResponse>) -> bool + Send + Sync + 'static
+    {
+        self.is_failure = Arc::new(predicate);
+        self
+    }
+
+    /// 设置状态变化回调,可用于监控与告警
+    pub fn on_state_change<F>(mut self, listener: F) -> Self
+        where F: Fn(CircuitState, CircuitState) + Send + Sync + 'static
+    {
+        self.listener = Some(Arc::new(listener));
+        self
+    }
+
+    /// 当前状态,冷却时间结束的打开状态视为半开
+    pub fn state(&self) -> CircuitState {
+        let state = self.state.lock().unwrap();
+        match state.stats.state {
+            CircuitState::Open if self.cooled_down(&state.stats) => CircuitState::HalfOpen,
+            current => current,
+        }
+    }
+
+    /// 统计信息快照
+    pub fn stats(&self) -> CircuitStats {
+        self.state.lock().unwrap().stats.clone()
+    }
+
+    /// 重置为关闭状态并清空统计信息
+    pub fn reset(&self) {
+        let previous = std::mem::take(&mut *self.state.lock().unwrap()).stats.state;
+        self.notify(previous, CircuitState::Closed);
+    }
+
+    // 请求发出前检查是否放行,返回是否占用了半开状态的试探名额
+    fn before(&self) -> HttpResult<bool> {
+        let mut state = self.state.lock().unwrap();
+        let previous = state.stats.state;
+        match previous {
+            CircuitState::Closed => return Ok(false),
+            CircuitState::Open if self.cooled_down(&state.stats) => {
+                state.stats.state = CircuitState::HalfOpen;
+                state.trials = 1;
+            }
+            CircuitState::HalfOpen if state.trials < self.half_open_requests => {
+                state.trials += 1;
+                return Ok(true);
+            }
+            _ => {
+                state.stats.rejected += 1;
+                let elapsed = state.stats.opened_at.map(|at| at.elapsed()).unwrap_or_default();
+                return Err(HttpError::CircuitOpen(self.cool_down.saturating_sub(elapsed)));
+            }
+        }
+        drop(state);
+        self.notify(previous, CircuitState::HalfOpen);
+        Ok(true)
+    }
+
+    // 记录请求结果并更新状态
+    fn after(&self, failed: bool, trial: bool) {
+        let mut state = self.state.lock().unwrap();
+        let previous = state.stats.state;
+        let stats = &mut state.stats;
+        if failed {
+            stats.failures += 1;
+            stats.consecutive_failures += 1;
+            if previous == CircuitState::HalfOpen
+                || (previous == CircuitState::Closed && stats.consecutive_failures >= self.failure_threshold) {
+                stats.state = CircuitState::Open;
+                stats.opened_at = Some(Instant::now());
+            }
+        } else {
+            stats.successes += 1;
+            stats.consecutive_failures = 0;
+            if previous == CircuitState::HalfOpen {
+                stats.state = CircuitState::Closed;
+            }
+        }
+        let current = stats.state;
+        if trial {
+            state.trials = state.trials.saturating_sub(1);
+        }
+        drop(state);
+        self.notify(previous, current);
+    }
+
+    // 检查流式请求是否放行,流式请求的结果不计入统计,也不占用试探名额
+    fn check(&self) -> HttpResult<()> {
+        let mut state = self.state.lock().unwrap();
+        if state.stats.state != CircuitState::Open || self.cooled_down(&state.stats) {
+            return Ok(());
+        }
+        state.stats.rejected += 1;
+        let elapsed = state.stats.opened_at.map(|at| at.elapsed()).unwrap_or_default();
+        Err(HttpError::CircuitOpen(self.cool_down.saturating_sub(elapsed)))
+    }
+
+    // 释放试探名额而不记录结果
+    fn release(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.trials = state.trials.saturating_sub(1);
+    }
+
+    fn cooled_down(&self, stats: &CircuitStats) -> bool {
+        stats.opened_at.is_none_or(|at| at.elapsed() >= self.cool_down)
+    }
+
+    fn notify(&self, from: CircuitState, to: CircuitState) {
+        if from != to {
+            if let Some(listener) = &self.listener {
+                listener(from, to);
+            }
+        }
+    }
+}
+
+// 默认的失败判断: 连接失败、超时、读写错误与5xx响应
+fn default_failure(result: &HttpResult<HttpResponse>) -> bool {
+    match result {
+        Ok(response) => response.status.is_server_error(),
+        Err(HttpError::Request(_) | HttpError::Timeout(_) | HttpError::ReadTimeout(_) | HttpError::Io(_)) => true,
+        Err(error) => error.status().is_some_and(|status| status.is_server_error()),
+    }
+}
+
+// 试探请求的名额,请求在完成前被取消时释放,避免熔断器一直停留在半开状态拒绝请求
+struct TrialGuard<'a> {
+    breaker: &'a CircuitBreaker,
+    active: bool,
+}
+
+impl Drop for TrialGuard<'_> {
+    fn drop(&mut self) {
+        if self.active {
+            self.breaker.release();
+        }
+    }
+}
+
+impl Middleware for CircuitBreaker {
+    fn prepare<'a>(&'a self, request: Request) -> BoxFuture<'a, HttpResult<Request>> {
+        Box::pin(async move {
+            self.check()?;
+            Ok(request)
+        })
+    }
+
+    fn handle<'a>(&'a self, request: Request, next: Next<'a>) -> BoxFuture<'a, HttpResult<HttpResponse>> {
+        Box::pin(async move {
+            let trial = self.before()?;
+            let mut guard = TrialGuard { breaker: self, active: trial };
+            let result = next.run(request).await;
+            guard.active = false;
+            self.after((self.is_failure)(&result), trial);
+            result
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    use crate::networks::http::{AsyncHttpClient, HttpClient, Method};
+    use crate::networks::http::breaker::{CircuitBreaker, CircuitState};
+    use crate::networks::http::error::HttpError;
+    use crate::networks::http::mock::{MockResponse, MockServer};
+    use crate::networks::http::retry::RetryPolicy;
+
+    /// 测试熔断器打开、半开与恢复
+    #[test]
+    fn test_open_and_recover() {
+        let server = MockServer::start();
+        server.mock(Method::GET, "/").times(3).respond(MockResponse::new(500));
+        server.mock(Method::GET, "/").respond(MockResponse::ok());
+        let transitions = Arc::new(Mutex::new(Vec::new()));
+        let recorded = transitions.clone();
+        let breaker = CircuitBreaker::new(2, Duration::from_millis(50))
+            .on_state_change(move |from, to| recorded.lock().unwrap().push((from, to)));
+        let client = HttpClient::builder().middleware(breaker.clone()).build().unwrap();
+        let url = server.url("/");
+
+        client.get(&url).send().unwrap();
+        client.get(&url).send().unwrap();
+        assert_eq!(breaker.state(), CircuitState::Open);
+        assert!(matches!(client.get(&url).send(), Err(HttpError::CircuitOpen(_))));
+        assert_eq!(server.received(Method::GET, "/"), 2);
+
+        // 试探请求失败后重新打开
+        std::thread::sleep(Duration::from_millis(60));
+        assert_eq!(breaker.state(), CircuitState::HalfOpen);
+        client.get(&url).send().unwrap();
+        assert_eq!(breaker.state(), CircuitState::Open);
+
+        std::thread::sleep(Duration::from_millis(60));
+        assert!(client.get(&url).send().unwrap().is_success());
+        assert_eq!(breaker.state(), CircuitState::Closed);
+
+        let stats = breaker.stats();
+        assert_eq!((stats.failures, stats.successes, stats.rejected), (3, 1, 1));
+        use CircuitState::*;
+        assert_eq!(*transitions.lock().unwrap(), vec![
+            (Closed, Open), (Open, HalfOpen), (HalfOpen, Open), (Open, HalfOpen), (HalfOpen, Closed),
+        ]);
+    }
+
+    /// 测试自定义失败判断与重置
+    #[test]
+    fn test_failure_predicate_and_reset() {
+        let server = MockServer::start();
+        server.mock(Method::GET, "/").respond(MockResponse::new(404));
+        let breaker = CircuitBreaker::new(1, Duration::from_secs(60));
+        let client = HttpClient::builder().middleware(breaker.clone()).build().unwrap();
+        client.get(&server.url("/")).send().unwrap();
+        assert_eq!(breaker.state(), CircuitState::Closed);
+
+        let strict = CircuitBreaker::new(1, Duration::from_secs(60))
+            .failure_when(|result| result.as_ref().map_or(true, |response| !response.is_success()));
+        let client = HttpClient::builder().middleware(strict.clone()).build().unwrap();
+        client.get(&server.url("/")).send().unwrap();
+        assert_eq!(strict.state(), CircuitState::Open);
+        strict.reset();
+        assert_eq!(strict.state(), CircuitState::Closed);
+        assert_eq!(strict.stats().failures, 0);
+    }
+
+    /// 测试半开状态下被取消的试探请求会释放名额
+    #[tokio::test]
+    async fn test_cancelled_trial() {
+        let server = MockServer::start();
+        server.mock(Method::GET, "/").times(1).respond(MockResponse::new(500));
+        server.mock(Method::GET, "/").times(1).respond(MockResponse::ok().delay(Duration::from_secs(2)));
+        server.mock(Method::GET, "/").respond(MockResponse::ok());
+        let breaker = CircuitBreaker::new(1, Duration::from_millis(50));
+        let client = AsyncHttpClient::builder().middleware(breaker.clone()).build_async().unwrap();
+        let url = server.url("/");
+
+        client.get(&url).send().await.unwrap();
+        assert_eq!(breaker.state(), CircuitState::Open);
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        let cancelled = tokio::time::timeout(Duration::from_millis(100), client.get(&url).send()).await;
+        assert!(cancelled.is_err());
+        assert_eq!(breaker.state(), CircuitState::HalfOpen);
+        assert!(client.get(&url).send().await.unwrap().is_success());
+        assert_eq!(breaker.state(), CircuitState::Closed);
+    }
+
+    /// 测试每次重试都经过熔断器,打开后不再继续重试
+    #[tokio::test]
+    async fn test_retries_counted() {
+        let server = MockServer::start();
+        server.mock(Method::GET, "/").respond(MockResponse::new(503));
+        let breaker = CircuitBreaker::new(2, Duration::from_secs(60));
+        let client = AsyncHttpClient::builder()
+            .middleware(breaker.clone())
+            .retry(RetryPolicy::new(5).backoff(Duration::from_millis(1), Duration::from_millis(1)))
+            .build_async()
+            .unwrap();
+        assert!(matches!(client.get(&server.url("/")).send().await, Err(HttpError::CircuitOpen(_))));
+        assert_eq!(server.received(Method::GET, "/"), 2);
+        assert_eq!(breaker.state(), CircuitState::Open);
+    }
+}
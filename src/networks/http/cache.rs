@@ -0,0 +1,558 @@
+//! # HTTP响应缓存
+//!
+//! 缓存GET请求的成功响应,遵循`Cache-Control`与`Expires`计算有效期,
+//! 过期后通过`If-None-Match`/`If-Modified-Since`向服务端确认,收到304时继续使用缓存.
+//! 对于不返回缓存响应头的接口,可以指定默认或强制的缓存时间.
+//! 响应带有`Vary`时按对应请求头的值分别缓存;携带`Authorization`的请求只缓存`Cache-Control: public`的响应.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use bytes::Bytes;
+use reqwest::header::{AGE, AUTHORIZATION, CACHE_CONTROL, DATE, ETAG, EXPIRES, HeaderMap, HeaderName, HeaderValue,
+                      IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED, VARY};
+use reqwest::{Method, StatusCode, Url, Version};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use crate::data::json::{from_json_str, to_json_str};
+use crate::networks::http::error::HttpResult;
+use crate::networks::http::middleware::{BoxFuture, Middleware, Next};
+use crate::networks::http::request::Request;
+use crate::networks::http::response::HttpResponse;
+
+/// 标记响应来源的响应头: `HIT`表示直接使用缓存,`REVALIDATED`表示经服务端确认后使用缓存,`MISS`表示来自服务端
+pub const CACHE_STATUS_HEADER: &str = "x-toys-cache";
+
+/// 缓存条目
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheEntry {
+    // 响应状态码
+    pub status: u16,
+    // 响应头
+    pub headers: Vec<(String, String)>,
+    // 响应地址
+    pub url: String,
+    // 过期时间,Unix时间戳毫秒
+    pub expires_at: u64,
+    // 响应`Vary`中的请求头名称(小写),不为空时该条目只记录名称,响应保存在包含请求头值的键下
+    #[serde(default)]
+    pub vary: Vec<String>,
+    // 响应体,磁盘存储时单独保存
+    #[serde(skip)]
+    pub body: Bytes,
+}
+
+impl CacheEntry {
+    /// 是否仍在有效期内
+    pub fn is_fresh(&self) -> bool {
+        now_millis() < self.expires_at
+    }
+
+    /// 获取一个响应头的值
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    // 从响应创建缓存条目
+    fn from_response(response: &HttpResponse, ttl: Duration) -> Self {
+        CacheEntry {
+            status: response.status.as_u16(),
+            headers: response.headers.iter()
+                .filter(|(name, _)| name.as_str() != CACHE_STATUS_HEADER)
+                .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+                .collect(),
+            url: response.url.to_string(),
+            expires_at: now_millis().saturating_add(ttl.as_millis() as u64),
+            vary: Vec::new(),
+            body: response.body.clone(),
+        }
+    }
+
+    // 转换为响应
+    fn to_response(&self, cache_status: &'static str) -> Option<HttpResponse> {
+        let mut headers = HeaderMap::new();
+        for (name, value) in &self.headers {
+            headers.append(HeaderName::from_bytes(name.as_bytes()).ok()?, HeaderValue::from_str(value).ok()?);
+        }
+        headers.insert(CACHE_STATUS_HEADER, HeaderValue::from_static(cache_status));
+        Some(HttpResponse {
+            status: StatusCode::from_u16(self.status).ok()?,
+            headers,
+            url: Url::parse(&self.url).ok()?,
+            version: Version::HTTP_11,
+            elapsed: Duration::ZERO,
+            body: self.body.clone(),
+        })
+    }
+
+    // 使用304响应中的响应头更新条目
+    fn refresh(&mut self, headers: &HeaderMap, ttl: Duration) {
+        for (name, value) in headers {
+            if let Ok(value) = value.to_str() {
+                self.headers.retain(|(key, _)| !key.eq_ignore_ascii_case(name.as_str()));
+                self.headers.push((name.to_string(), value.to_string()));
+            }
+        }
+        self.expires_at = now_millis().saturating_add(ttl.as_millis() as u64);
+    }
+}
+
+/// 缓存存储
+pub trait CacheStore: Send + Sync + 'static {
+    /// 读取缓存条目
+    fn get(&self, key: &str) -> Option<CacheEntry>;
+
+    /// 写入缓存条目
+    fn put(&self, key: &str, entry: CacheEntry);
+
+    /// 删除缓存条目
+    fn remove(&self, key: &str);
+
+    /// 清空缓存
+    fn clear(&self);
+}
+
+/// 内存缓存,条目数超过上限时淘汰最早过期的条目
+#[derive(Debug)]
+pub struct MemoryStore {
+    max_entries: usize,
+    entries: Mutex<HashMap<String, CacheEntry>>,
+}
+
+impl Default for MemoryStore {
+    fn default() -> Self {
+        MemoryStore::new(1024)
+    }
+}
+
+impl MemoryStore {
+    /// 创建最多保存`max_entries`个条目的内存缓存
+    pub fn new(max_entries: usize) -> Self {
+        MemoryStore { max_entries: max_entries.max(1), entries: Mutex::new(HashMap::new()) }
+    }
+
+    /// 当前条目数
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    /// 是否为空
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl CacheStore for MemoryStore {
+    fn get(&self, key: &str) -> Option<CacheEntry> {
+        self.entries.lock().unwrap().get(key).cloned()
+    }
+
+    fn put(&self, key: &str, entry: CacheEntry) {
+        let mut entries = self.entries.lock().unwrap();
+        if !entries.contains_key(key) && entries.len() >= self.max_entries {
+            let oldest = entries.iter().min_by_key(|(_, entry)| entry.expires_at).map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                entries.remove(&oldest);
+            }
+        }
+        entries.insert(key.to_string(), entry);
+    }
+
+    fn remove(&self, key: &str) {
+        self.entries.lock().unwrap().remove(key);
+    }
+
+    fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+}
+
+/// 磁盘缓存,每个条目保存为`<摘要>.json`元数据与`<摘要>.body`响应体两个文件
+#[derive(Debug, Clone)]
+pub struct DiskStore {
+    dir: PathBuf,
+}
+
+impl DiskStore {
+    /// 使用指定目录保存缓存,目录不存在时自动创建
+    pub fn new<P: AsRef<Path>>(dir: P) -> std::io::Result<Self> {
+        std::fs::create_dir_all(dir.as_ref())?;
+        Ok(DiskStore { dir: dir.as_ref().to_path_buf() })
+    }
+
+    // 条目的文件路径,不含扩展名
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(hex::encode(Sha256::digest(key.as_bytes())))
+    }
+}
+
+impl CacheStore for DiskStore {
+    fn get(&self, key: &str) -> Option<CacheEntry> {
+        let path = self.path(key);
+        let json = std::fs::read_to_string(path.with_extension("json")).ok()?;
+        let mut entry: CacheEntry = from_json_str(&json).ok()?;
+        entry.body = Bytes::from(std::fs::read(path.with_extension("body")).ok()?);
+        Some(entry)
+    }
+
+    fn put(&self, key: &str, entry: CacheEntry) {
+        let path = self.path(key);
+        // 先写响应体,避免读到只有元数据的条目
+        if std::fs::write(path.with_extension("body"), &entry.body).is_err() {
+            return;
+        }
+        if let Ok(json) = to_json_str(&entry) {
+            let _ = std::fs::write(path.with_extension("json"), json);
+        }
+    }
+
+    fn remove(&self, key: &str) {
+        let path = self.path(key);
+        let _ = std::fs::remove_file(path.with_extension("json"));
+        let _ = std::fs::remove_file(path.with_extension("body"));
+    }
+
+    fn clear(&self) {
+        let Ok(entries) = std::fs::read_dir(&self.dir) else { return; };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if matches!(path.extension().and_then(|ext| ext.to_str()), Some("json" | "body")) {
+                let _ = std::fs::remove_file(path);
+            }
+        }
+    }
+}
+
+/// 响应缓存中间件
+///
+/// # Examples
+/// ```
+/// use std::time::Duration;
+/// use toys::networks::http::HttpClient;
+/// use toys::networks::http::cache::CacheMiddleware;
+/// let client = HttpClient::builder()
+///     .middleware(CacheMiddleware::memory().default_ttl(Duration::from_secs(60)))
+///     .build()
+///     .unwrap();
+/// ```
+#[derive(Clone)]
+pub struct CacheMiddleware {
+    store: Arc<dyn CacheStore>,
+    // 响应没有缓存响应头时使用的缓存时间
+    default_ttl: Option<Duration>,
+    // 忽略缓存响应头,强制使用的缓存时间
+    force_ttl: Option<Duration>,
+}
+
+impl CacheMiddleware {
+    /// 使用指定的存储创建缓存中间件
+    pub fn new<S: CacheStore>(store: S) -> Self {
+        CacheMiddleware { store: Arc::new(store), default_ttl: None, force_ttl: None }
+    }
+
+    /// 使用默认容量的内存缓存
+    pub fn memory() -> Self {
+        CacheMiddleware::new(MemoryStore::default())
+    }
+
+    /// 使用磁盘缓存
+    pub fn disk<P: AsRef<Path>>(dir: P) -> std::io::Result<Self> {
+        Ok(CacheMiddleware::new(DiskStore::new(dir)?))
+    }
+
+    /// 设置响应没有`Cache-Control`与`Expires`时的缓存时间
+    pub fn default_ttl(mut self, ttl: Duration) -> Self {
+        self.default_ttl = Some(ttl);
+        self
+    }
+
+    /// 忽略响应的缓存响应头,强制缓存`ttl`时间
+    pub fn force_ttl(mut self, ttl: Duration) -> Self {
+        self.force_ttl = Some(ttl);
+        self
+    }
+
+    /// 获取缓存存储
+    pub fn store(&self) -> &Arc<dyn CacheStore> {
+        &self.store
+    }
+
+    // 计算响应的缓存时间,返回None时不缓存
+    fn ttl(&self, headers: &HeaderMap) -> Option<Duration> {
+        if let Some(ttl) = self.force_ttl {
+            return Some(ttl);
+        }
+        let directives = cache_control(headers);
+        if directives.contains_key("no-store") {
+            return None;
+        }
+        if directives.contains_key("no-cache") {
+            return Some(Duration::ZERO);
+        }
+        let age = header_str(headers, AGE.as_str()).and_then(|age| age.trim().parse::<u64>().ok()).unwrap_or(0);
+        if let Some(max_age) = directives.get("max-age").and_then(|value| value.parse::<u64>().ok()) {
+            return Some(Duration::from_secs(max_age.saturating_sub(age)));
+        }
+        if let Some(expires) = header_str(headers, EXPIRES.as_str()) {
+            // 非法的Expires视为已过期
+            let Ok(expires) = httpdate::parse_http_date(expires) else { return Some(Duration::ZERO); };
+            let date = header_str(headers, DATE.as_str())
+                .and_then(|date| httpdate::parse_http_date(date).ok())
+                .unwrap_or_else(SystemTime::now);
+            return Some(expires.duration_since(date).unwrap_or(Duration::ZERO));
+        }
+        if self.default_ttl.is_some() {
+            return self.default_ttl;
+        }
+        // 没有有效期但可以协商时缓存,每次使用前向服务端确认
+        if headers.contains_key(ETAG) || headers.contains_key(LAST_MODIFIED) {
+            return Some(Duration::ZERO);
+        }
+        None
+    }
+
+    // 存储可缓存的响应,`key`为不含`Vary`请求头的基础键
+    fn store_response(&self, key: &str, request_headers: &HeaderMap, response: &HttpResponse) {
+        if response.status != StatusCode::OK {
+            return;
+        }
+        // 携带凭证的请求只缓存明确声明为public的响应,避免不同用户共享缓存
+        if request_headers.contains_key(AUTHORIZATION) && !cache_control(&response.headers).contains_key("public") {
+            return;
+        }
+        let vary = vary_names(&response.headers);
+        let ttl = match self.ttl(&response.headers) {
+            Some(ttl) if !vary.iter().any(|name| name == "*") => ttl,
+            _ => return self.store.remove(key),
+        };
+        let entry = CacheEntry::from_response(response, ttl);
+        if vary.is_empty() {
+            return self.store.put(key, entry);
+        }
+        // 基础键下只记录Vary的请求头名称,响应保存在包含请求头值的键下
+        let index = CacheEntry { vary: vary.clone(), body: Bytes::new(), ..entry.clone() };
+        self.store.put(&vary_key(key, &vary, request_headers), entry);
+        self.store.put(key, index);
+    }
+
+    // 查找请求对应的缓存键与缓存条目
+    fn lookup(&self, key: String, request_headers: &HeaderMap) -> (String, Option<CacheEntry>) {
+        match self.store.get(&key) {
+            Some(index) if !index.vary.is_empty() => {
+                let key = vary_key(&key, &index.vary, request_headers);
+                let cached = self.store.get(&key);
+                (key, cached)
+            }
+            cached => (key, cached),
+        }
+    }
+}
+
+impl Middleware for CacheMiddleware {
+    fn handle<'a>(&'a self, mut request: Request, next: Next<'a>) -> BoxFuture<'a, HttpResult<HttpResponse>> {
+        Box::pin(async move {
+            let request_directives = cache_control(&request.headers);
+            if request.method != Method::GET || request_directives.contains_key("no-store") {
+                return next.run(request).await;
+            }
+            let base = format!("{} {}", request.method, request.url);
+            let request_headers = request.headers.clone();
+            let (key, cached) = self.lookup(base.clone(), &request_headers);
+            if let Some(entry) = &cached {
+                if entry.is_fresh() && !request_directives.contains_key("no-cache") {
+                    if let Some(response) = entry.to_response("HIT") {
+                        return Ok(response);
+                    }
+                }
+                // 添加协商缓存请求头,调用方已经指定时保留调用方的值
+                if let Some(etag) = entry.header(ETAG.as_str()).and_then(|value| HeaderValue::from_str(value).ok()) {
+                    request.headers.entry(IF_NONE_MATCH).or_insert(etag);
+                }
+                if let Some(modified) = entry.header(LAST_MODIFIED.as_str()).and_then(|value| HeaderValue::from_str(value).ok()) {
+                    request.headers.entry(IF_MODIFIED_SINCE).or_insert(modified);
+                }
+            }
+
+            let mut response = next.run(request).await?;
+            if response.status == StatusCode::NOT_MODIFIED {
+                if let Some(mut entry) = cached {
+                    let ttl = self.ttl(&response.headers)
+                        .or_else(|| self.ttl(&response_headers(&entry)))
+                        .unwrap_or(Duration::ZERO);
+                    entry.refresh(&response.headers, ttl);
+                    if let Some(revalidated) = entry.to_response("REVALIDATED") {
+                        self.store.put(&key, entry);
+                        return Ok(revalidated);
+                    }
+                }
+                return Ok(response);
+            }
+            self.store_response(&base, &request_headers, &response);
+            response.headers.insert(CACHE_STATUS_HEADER, HeaderValue::from_static("MISS"));
+            Ok(response)
+        })
+    }
+}
+
+// 解析Cache-Control,指令名统一为小写
+fn cache_control(headers: &HeaderMap) -> HashMap<String, String> {
+    headers.get_all(CACHE_CONTROL).iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|directive| {
+            let mut kv = directive.trim().splitn(2, '=');
+            let name = kv.next()?.trim().to_ascii_lowercase();
+            if name.is_empty() {
+                return None;
+            }
+            Some((name, kv.next().unwrap_or("").trim().trim_matches('"').to_string()))
+        })
+        .collect()
+}
+
+// 解析Vary响应头,名称统一为小写
+fn vary_names(headers: &HeaderMap) -> Vec<String> {
+    let mut names: Vec<String> = headers.get_all(VARY).iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|name| name.trim().to_ascii_lowercase())
+        .filter(|name| !name.is_empty())
+        .collect();
+    names.sort();
+    names.dedup();
+    names
+}
+
+// 在基础键后拼接Vary请求头的值
+fn vary_key(key: &str, names: &[String], headers: &HeaderMap) -> String {
+    let mut key = key.to_string();
+    for name in names {
+        let values: Vec<&str> = headers.get_all(name.as_str()).iter().filter_map(|value| value.to_str().ok()).collect();
+        key.push_str(&format!("\n{}: {}", name, values.join(",")));
+    }
+    key
+}
+
+// 缓存条目中的响应头
+fn response_headers(entry: &CacheEntry) -> HeaderMap {
+    entry.to_response("HIT").map(|response| response.headers).unwrap_or_default()
+}
+
+fn header_str<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers.get(name).and_then(|value| value.to_str().ok())
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use reqwest::header::HeaderMap;
+    use reqwest::StatusCode;
+    use crate::networks::http::{HttpClient, Method};
+    use crate::networks::http::cache::{CACHE_STATUS_HEADER, CacheMiddleware, CacheStore, DiskStore};
+    use crate::networks::http::mock::{MockResponse, MockServer};
+
+    /// 测试有效期计算
+    #[test]
+    fn test_ttl() {
+        let cache = CacheMiddleware::memory();
+        let headers = |pairs: &[(&'static str, &'static str)]| {
+            pairs.iter().map(|(k, v)| (k.parse().unwrap(), v.parse().unwrap())).collect::<HeaderMap>()
+        };
+        assert_eq!(cache.ttl(&headers(&[("cache-control", "public, max-age=60"), ("age", "10")])), Some(Duration::from_secs(50)));
+        assert_eq!(cache.ttl(&headers(&[("cache-control", "no-store, max-age=60")])), None);
+        assert_eq!(cache.ttl(&headers(&[("cache-control", "no-cache")])), Some(Duration::ZERO));
+        assert_eq!(cache.ttl(&headers(&[
+            ("date", "Wed, 21 Oct 2015 07:28:00 GMT"),
+            ("expires", "Wed, 21 Oct 2015 07:38:00 GMT"),
+        ])), Some(Duration::from_secs(600)));
+        assert_eq!(cache.ttl(&headers(&[("etag", "\"v1\"")])), Some(Duration::ZERO));
+        assert_eq!(cache.ttl(&HeaderMap::new()), None);
+        let cache = cache.default_ttl(Duration::from_secs(5));
+        assert_eq!(cache.ttl(&HeaderMap::new()), Some(Duration::from_secs(5)));
+        let cache = cache.force_ttl(Duration::from_secs(7));
+        assert_eq!(cache.ttl(&headers(&[("cache-control", "no-store")])), Some(Duration::from_secs(7)));
+    }
+
+    /// 测试有效期内直接使用缓存,过期后通过ETag协商
+    #[test]
+    fn test_fresh_and_revalidate() {
+        let server = MockServer::start();
+        server.mock(Method::GET, "/fresh").respond(MockResponse::text("fresh").header("Cache-Control", "max-age=60"));
+        server.mock(Method::GET, "/etag").header("if-none-match", "\"v1\"").respond(MockResponse::new(304));
+        server.mock(Method::GET, "/etag").respond(MockResponse::text("etag").header("ETag", "\"v1\"").header("Cache-Control", "no-cache"));
+        let client = HttpClient::builder().middleware(CacheMiddleware::memory()).build().unwrap();
+
+        let first = client.get(&server.url("/fresh")).send().unwrap();
+        let second = client.get(&server.url("/fresh")).send().unwrap();
+        assert_eq!(first.header(CACHE_STATUS_HEADER), Some("MISS"));
+        assert_eq!(second.header(CACHE_STATUS_HEADER), Some("HIT"));
+        assert_eq!(second.text(), "fresh");
+        assert_eq!(server.received(Method::GET, "/fresh"), 1);
+
+        client.get(&server.url("/etag")).send().unwrap();
+        let revalidated = client.get(&server.url("/etag")).send().unwrap();
+        assert_eq!(revalidated.status, StatusCode::OK);
+        assert_eq!(revalidated.header(CACHE_STATUS_HEADER), Some("REVALIDATED"));
+        assert_eq!(revalidated.text(), "etag");
+        assert_eq!(server.received(Method::GET, "/etag"), 2);
+    }
+
+    /// 测试按Vary请求头分别缓存,携带Authorization的请求只缓存public响应
+    #[test]
+    fn test_vary_and_authorization() {
+        let server = MockServer::start();
+        server.mock(Method::GET, "/lang").respond_with(|request| {
+            MockResponse::text(request.header("accept-language").unwrap_or(""))
+                .header("Cache-Control", "max-age=60")
+                .header("Vary", "Accept-Language")
+        });
+        server.mock(Method::GET, "/private").respond(MockResponse::text("private").header("Cache-Control", "max-age=60"));
+        server.mock(Method::GET, "/public").respond(MockResponse::text("public").header("Cache-Control", "public, max-age=60"));
+        let client = HttpClient::builder().middleware(CacheMiddleware::memory()).build().unwrap();
+
+        let get = |path: &str, name: &str, value: &str| client.get(&server.url(path)).header(name, value).send().unwrap();
+        assert_eq!(get("/lang", "Accept-Language", "zh").text(), "zh");
+        assert_eq!(get("/lang", "Accept-Language", "en").text(), "en");
+        let cached = get("/lang", "Accept-Language", "zh");
+        assert_eq!((cached.text(), cached.header(CACHE_STATUS_HEADER)), ("zh".to_string(), Some("HIT")));
+        assert_eq!(server.received(Method::GET, "/lang"), 2);
+
+        for _ in 0..2 {
+            get("/private", "Authorization", "Bearer a");
+            get("/public", "Authorization", "Bearer a");
+        }
+        assert_eq!(server.received(Method::GET, "/private"), 2);
+        assert_eq!(server.received(Method::GET, "/public"), 1);
+    }
+
+    /// 测试磁盘缓存与强制缓存时间
+    #[test]
+    fn test_disk_store() {
+        let server = MockServer::start();
+        server.mock(Method::GET, "/ip").respond(MockResponse::text("body 0"));
+        let dir = std::env::temp_dir().join("toys_cache_test");
+        let store = DiskStore::new(&dir).unwrap();
+        store.clear();
+        let cache = CacheMiddleware::new(store.clone()).default_ttl(Duration::from_secs(60));
+        let client = HttpClient::builder().middleware(cache).build().unwrap();
+        client.get(&server.url("/ip")).send().unwrap();
+
+        // 新客户端从磁盘读取缓存
+        let client = HttpClient::builder()
+            .middleware(CacheMiddleware::disk(&dir).unwrap().default_ttl(Duration::from_secs(60)))
+            .build()
+            .unwrap();
+        assert_eq!(client.get(&server.url("/ip")).send().unwrap().text(), "body 0");
+        assert_eq!(server.received(Method::GET, "/ip"), 1);
+        assert!(store.get(&format!("GET {}", server.url("/ip"))).is_some());
+        store.clear();
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
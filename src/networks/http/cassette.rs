@@ -0,0 +1,409 @@
+//! # 请求录制与回放
+//!
+//! 录制模式下将请求与响应保存到录制文件(cassette),回放模式下直接返回录制的响应而不访问网络,
+//! 用于编写不依赖外部服务的测试.请求按方法、地址(忽略Query参数顺序)以及可选的请求体匹配,
+//! 录制时可以隐藏请求头、Query参数与请求体中的密钥.
+
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use bytes::Bytes;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::{StatusCode, Url, Version};
+use serde::{Deserialize, Serialize};
+use crate::data::json::{from_json_str, to_json_pretty};
+use crate::networks::http::error::{HttpError, HttpResult};
+use crate::networks::http::middleware::{BoxFuture, Middleware, Next};
+use crate::networks::http::request::Request;
+use crate::networks::http::response::HttpResponse;
+
+/// 指定录制模式的环境变量,取值为`record`、`replay`或`once`
+pub const CASSETTE_MODE_ENV: &str = "TOYS_CASSETTE";
+
+// 隐藏密钥后的占位文本
+const REDACTED: &str = "[REDACTED]";
+
+/// 录制模式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CassetteMode {
+    /// 发送真实请求并覆盖录制文件
+    Record,
+    /// 只使用录制文件中的响应,没有匹配的记录时返回错误
+    Replay,
+    /// 录制文件存在时回放,不存在时录制
+    Once,
+}
+
+/// 一次录制的请求与响应
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Interaction {
+    pub request: RecordedRequest,
+    pub response: RecordedResponse,
+}
+
+/// 录制的请求
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedRequest {
+    pub method: String,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: RecordedBody,
+}
+
+/// 录制的响应
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: RecordedBody,
+}
+
+/// 录制的消息体,文本直接保存,二进制数据以十六进制保存
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum RecordedBody {
+    Text(String),
+    Binary { hex: String },
+}
+
+impl RecordedBody {
+    fn new(bytes: &[u8]) -> Self {
+        match std::str::from_utf8(bytes) {
+            Ok(text) => RecordedBody::Text(text.to_string()),
+            Err(_) => RecordedBody::Binary { hex: hex::encode(bytes) },
+        }
+    }
+
+    fn to_bytes(&self) -> Bytes {
+        match self {
+            RecordedBody::Text(text) => Bytes::from(text.clone()),
+            RecordedBody::Binary { hex } => Bytes::from(hex::decode(hex).unwrap_or_default()),
+        }
+    }
+}
+
+// 录制文件内容
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CassetteFile {
+    interactions: Vec<Interaction>,
+}
+
+#[derive(Debug, Default)]
+struct CassetteState {
+    interactions: Vec<Interaction>,
+    // 每条记录是否已经回放过,相同的请求按录制顺序依次回放
+    played: Vec<bool>,
+    // 是否在回放
+    replaying: bool,
+}
+
+/// 录制与回放中间件,克隆后共享同一份记录
+///
+/// # Examples
+/// ```no_run
+/// use toys::networks::http::HttpClient;
+/// use toys::networks::http::cassette::Cassette;
+/// // 默认回放,设置环境变量TOYS_CASSETTE=record后重新录制
+/// let cassette = Cassette::from_env("tests/cassettes/users.json").unwrap()
+///     .redact_header("x-api-key")
+///     .redact_query("token");
+/// let client = HttpClient::builder().middleware(cassette).build().unwrap();
+/// let users = client.get("https://api.example.com/users?token=secret").send().unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct Cassette {
+    path: PathBuf,
+    // 是否比较请求体
+    match_body: bool,
+    // 需要隐藏的请求头与响应头,名称为小写
+    redact_headers: Vec<String>,
+    // 需要隐藏的Query参数
+    redact_query: Vec<String>,
+    // 需要从请求体、响应体中隐藏的文本
+    redact_text: Vec<String>,
+    state: Arc<Mutex<CassetteState>>,
+}
+
+impl Cassette {
+    /// 以指定模式打开录制文件,回放模式下文件不存在时返回错误
+    pub fn new<P: AsRef<Path>>(path: P, mode: CassetteMode) -> HttpResult<Self> {
+        let path = path.as_ref().to_path_buf();
+        let replaying = match mode {
+            CassetteMode::Record => false,
+            CassetteMode::Replay => true,
+            CassetteMode::Once => path.exists(),
+        };
+        let mut state = CassetteState { replaying, ..CassetteState::default() };
+        if replaying {
+            let json = std::fs::read_to_string(&path)
+                .map_err(|e| HttpError::Cassette(format!("failed to read {:?}: {}", path, e)))?;
+            let file: CassetteFile = from_json_str(&json)
+                .map_err(|e| HttpError::Cassette(format!("invalid cassette {:?}: {}", path, e)))?;
+            state.played = vec![false; file.interactions.len()];
+            state.interactions = file.interactions;
+        }
+        Ok(Cassette {
+            path,
+            match_body: false,
+            redact_headers: ["authorization", "proxy-authorization", "cookie", "set-cookie", "x-api-key"]
+                .iter().map(|name| name.to_string()).collect(),
+            redact_query: Vec::new(),
+            redact_text: Vec::new(),
+            state: Arc::new(Mutex::new(state)),
+        })
+    }
+
+    /// 回放录制文件
+    pub fn replay<P: AsRef<Path>>(path: P) -> HttpResult<Self> {
+        Cassette::new(path, CassetteMode::Replay)
+    }
+
+    /// 录制到文件,覆盖已有的记录
+    pub fn record<P: AsRef<Path>>(path: P) -> HttpResult<Self> {
+        Cassette::new(path, CassetteMode::Record)
+    }
+
+    /// 根据环境变量[`CASSETTE_MODE_ENV`]选择模式,未设置时回放
+    pub fn from_env<P: AsRef<Path>>(path: P) -> HttpResult<Self> {
+        let mode = match std::env::var(CASSETTE_MODE_ENV).unwrap_or_default().to_ascii_lowercase().as_str() {
+            "record" => CassetteMode::Record,
+            "once" => CassetteMode::Once,
+            _ => CassetteMode::Replay,
+        };
+        Cassette::new(path, mode)
+    }
+
+    /// 设置匹配记录时是否比较请求体,默认只比较请求方法与地址
+    pub fn match_body(mut self, enable: bool) -> Self {
+        self.match_body = enable;
+        self
+    }
+
+    /// 录制时隐藏请求头与响应头,默认隐藏Authorization、Cookie、Set-Cookie与X-Api-Key
+    pub fn redact_header(mut self, name: &str) -> Self {
+        self.redact_headers.push(name.to_ascii_lowercase());
+        self
+    }
+
+    /// 录制时隐藏Query参数的值,匹配时同样忽略该参数的值
+    pub fn redact_query(mut self, name: &str) -> Self {
+        self.redact_query.push(name.to_string());
+        self
+    }
+
+    /// 录制时将请求体与响应体中出现的`secret`替换为占位文本
+    pub fn redact_text<S: Into<String>>(mut self, secret: S) -> Self {
+        let secret = secret.into();
+        if !secret.is_empty() {
+            self.redact_text.push(secret);
+        }
+        self
+    }
+
+    /// 是否处于回放状态
+    pub fn is_replaying(&self) -> bool {
+        self.state.lock().unwrap().replaying
+    }
+
+    /// 已有的记录
+    pub fn interactions(&self) -> Vec<Interaction> {
+        self.state.lock().unwrap().interactions.clone()
+    }
+
+    // 查找与请求匹配的记录,优先使用未回放过的记录
+    fn find(&self, request: &RecordedRequest) -> HttpResult<RecordedResponse> {
+        let mut state = self.state.lock().unwrap();
+        let matched: Vec<usize> = state.interactions.iter().enumerate()
+            .filter(|(_, interaction)| self.matches(&interaction.request, request))
+            .map(|(index, _)| index)
+            .collect();
+        let index = matched.iter().copied().find(|index| !state.played[*index])
+            .or_else(|| matched.last().copied())
+            .ok_or_else(|| HttpError::Cassette(format!(
+                "no recorded interaction for {} {} in {:?}", request.method, request.url, self.path)))?;
+        state.played[index] = true;
+        Ok(state.interactions[index].response.clone())
+    }
+
+    fn matches(&self, recorded: &RecordedRequest, request: &RecordedRequest) -> bool {
+        recorded.method.eq_ignore_ascii_case(&request.method)
+            && normalize_url(&recorded.url) == normalize_url(&request.url)
+            && (!self.match_body || recorded.body == request.body)
+    }
+
+    // 保存记录并写入文件
+    fn save(&self, interaction: Interaction) -> HttpResult<()> {
+        let mut state = self.state.lock().unwrap();
+        state.interactions.push(interaction);
+        state.played.push(true);
+        let file = CassetteFile { interactions: state.interactions.clone() };
+        let json = to_json_pretty(&file).map_err(|e| HttpError::Cassette(e.to_string()))?;
+        if let Some(dir) = self.path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(&self.path, json)?;
+        Ok(())
+    }
+
+    fn record_request(&self, request: &Request) -> RecordedRequest {
+        let mut url = request.url.clone();
+        if !self.redact_query.is_empty() && url.query().is_some() {
+            let pairs: Vec<(String, String)> = url.query_pairs()
+                .map(|(key, value)| {
+                    let value = if self.redact_query.iter().any(|name| *name == key) { REDACTED.into() } else { value.into_owned() };
+                    (key.into_owned(), value)
+                })
+                .collect();
+            url.query_pairs_mut().clear().extend_pairs(pairs);
+        }
+        RecordedRequest {
+            method: request.method.to_string(),
+            url: url.to_string(),
+            headers: self.record_headers(&request.headers),
+            body: self.record_body(request.body.as_bytes().unwrap_or_default()),
+        }
+    }
+
+    fn record_headers(&self, headers: &HeaderMap) -> Vec<(String, String)> {
+        headers.iter()
+            .filter_map(|(name, value)| {
+                let value = if self.redact_headers.iter().any(|redacted| redacted == name.as_str()) {
+                    REDACTED.to_string()
+                } else {
+                    value.to_str().ok()?.to_string()
+                };
+                Some((name.to_string(), value))
+            })
+            .collect()
+    }
+
+    fn record_body(&self, bytes: &[u8]) -> RecordedBody {
+        match RecordedBody::new(bytes) {
+            RecordedBody::Text(mut text) => {
+                for secret in &self.redact_text {
+                    text = text.replace(secret.as_str(), REDACTED);
+                }
+                RecordedBody::Text(text)
+            }
+            binary => binary,
+        }
+    }
+}
+
+impl Middleware for Cassette {
+    fn handle<'a>(&'a self, request: Request, next: Next<'a>) -> BoxFuture<'a, HttpResult<HttpResponse>> {
+        Box::pin(async move {
+            let recorded = self.record_request(&request);
+            if self.is_replaying() {
+                let response = self.find(&recorded)?;
+                return to_response(&response, request.url);
+            }
+            let response = next.run(request).await?;
+            self.save(Interaction {
+                request: recorded,
+                response: RecordedResponse {
+                    status: response.status.as_u16(),
+                    headers: self.record_headers(&response.headers),
+                    body: self.record_body(&response.body),
+                },
+            })?;
+            Ok(response)
+        })
+    }
+}
+
+// 将录制的响应转换为响应
+fn to_response(recorded: &RecordedResponse, url: Url) -> HttpResult<HttpResponse> {
+    let invalid = |what: &str| HttpError::Cassette(format!("invalid recorded {}", what));
+    let mut headers = HeaderMap::new();
+    for (name, value) in &recorded.headers {
+        headers.append(
+            HeaderName::from_bytes(name.as_bytes()).map_err(|_| invalid("header name"))?,
+            HeaderValue::from_str(value).map_err(|_| invalid("header value"))?,
+        );
+    }
+    Ok(HttpResponse {
+        status: StatusCode::from_u16(recorded.status).map_err(|_| invalid("status"))?,
+        headers,
+        url,
+        version: Version::HTTP_11,
+        elapsed: Duration::ZERO,
+        body: recorded.body.to_bytes(),
+    })
+}
+
+// 按参数名排序Query参数,使参数顺序不影响匹配
+fn normalize_url(url: &str) -> String {
+    let Ok(mut url) = Url::parse(url) else { return url.to_string(); };
+    if url.query().is_some() {
+        let mut pairs: Vec<(String, String)> = url.query_pairs().into_owned().collect();
+        pairs.sort();
+        url.query_pairs_mut().clear().extend_pairs(pairs);
+    }
+    url.set_fragment(None);
+    url.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::networks::http::{HttpClient, Method};
+    use crate::networks::http::cassette::{Cassette, CassetteMode, RecordedBody};
+    use crate::networks::http::error::HttpError;
+    use crate::networks::http::mock::{MockResponse, MockServer};
+
+    /// 测试录制后回放,回放时不访问网络
+    #[test]
+    fn test_record_and_replay() {
+        let server = MockServer::start();
+        for hit in 0..2 {
+            server.mock(Method::POST, "/echo").times(1).respond_with(move |request| {
+                MockResponse::ok().header("Set-Cookie", "session=abc").body(format!("{} {}", hit, request.text()))
+            });
+        }
+        let path = std::env::temp_dir().join("toys_cassette_test.json");
+        let cassette = Cassette::new(&path, CassetteMode::Record).unwrap()
+            .match_body(true)
+            .redact_query("token")
+            .redact_text("s3cret");
+        let client = HttpClient::builder().middleware(cassette.clone()).build().unwrap();
+        client.post(&server.url("/echo?b=2&token=abc&a=1")).body("password=s3cret").send().unwrap();
+        client.post(&server.url("/echo?token=abc")).body("other").send().unwrap();
+
+        let recorded = cassette.interactions();
+        assert_eq!(recorded[0].request.url, server.url("/echo?b=2&token=%5BREDACTED%5D&a=1"));
+        assert_eq!(recorded[0].request.body, RecordedBody::Text("password=[REDACTED]".into()));
+        assert_eq!(recorded[0].response.headers[0], ("set-cookie".to_string(), "[REDACTED]".to_string()));
+
+        let cassette = Cassette::replay(&path).unwrap().match_body(true).redact_query("token").redact_text("s3cret");
+        let client = HttpClient::builder().middleware(cassette).build().unwrap();
+        let response = client.post(&server.url("/echo?a=1&token=xyz&b=2")).body("password=s3cret").send().unwrap();
+        assert_eq!(response.text(), "0 password=[REDACTED]");
+        assert_eq!(client.post(&server.url("/echo?token=1")).body("other").send().unwrap().text(), "1 other");
+        assert!(matches!(client.post(&server.url("/echo")).body("missing").send(), Err(HttpError::Cassette(_))));
+        assert_eq!(server.received(Method::POST, "/echo"), 2);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    /// 测试相同请求按录制顺序依次回放,二进制响应体
+    #[test]
+    fn test_replay_sequence() {
+        let server = MockServer::start();
+        server.mock(Method::GET, "/").times(1).respond(MockResponse::ok().body(vec![0xff, 0x00]));
+        server.mock(Method::GET, "/").respond(MockResponse::ok().body("second"));
+        let path = std::env::temp_dir().join("toys_cassette_sequence.json");
+        let _ = std::fs::remove_file(&path);
+        let client = HttpClient::builder().middleware(Cassette::new(&path, CassetteMode::Once).unwrap()).build().unwrap();
+        client.get(&server.url("/")).send().unwrap();
+        client.get(&server.url("/")).send().unwrap();
+
+        let cassette = Cassette::new(&path, CassetteMode::Once).unwrap();
+        assert!(cassette.is_replaying());
+        let client = HttpClient::builder().middleware(cassette).build().unwrap();
+        assert_eq!(client.get(&server.url("/")).send().unwrap().body.as_ref(), &[0xff, 0x00]);
+        assert_eq!(client.get(&server.url("/")).send().unwrap().text(), "second");
+        // 记录用完后重复回放最后一条
+        assert_eq!(client.get(&server.url("/")).send().unwrap().text(), "second");
+        assert_eq!(server.received(Method::GET, "/"), 2);
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,815 @@
+//! # 可配置的HTTP客户端
+//!
+//! `AsyncHttpClient` 为异步客户端; `HttpClient` 为同步客户端,
+//! 内部持有一个`AsyncHttpClient`并在共享的后台运行时上执行请求.
+
+use std::future::Future;
+use std::net::{IpAddr, SocketAddr};
+use std::path::Path;
+use std::sync::{Arc, mpsc};
+use std::time::{Duration, Instant};
+use bytes::Bytes;
+use lazy_static::lazy_static;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::redirect::Policy;
+use reqwest::{Certificate, Identity, Method, Url};
+use tokio::runtime::{Handle, RuntimeFlavor};
+use crate::networks::http::download::{AsyncDownload, Download};
+use crate::networks::http::error::{HttpError, HttpResult};
+use crate::networks::http::auth::{AuthMiddleware, AuthProvider};
+use crate::networks::http::middleware::{Middleware, Next};
+use crate::networks::http::proxy::Proxy;
+use crate::networks::http::request::{AsyncRequestBuilder, Body, Request, RequestBuilder};
+use crate::networks::http::response::HttpResponse;
+use crate::networks::http::retry::RetryPolicy;
+use crate::networks::http::session::CookieJar;
+
+/// 默认的请求总超时时间
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// 默认的最大重定向次数
+pub const DEFAULT_MAX_REDIRECTS: usize = 10;
+
+/// 默认的User-Agent
+pub const DEFAULT_USER_AGENT: &str = concat!("toys/", env!("CARGO_PKG_VERSION"));
+
+lazy_static! {
+    // 同步客户端共享的后台运行时
+    static ref RUNTIME: tokio::runtime::Runtime = tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(2)
+        .thread_name("toys-http-runtime")
+        .enable_all()
+        .build()
+        .expect("failed to build toys http runtime");
+}
+
+/// 在后台运行时上执行一个Future,并阻塞当前线程等待其结果.
+/// 在多线程运行时(包括后台运行时自身)的工作线程中调用时,会通过`block_in_place`交出该工作线程,避免占满工作线程导致死锁;
+/// 在单线程运行时中调用会阻塞整个运行时直到请求完成,此时应使用异步API.
+pub(crate) fn block_on<F>(future: F) -> F::Output
+    where F: Future + Send + 'static,
+          F::Output: Send + 'static
+{
+    let (tx, rx) = mpsc::channel();
+    RUNTIME.spawn(async move {
+        let _ = tx.send(future.await);
+    });
+    let recv = || rx.recv().expect("toys http runtime stopped unexpectedly");
+    match Handle::try_current() {
+        Ok(handle) if handle.runtime_flavor() == RuntimeFlavor::MultiThread => tokio::task::block_in_place(recv),
+        _ => recv(),
+    }
+}
+
+// 在共享的运行时中后台执行异步任务
+pub(crate) fn spawn<F>(future: F)
+    where F: Future<Output = ()> + Send + 'static
+{
+    RUNTIME.spawn(future);
+}
+
+/// 客户端配置
+#[derive(Debug, Clone)]
+pub struct ClientConfig {
+    // 建立连接的超时时间
+    pub connect_timeout: Option<Duration>,
+    // 两次读取响应数据之间允许的最长间隔
+    pub read_timeout: Option<Duration>,
+    // 整个请求(连接、发送、读取响应)的超时时间
+    pub timeout: Option<Duration>,
+    // 每个请求都会携带的请求头
+    pub default_headers: HeaderMap,
+    // 相对地址的请求会拼接在该地址之后
+    pub base_url: Option<Url>,
+    // User-Agent请求头
+    pub user_agent: String,
+    // 每个Host最多保留的空闲连接数
+    pub pool_max_idle_per_host: Option<usize>,
+    // 空闲连接的存活时间
+    pub pool_idle_timeout: Option<Duration>,
+    // 默认的重试策略,可以被单个请求覆盖
+    pub retry: RetryPolicy,
+    // 自动跟随重定向的最大次数,为0时不跟随重定向
+    pub max_redirects: usize,
+    // 代理,为空时使用环境变量中的代理
+    pub proxies: Vec<Proxy>,
+    // 未设置代理时是否使用环境变量中的代理
+    pub env_proxy: bool,
+    // 额外信任的根证书
+    pub root_certificates: Vec<Certificate>,
+    // 是否信任系统内置的根证书
+    pub built_in_roots: bool,
+    // 双向TLS的客户端证书
+    pub identity: Option<Identity>,
+    // 是否跳过服务端证书校验
+    pub accept_invalid_certs: bool,
+    // 域名解析覆盖
+    pub resolve: Vec<(String, IpAddr)>,
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        ClientConfig {
+            connect_timeout: None,
+            read_timeout: None,
+            timeout: Some(DEFAULT_TIMEOUT),
+            default_headers: HeaderMap::new(),
+            base_url: None,
+            user_agent: DEFAULT_USER_AGENT.to_string(),
+            pool_max_idle_per_host: None,
+            pool_idle_timeout: None,
+            retry: RetryPolicy::none(),
+            max_redirects: DEFAULT_MAX_REDIRECTS,
+            proxies: Vec::new(),
+            env_proxy: true,
+            root_certificates: Vec::new(),
+            built_in_roots: true,
+            identity: None,
+            accept_invalid_certs: false,
+            resolve: Vec::new(),
+        }
+    }
+}
+
+/// HTTP客户端构建器
+///
+/// # Examples
+/// ```
+/// use std::time::Duration;
+/// use toys::networks::http::HttpClient;
+/// let client = HttpClient::builder()
+///     .base_url("http://127.0.0.1:13001/example/")
+///     .connect_timeout(Duration::from_secs(1))
+///     .timeout(Duration::from_secs(30))
+///     .default_header("X-App", "toys")
+///     .build()
+///     .unwrap();
+/// assert_eq!(client.config().timeout, Some(Duration::from_secs(30)));
+/// ```
+#[derive(Debug, Default)]
+pub struct HttpClientBuilder {
+    config: ClientConfig,
+    // 按添加顺序执行的中间件
+    middlewares: Vec<Arc<dyn Middleware>>,
+    // 构建过程中产生的第一个错误,在build时返回
+    error: Option<HttpError>,
+    // 由Cookie Jar跟随重定向,底层客户端不再自动跟随
+    cookie_jar: Option<CookieJar>,
+}
+
+impl HttpClientBuilder {
+    /// 创建一个使用默认配置的构建器
+    pub fn new() -> Self {
+        HttpClientBuilder::default()
+    }
+
+    /// 设置建立连接的超时时间
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.config.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// 设置读超时,即等待响应头或两块响应数据之间允许的最长时间
+    pub fn read_timeout(mut self, timeout: Duration) -> Self {
+        self.config.read_timeout = Some(timeout);
+        self
+    }
+
+    /// 设置请求的总超时时间,默认为3s
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.config.timeout = Some(timeout);
+        self
+    }
+
+    /// 取消请求的总超时时间
+    pub fn no_timeout(mut self) -> Self {
+        self.config.timeout = None;
+        self
+    }
+
+    /// 添加一个默认请求头
+    pub fn default_header(mut self, key: &str, value: &str) -> Self {
+        match (HeaderName::from_bytes(key.as_bytes()), HeaderValue::from_str(value)) {
+            (Ok(name), Ok(value)) => {
+                self.config.default_headers.insert(name, value);
+            }
+            _ => self.set_error(HttpError::Builder(format!("invalid header: {}: {}", key, value))),
+        }
+        self
+    }
+
+    /// 批量设置默认请求头
+    pub fn default_headers(mut self, headers: HeaderMap) -> Self {
+        self.config.default_headers.extend(headers);
+        self
+    }
+
+    /// 设置Base URL,相对地址的请求会拼接在其后
+    pub fn base_url(mut self, base_url: &str) -> Self {
+        // 保证以'/'结尾,避免Url::join时丢弃最后一段路径
+        let normalized = if base_url.ends_with('/') {
+            base_url.to_string()
+        } else {
+            format!("{}/", base_url)
+        };
+        match Url::parse(&normalized) {
+            Ok(url) => self.config.base_url = Some(url),
+            Err(_) => self.set_error(HttpError::Builder(format!("invalid base url: {}", base_url))),
+        }
+        self
+    }
+
+    /// 设置User-Agent
+    pub fn user_agent(mut self, user_agent: &str) -> Self {
+        self.config.user_agent = user_agent.to_string();
+        self
+    }
+
+    /// 设置每个Host最多保留的空闲连接数
+    pub fn pool_max_idle_per_host(mut self, max: usize) -> Self {
+        self.config.pool_max_idle_per_host = Some(max);
+        self
+    }
+
+    /// 设置空闲连接的存活时间
+    pub fn pool_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.config.pool_idle_timeout = Some(timeout);
+        self
+    }
+
+    /// 设置默认的重试策略
+    pub fn retry(mut self, policy: RetryPolicy) -> Self {
+        self.config.retry = policy;
+        self
+    }
+
+    /// 添加一个中间件,先添加的中间件最先处理请求
+    pub fn middleware<M: Middleware>(mut self, middleware: M) -> Self {
+        self.middlewares.push(Arc::new(middleware));
+        self
+    }
+
+    /// 设置自动跟随重定向的最大次数,为0时不跟随重定向
+    pub fn max_redirects(mut self, max: usize) -> Self {
+        self.config.max_redirects = max;
+        self
+    }
+
+    /// 添加代理,可以添加多个,按添加顺序匹配
+    pub fn proxy(mut self, proxy: Proxy) -> Self {
+        self.config.proxies.push(proxy);
+        self
+    }
+
+    /// 不使用任何代理,包括环境变量中的代理
+    pub fn no_proxy(mut self) -> Self {
+        self.config.proxies.clear();
+        self.config.env_proxy = false;
+        self
+    }
+
+    /// 添加受信任的根证书,支持PEM(可以包含多个证书)与DER格式
+    pub fn root_certificate(mut self, cert: &[u8]) -> Self {
+        let certs = if cert.starts_with(b"-----") {
+            Certificate::from_pem_bundle(cert)
+        } else {
+            Certificate::from_der(cert).map(|cert| vec![cert])
+        };
+        match certs {
+            Ok(certs) if !certs.is_empty() => self.config.root_certificates.extend(certs),
+            _ => self.set_error(HttpError::Builder("invalid root certificate".to_string())),
+        }
+        self
+    }
+
+    /// 从文件添加受信任的根证书
+    pub fn root_certificate_file<P: AsRef<Path>>(mut self, path: P) -> Self {
+        match std::fs::read(path) {
+            Ok(cert) => self.root_certificate(&cert),
+            Err(e) => {
+                self.set_error(HttpError::Io(e));
+                self
+            }
+        }
+    }
+
+    /// 设置是否信任系统内置的根证书,关闭后只信任添加的根证书
+    pub fn built_in_roots(mut self, enable: bool) -> Self {
+        self.config.built_in_roots = enable;
+        self
+    }
+
+    /// 使用PKCS#12格式的客户端证书进行双向TLS认证
+    pub fn identity_pkcs12(mut self, der: &[u8], password: &str) -> Self {
+        match Identity::from_pkcs12_der(der, password) {
+            Ok(identity) => self.config.identity = Some(identity),
+            Err(e) => self.set_error(HttpError::Builder(format!("invalid client certificate: {}", e))),
+        }
+        self
+    }
+
+    /// 使用PEM格式的客户端证书链与PKCS#8私钥进行双向TLS认证
+    pub fn identity_pem(mut self, cert: &[u8], key: &[u8]) -> Self {
+        match Identity::from_pkcs8_pem(cert, key) {
+            Ok(identity) => self.config.identity = Some(identity),
+            Err(e) => self.set_error(HttpError::Builder(format!("invalid client certificate: {}", e))),
+        }
+        self
+    }
+
+    /// 跳过服务端证书校验,只应在测试环境中使用
+    pub fn danger_accept_invalid_certs(mut self, accept: bool) -> Self {
+        self.config.accept_invalid_certs = accept;
+        self
+    }
+
+    /// 将域名解析到指定的IP地址,端口仍然使用请求地址中的端口
+    pub fn resolve(mut self, host: &str, ip: IpAddr) -> Self {
+        self.config.resolve.push((host.to_string(), ip));
+        self
+    }
+
+    /// 使用Cookie Jar保存与发送Cookie.无论调用顺序如何,Cookie Jar总是位于所有中间件之后,
+    /// 由它跟随重定向并保存每一跳响应中的Cookie,中间件只处理第一跳的请求与最终的响应
+    pub fn cookie_jar(mut self, jar: CookieJar) -> Self {
+        self.cookie_jar = Some(jar);
+        self
+    }
+
+    /// 添加认证提供者,相当于添加一个[`AuthMiddleware`]
+    pub fn auth<P: AuthProvider>(self, provider: P) -> Self {
+        self.middleware(AuthMiddleware::new(provider))
+    }
+
+    /// 构建同步客户端
+    pub fn build(self) -> HttpResult<HttpClient> {
+        Ok(HttpClient { inner: self.build_async()? })
+    }
+
+    /// 构建异步客户端
+    pub fn build_async(self) -> HttpResult<AsyncHttpClient> {
+        if let Some(error) = self.error {
+            return Err(error);
+        }
+        let config = self.config;
+        let redirect = match config.max_redirects {
+            0 => Policy::none(),
+            _ if self.cookie_jar.is_some() => Policy::none(),
+            max => Policy::limited(max),
+        };
+        let mut builder = reqwest::Client::builder()
+            .default_headers(config.default_headers.clone())
+            .user_agent(config.user_agent.clone())
+            .redirect(redirect)
+            .tls_built_in_root_certs(config.built_in_roots)
+            .danger_accept_invalid_certs(config.accept_invalid_certs);
+        if !config.env_proxy {
+            builder = builder.no_proxy();
+        }
+        for proxy in &config.proxies {
+            builder = builder.proxy(proxy.to_reqwest()?);
+        }
+        for cert in &config.root_certificates {
+            builder = builder.add_root_certificate(cert.clone());
+        }
+        if let Some(identity) = &config.identity {
+            builder = builder.identity(identity.clone());
+        }
+        for (host, ip) in &config.resolve {
+            builder = builder.resolve(host, SocketAddr::new(*ip, 0));
+        }
+        if let Some(timeout) = config.connect_timeout {
+            builder = builder.connect_timeout(timeout);
+        }
+        if let Some(max) = config.pool_max_idle_per_host {
+            builder = builder.pool_max_idle_per_host(max);
+        }
+        if let Some(timeout) = config.pool_idle_timeout {
+            builder = builder.pool_idle_timeout(timeout);
+        }
+        Ok(AsyncHttpClient {
+            inner: builder.build()?,
+            config: Arc::new(config),
+            middlewares: Arc::new(self.middlewares),
+            cookie_jar: self.cookie_jar,
+        })
+    }
+
+    // 只保留第一个错误
+    fn set_error(&mut self, error: HttpError) {
+        if self.error.is_none() {
+            self.error = Some(error);
+        }
+    }
+}
+
+/// 异步HTTP客户端,内部连接池可共享,克隆开销很小
+#[derive(Debug, Clone)]
+pub struct AsyncHttpClient {
+    inner: reqwest::Client,
+    config: Arc<ClientConfig>,
+    middlewares: Arc<Vec<Arc<dyn Middleware>>>,
+    // 在中间件之后添加Cookie并跟随重定向
+    cookie_jar: Option<CookieJar>,
+}
+
+impl Default for AsyncHttpClient {
+    fn default() -> Self {
+        HttpClientBuilder::new().build_async().expect("failed to build default http client")
+    }
+}
+
+impl AsyncHttpClient {
+    /// 使用默认配置创建客户端
+    pub fn new() -> Self {
+        AsyncHttpClient::default()
+    }
+
+    /// 获取客户端构建器
+    pub fn builder() -> HttpClientBuilder {
+        HttpClientBuilder::new()
+    }
+
+    /// 获取客户端配置
+    pub fn config(&self) -> &ClientConfig {
+        &self.config
+    }
+
+    /// 获取底层的`reqwest`客户端
+    pub fn inner(&self) -> &reqwest::Client {
+        &self.inner
+    }
+
+    /// 将请求地址解析为完整的URL,相对地址会拼接在Base URL之后
+    pub fn build_url(&self, url: &str) -> HttpResult<Url> {
+        match Url::parse(url) {
+            Ok(url) => Ok(url),
+            Err(_) => match &self.config.base_url {
+                Some(base) => base.join(url.trim_start_matches('/'))
+                    .map_err(|_| HttpError::InvalidUrl(url.to_string())),
+                None => Err(HttpError::InvalidUrl(url.to_string())),
+            },
+        }
+    }
+
+    /// 创建一个指定请求方法的请求构建器,地址不合法时错误会在发送时返回
+    pub fn request(&self, method: Method, url: &str) -> AsyncRequestBuilder {
+        let request = self.build_url(url).map(|url| Request::new(method, url));
+        AsyncRequestBuilder::new(self.clone(), request)
+    }
+
+    /// 创建GET请求构建器
+    pub fn get(&self, url: &str) -> AsyncRequestBuilder {
+        self.request(Method::GET, url)
+    }
+
+    /// 创建POST请求构建器
+    pub fn post(&self, url: &str) -> AsyncRequestBuilder {
+        self.request(Method::POST, url)
+    }
+
+    /// 创建PUT请求构建器
+    pub fn put(&self, url: &str) -> AsyncRequestBuilder {
+        self.request(Method::PUT, url)
+    }
+
+    /// 创建PATCH请求构建器
+    pub fn patch(&self, url: &str) -> AsyncRequestBuilder {
+        self.request(Method::PATCH, url)
+    }
+
+    /// 创建DELETE请求构建器
+    pub fn delete(&self, url: &str) -> AsyncRequestBuilder {
+        self.request(Method::DELETE, url)
+    }
+
+    /// 创建HEAD请求构建器
+    pub fn head(&self, url: &str) -> AsyncRequestBuilder {
+        self.request(Method::HEAD, url)
+    }
+
+    /// 创建OPTIONS请求构建器
+    pub fn options(&self, url: &str) -> AsyncRequestBuilder {
+        self.request(Method::OPTIONS, url)
+    }
+
+    /// 创建一个将`url`下载到本地文件`path`的下载构建器
+    pub fn download<P: AsRef<Path>>(&self, url: &str, path: P) -> AsyncDownload {
+        AsyncDownload::new(self.clone(), url, path.as_ref().to_path_buf())
+    }
+
+    /// 依次经过中间件后发送请求并读取完整的响应体,按照重试策略重试失败的请求.
+    /// 每次重试都会重新经过全部中间件,限流器为每次尝试获取令牌,熔断器统计每次尝试的结果.
+    /// 不会检查响应状态码
+    pub async fn execute(&self, request: Request) -> HttpResult<HttpResponse> {
+        let policy = request.retry.as_ref().unwrap_or(&self.config.retry).clone();
+        let mut attempt = 1;
+        loop {
+            let result = Next::new(self, &self.middlewares).run(request.clone()).await;
+            let delay = match &result {
+                Ok(response) => policy.retry_response(&request.method, response, attempt),
+                Err(error) => policy.retry_error(&request.method, error, attempt),
+            };
+            match delay {
+                Some(delay) => {
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                None => return result,
+            }
+        }
+    }
+
+    // 中间件执行完后发送请求,设置了Cookie Jar时由其添加Cookie并跟随重定向
+    pub(crate) async fn send_after_middlewares(&self, request: Request) -> HttpResult<HttpResponse> {
+        match &self.cookie_jar {
+            Some(jar) => jar.send_following(request, self.config.max_redirects, |request| self.send_once(request)).await,
+            None => self.send_once(request).await,
+        }
+    }
+
+    // 发送一次请求并读取完整的响应体.
+    // 总超时作用于整个请求,读超时作用于等待响应头以及每一块响应数据
+    async fn send_once(&self, request: Request) -> HttpResult<HttpResponse> {
+        let timeout = request.timeout.or(self.config.timeout);
+        let read_timeout = self.config.read_timeout;
+        let start = Instant::now();
+        let future = async {
+            let mut response = self.send_raw(request).await?;
+            let mut body = Vec::new();
+            while let Some(chunk) = with_read_timeout(read_timeout, response.chunk()).await?? {
+                body.extend_from_slice(&chunk);
+            }
+            Ok(HttpResponse {
+                status: response.status(),
+                headers: std::mem::take(response.headers_mut()),
+                url: response.url().clone(),
+                version: response.version(),
+                elapsed: start.elapsed(),
+                body: Bytes::from(body),
+            })
+        };
+        match timeout {
+            Some(duration) => tokio::time::timeout(duration, future).await
+                .map_err(|_| HttpError::Timeout(duration))?,
+            None => future.await,
+        }
+    }
+
+    // 发送请求但不读取响应体,供下载、事件流等流式场景使用.
+    // 依次调用中间件的`prepare`(认证、签名、限流等)后发送,不经过`handle`与重试
+    pub(crate) async fn send_streaming(&self, mut request: Request) -> HttpResult<reqwest::Response> {
+        for middleware in self.middlewares.iter() {
+            request = middleware.prepare(request).await?;
+        }
+        match &self.cookie_jar {
+            Some(jar) => jar.send_following(request, self.config.max_redirects, |request| self.send_raw(request)).await,
+            None => self.send_raw(request).await,
+        }
+    }
+
+    // 直接发送一次请求,读超时只作用于等待响应头
+    async fn send_raw(&self, request: Request) -> HttpResult<reqwest::Response> {
+        Ok(with_read_timeout(self.config.read_timeout, self.to_reqwest(request).send()).await??)
+    }
+
+    // 转换为reqwest的请求
+    fn to_reqwest(&self, request: Request) -> reqwest::RequestBuilder {
+        let builder = self.inner.request(request.method, request.url).headers(request.headers);
+        match request.body {
+            Body::Empty => builder,
+            Body::Bytes(bytes) => builder.body(bytes),
+            Body::Multipart(form) => match form.to_bytes() {
+                Some(bytes) => builder.body(bytes),
+                None => builder.body(reqwest::Body::wrap_stream(form.into_stream())),
+            },
+        }
+    }
+}
+
+// 如果设置了读超时,则为Future加上超时限制
+pub(crate) async fn with_read_timeout<F: Future>(timeout: Option<Duration>, future: F) -> HttpResult<F::Output> {
+    match timeout {
+        Some(duration) => tokio::time::timeout(duration, future).await
+            .map_err(|_| HttpError::ReadTimeout(duration)),
+        None => Ok(future.await),
+    }
+}
+
+/// 同步HTTP客户端,与`AsyncHttpClient`提供相同的接口
+#[derive(Debug, Clone, Default)]
+pub struct HttpClient {
+    inner: AsyncHttpClient,
+}
+
+impl HttpClient {
+    /// 使用默认配置创建客户端
+    pub fn new() -> Self {
+        HttpClient::default()
+    }
+
+    /// 获取客户端构建器
+    pub fn builder() -> HttpClientBuilder {
+        HttpClientBuilder::new()
+    }
+
+    /// 获取客户端配置
+    pub fn config(&self) -> &ClientConfig {
+        self.inner.config()
+    }
+
+    /// 获取内部使用的异步客户端
+    pub fn as_async(&self) -> &AsyncHttpClient {
+        &self.inner
+    }
+
+    /// 将请求地址解析为完整的URL,相对地址会拼接在Base URL之后
+    pub fn build_url(&self, url: &str) -> HttpResult<Url> {
+        self.inner.build_url(url)
+    }
+
+    /// 创建一个指定请求方法的请求构建器,地址不合法时错误会在发送时返回
+    pub fn request(&self, method: Method, url: &str) -> RequestBuilder {
+        RequestBuilder::new(self.inner.request(method, url))
+    }
+
+    /// 创建GET请求构建器
+    pub fn get(&self, url: &str) -> RequestBuilder {
+        self.request(Method::GET, url)
+    }
+
+    /// 创建POST请求构建器
+    pub fn post(&self, url: &str) -> RequestBuilder {
+        self.request(Method::POST, url)
+    }
+
+    /// 创建PUT请求构建器
+    pub fn put(&self, url: &str) -> RequestBuilder {
+        self.request(Method::PUT, url)
+    }
+
+    /// 创建PATCH请求构建器
+    pub fn patch(&self, url: &str) -> RequestBuilder {
+        self.request(Method::PATCH, url)
+    }
+
+    /// 创建DELETE请求构建器
+    pub fn delete(&self, url: &str) -> RequestBuilder {
+        self.request(Method::DELETE, url)
+    }
+
+    /// 创建HEAD请求构建器
+    pub fn head(&self, url: &str) -> RequestBuilder {
+        self.request(Method::HEAD, url)
+    }
+
+    /// 创建OPTIONS请求构建器
+    pub fn options(&self, url: &str) -> RequestBuilder {
+        self.request(Method::OPTIONS, url)
+    }
+
+    /// 创建一个将`url`下载到本地文件`path`的下载构建器
+    pub fn download<P: AsRef<Path>>(&self, url: &str, path: P) -> Download {
+        Download::new(self, url, path.as_ref().to_path_buf())
+    }
+
+    /// 发送请求并读取完整的响应体,不会检查响应状态码
+    pub fn execute(&self, request: Request) -> HttpResult<HttpResponse> {
+        let client = self.inner.clone();
+        block_on(async move { client.execute(request).await })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use openssl::pkcs12::Pkcs12;
+    use openssl::ssl::{Ssl, SslAcceptor, SslMethod, SslVerifyMode};
+    use openssl::x509::X509;
+    use tokio_openssl::SslStream;
+    use crate::networks::http::client::{block_on, AsyncHttpClient, DEFAULT_TIMEOUT, HttpClient};
+    use crate::networks::http::error::HttpError;
+
+    /// 测试构建器配置
+    #[test]
+    fn test_builder_config() {
+        let client = HttpClient::builder()
+            .connect_timeout(Duration::from_secs(1))
+            .read_timeout(Duration::from_secs(2))
+            .user_agent("toys-test")
+            .default_header("X-Trace", "1")
+            .pool_max_idle_per_host(4)
+            .build()
+            .unwrap();
+        let config = client.config();
+        assert_eq!(config.connect_timeout, Some(Duration::from_secs(1)));
+        assert_eq!(config.read_timeout, Some(Duration::from_secs(2)));
+        assert_eq!(config.timeout, Some(DEFAULT_TIMEOUT));
+        assert_eq!(config.user_agent, "toys-test");
+        assert_eq!(config.default_headers["X-Trace"], "1");
+        assert_eq!(config.pool_max_idle_per_host, Some(4));
+    }
+
+    /// 测试在后台运行时的工作线程中嵌套调用同步接口不会死锁
+    #[test]
+    fn test_block_on_nested() {
+        let sum = block_on(async {
+            let tasks: Vec<_> = (0..4u32).map(|i| tokio::spawn(async move { block_on(async move { i }) })).collect();
+            let mut sum = 0;
+            for task in tasks {
+                sum += task.await.unwrap();
+            }
+            sum
+        });
+        assert_eq!(sum, 6);
+    }
+
+    /// 测试非法的构建参数
+    #[test]
+    fn test_builder_error() {
+        let result = AsyncHttpClient::builder().default_header("bad header", "v").build_async();
+        assert!(matches!(result, Err(HttpError::Builder(_))));
+        let result = AsyncHttpClient::builder().base_url("not a url").build_async();
+        assert!(matches!(result, Err(HttpError::Builder(_))));
+    }
+
+    /// 测试Base URL拼接
+    #[test]
+    fn test_build_url() {
+        let client = HttpClient::builder().base_url("http://127.0.0.1:13001/example").build().unwrap();
+        assert_eq!(client.build_url("index").unwrap().as_str(), "http://127.0.0.1:13001/example/index");
+        assert_eq!(client.build_url("/index/post").unwrap().as_str(), "http://127.0.0.1:13001/example/index/post");
+        assert_eq!(client.build_url("http://localhost/a").unwrap().as_str(), "http://localhost/a");
+        assert!(HttpClient::new().build_url("index").is_err());
+    }
+
+    // 启动一个使用测试CA签发证书的HTTPS服务,要求客户端提供同一CA签发的证书,返回端口
+    async fn tls_server() -> u16 {
+        let der = std::fs::read(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/certs/server.p12")).unwrap();
+        let identity = Pkcs12::from_der(&der).unwrap().parse2("toys").unwrap();
+        let ca = X509::from_pem(&std::fs::read(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/certs/ca.pem")).unwrap()).unwrap();
+        let mut builder = SslAcceptor::mozilla_intermediate_v5(SslMethod::tls()).unwrap();
+        builder.set_certificate(identity.cert.as_ref().unwrap()).unwrap();
+        builder.set_private_key(identity.pkey.as_ref().unwrap()).unwrap();
+        builder.cert_store_mut().add_cert(ca.clone()).unwrap();
+        builder.add_client_ca(&ca).unwrap();
+        builder.set_verify(SslVerifyMode::PEER | SslVerifyMode::FAIL_IF_NO_PEER_CERT);
+        let acceptor = std::sync::Arc::new(builder.build());
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let acceptor = acceptor.clone();
+                tokio::spawn(async move {
+                    let Ok(mut stream) = SslStream::new(Ssl::new(acceptor.context()).unwrap(), stream) else { return };
+                    if std::pin::Pin::new(&mut stream).accept().await.is_err() {
+                        return;
+                    }
+                    let mut buf = [0u8; 1024];
+                    let _ = stream.read(&mut buf).await;
+                    let _ = stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok").await;
+                    let _ = stream.shutdown().await;
+                });
+            }
+        });
+        port
+    }
+
+    /// 测试信任自定义根证书与加载客户端证书
+    #[tokio::test]
+    async fn test_custom_root_certificate() {
+        let port = tls_server().await;
+        let url = format!("https://localhost:{}/", port);
+        let cert = std::fs::read(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/certs/client.pem")).unwrap();
+        let key = std::fs::read(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/certs/client.key")).unwrap();
+        let client = AsyncHttpClient::builder()
+            .no_proxy()
+            .root_certificate_file(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/certs/ca.pem"))
+            .built_in_roots(false)
+            .identity_pem(&cert, &key)
+            .build_async()
+            .unwrap();
+        assert_eq!(client.get(&url).send_text().await.unwrap().body, "ok");
+
+        // 信任服务端证书但未提供客户端证书时服务端拒绝连接
+        let anonymous = AsyncHttpClient::builder()
+            .no_proxy()
+            .root_certificate_file(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/certs/ca.pem"))
+            .built_in_roots(false)
+            .build_async()
+            .unwrap();
+        assert!(matches!(anonymous.get(&url).send().await, Err(HttpError::Request(_))));
+
+        let untrusted = AsyncHttpClient::builder().no_proxy().identity_pem(&cert, &key).build_async().unwrap();
+        assert!(matches!(untrusted.get(&url).send().await, Err(HttpError::Request(_))));
+        let insecure = AsyncHttpClient::builder()
+            .no_proxy()
+            .danger_accept_invalid_certs(true)
+            .identity_pem(&cert, &key)
+            .build_async()
+            .unwrap();
+        assert!(insecure.get(&url).send().await.unwrap().is_success());
+    }
+}
@@ -0,0 +1,612 @@
+//! # 文件下载
+//!
+//! 以流的方式将响应体写入磁盘,支持进度回调、校验值验证与断点续传.
+//! 下载过程中数据写入`<文件名>.part`,同时在`<文件名>.part.json`中记录断点信息,
+//! 下载完成并校验通过后才会重命名为目标文件.
+//!
+//! 下载请求会经过中间件的`prepare`,等待响应头的阶段按照重试策略重试,
+//! 下载默认不受客户端总超时限制,连接超时作用于建立连接,读超时作用于两块数据之间的间隔.
+
+use std::fmt::{Debug, Formatter};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use md5::Md5;
+use reqwest::header::{CONTENT_LENGTH, CONTENT_RANGE, ETAG, HeaderValue, IF_RANGE, LAST_MODIFIED, RANGE};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use crate::data::json::{from_json_str, to_json_str};
+use crate::networks::http::client::{AsyncHttpClient, block_on, HttpClient, with_read_timeout};
+use crate::networks::http::error::{HttpError, HttpResult};
+use crate::networks::http::request::{AsyncRequestBuilder, Request};
+use crate::networks::http::response::HttpResponse;
+use crate::networks::http::retry::RetryPolicy;
+
+/// 下载进度
+#[derive(Debug, Clone)]
+pub struct Progress {
+    // 已下载的字节数,包含续传前已有的部分
+    pub downloaded: u64,
+    // 文件总大小,服务端未返回长度时为None
+    pub total: Option<u64>,
+    // 本次下载的平均速度,单位: 字节/秒
+    pub bytes_per_second: f64,
+    // 本次下载已用时间
+    pub elapsed: Duration,
+}
+
+impl Progress {
+    /// 下载百分比,总大小未知时返回None
+    pub fn percent(&self) -> Option<f64> {
+        self.total.filter(|total| *total > 0).map(|total| self.downloaded as f64 * 100.0 / total as f64)
+    }
+}
+
+/// 文件校验值,使用十六进制字符串表示
+#[derive(Debug, Clone, PartialEq)]
+pub enum Checksum {
+    Sha256(String),
+    Md5(String),
+}
+
+impl Checksum {
+    /// SHA-256校验值
+    pub fn sha256(hex: &str) -> Self {
+        Checksum::Sha256(hex.to_ascii_lowercase())
+    }
+
+    /// MD5校验值
+    pub fn md5(hex: &str) -> Self {
+        Checksum::Md5(hex.to_ascii_lowercase())
+    }
+
+    fn expected(&self) -> &str {
+        match self {
+            Checksum::Sha256(hex) | Checksum::Md5(hex) => hex,
+        }
+    }
+
+    // 计算文件的校验值
+    async fn compute(&self, path: &Path) -> std::io::Result<String> {
+        match self {
+            Checksum::Sha256(_) => hash_file::<Sha256>(path).await,
+            Checksum::Md5(_) => hash_file::<Md5>(path).await,
+        }
+    }
+}
+
+// 分块读取文件计算摘要
+async fn hash_file<D: Digest>(path: &Path) -> std::io::Result<String> {
+    let mut file = tokio::fs::File::open(path).await?;
+    let mut hasher = D::new();
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        let n = file.read(&mut buf).await?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// 下载结果
+#[derive(Debug, Clone)]
+pub struct DownloadReport {
+    // 目标文件
+    pub path: PathBuf,
+    // 文件大小
+    pub size: u64,
+    // 续传开始的位置,为0表示从头下载
+    pub resumed_from: u64,
+    // 本次下载用时
+    pub elapsed: Duration,
+}
+
+// 断点信息,用于判断服务端文件是否发生了变化
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+struct PartialMarker {
+    url: String,
+    etag: Option<String>,
+    last_modified: Option<String>,
+    total: Option<u64>,
+}
+
+// 进度回调
+type ProgressCallback = Arc<dyn Fn(&Progress) + Send + Sync>;
+
+/// 异步文件下载构建器,由`AsyncHttpClient::download`创建
+///
+/// # Examples
+/// ```no_run
+/// use toys::networks::http::AsyncHttpClient;
+/// use toys::networks::http::download::Checksum;
+/// # async fn run() -> toys::networks::http::HttpResult<()> {
+/// let report = AsyncHttpClient::new()
+///     .download("https://example.com/big.iso", "big.iso")
+///     .checksum(Checksum::sha256("9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08"))
+///     .on_progress(|p| println!("{}/{:?} {:.0}B/s", p.downloaded, p.total, p.bytes_per_second))
+///     .start()
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct AsyncDownload {
+    request: AsyncRequestBuilder,
+    client: AsyncHttpClient,
+    path: PathBuf,
+    checksum: Option<Checksum>,
+    resume: bool,
+    progress: Option<ProgressCallback>,
+}
+
+impl Debug for AsyncDownload {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AsyncDownload")
+            .field("request", &self.request)
+            .field("path", &self.path)
+            .field("checksum", &self.checksum)
+            .field("resume", &self.resume)
+            .finish()
+    }
+}
+
+impl AsyncDownload {
+    pub(crate) fn new(client: AsyncHttpClient, url: &str, path: PathBuf) -> Self {
+        AsyncDownload {
+            request: client.get(url),
+            client,
+            path,
+            checksum: None,
+            resume: true,
+            progress: None,
+        }
+    }
+
+    /// 添加请求头
+    pub fn header(mut self, key: &str, value: &str) -> Self {
+        self.request = self.request.header(key, value);
+        self
+    }
+
+    /// 设置整个下载过程的总超时时间,默认不限制
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.request = self.request.timeout(timeout);
+        self
+    }
+
+    /// 设置等待响应头阶段的重试策略,默认使用客户端的重试策略
+    pub fn retry(mut self, policy: RetryPolicy) -> Self {
+        self.request = self.request.retry(policy);
+        self
+    }
+
+    /// 设置下载完成后需要验证的校验值,不一致时删除已下载的数据并返回错误
+    pub fn checksum(mut self, checksum: Checksum) -> Self {
+        self.checksum = Some(checksum);
+        self
+    }
+
+    /// 设置是否断点续传,默认开启
+    pub fn resume(mut self, resume: bool) -> Self {
+        self.resume = resume;
+        self
+    }
+
+    /// 设置进度回调,每写入一块数据调用一次
+    pub fn on_progress<F>(mut self, callback: F) -> Self where F: Fn(&Progress) + Send + Sync + 'static {
+        self.progress = Some(Arc::new(callback));
+        self
+    }
+
+    /// 开始下载
+    pub async fn start(self) -> HttpResult<DownloadReport> {
+        let request = self.request.build()?;
+        let timeout = request.timeout;
+        let future = download(self.client, request, self.path, self.checksum, self.resume, self.progress);
+        match timeout {
+            Some(duration) => tokio::time::timeout(duration, future).await
+                .map_err(|_| HttpError::Timeout(duration))?,
+            None => future.await,
+        }
+    }
+}
+
+// 下载文件,已有断点信息时从断点处续传
+async fn download(client: AsyncHttpClient, request: Request, path: PathBuf, checksum: Option<Checksum>,
+                  resume: bool, progress: Option<ProgressCallback>) -> HttpResult<DownloadReport> {
+    let start = Instant::now();
+    let part_path = with_suffix(&path, ".part");
+    let marker_path = with_suffix(&path, ".part.json");
+    let marker = if resume { load_marker(&marker_path).await } else { None };
+    let existing = match tokio::fs::metadata(&part_path).await {
+        Ok(meta) if marker.as_ref().is_some_and(|m| m.url == request.url.as_str()) => meta.len(),
+        _ => 0,
+    };
+
+    let response = send_with_retry(&client, range_request(request.clone(), existing, marker.as_ref())).await?;
+    // 请求的范围超出文件大小,确认服务端文件未变化后说明上次已经下载完整
+    if response.status() == StatusCode::RANGE_NOT_SATISFIABLE && existing > 0 {
+        if let Some(marker) = marker.as_ref().filter(|m| m.total == Some(existing)) {
+            if unchanged(&client, &request, marker, &response).await? {
+                return finish(path, checksum, &part_path, &marker_path, existing, existing, start).await;
+            }
+        }
+    }
+    let (mut response, resumed_from) = match response.status() {
+        StatusCode::PARTIAL_CONTENT if existing > 0 && content_range_start(&response) == Some(existing) => (response, existing),
+        // 续传的范围与本地数据对不上,从头下载
+        StatusCode::PARTIAL_CONTENT | StatusCode::RANGE_NOT_SATISFIABLE if existing > 0 => {
+            (send_with_retry(&client, request.clone()).await?, 0)
+        }
+        // 服务端忽略了Range或文件已变化时会返回完整内容
+        _ => (response, 0),
+    };
+    let read_timeout = client.config().read_timeout;
+    if !response.status().is_success() {
+        let (status, url) = (response.status(), response.url().clone());
+        let mut body = Vec::new();
+        while let Some(chunk) = with_read_timeout(read_timeout, response.chunk()).await?? {
+            body.extend_from_slice(&chunk);
+        }
+        return Err(HttpError::Status { status, url, body: body.into() });
+    }
+
+    let total = total_size(&response, resumed_from);
+    let new_marker = PartialMarker {
+        url: request.url.to_string(),
+        etag: header_string(&response, ETAG.as_str()),
+        last_modified: header_string(&response, LAST_MODIFIED.as_str()),
+        total,
+    };
+    if let Ok(json) = to_json_str(&new_marker) {
+        tokio::fs::write(&marker_path, json).await?;
+    }
+
+    let mut file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(resumed_from > 0)
+        .truncate(resumed_from == 0)
+        .open(&part_path)
+        .await?;
+    let session_start = Instant::now();
+    let mut downloaded = resumed_from;
+    while let Some(chunk) = with_read_timeout(read_timeout, response.chunk()).await?? {
+        file.write_all(&chunk).await?;
+        downloaded += chunk.len() as u64;
+        if let Some(callback) = &progress {
+            let elapsed = session_start.elapsed();
+            let speed = (downloaded - resumed_from) as f64 / elapsed.as_secs_f64().max(f64::EPSILON);
+            callback(&Progress { downloaded, total, bytes_per_second: speed, elapsed });
+        }
+    }
+    file.flush().await?;
+    drop(file);
+    finish(path, checksum, &part_path, &marker_path, downloaded, resumed_from, start).await
+}
+
+// 发送下载请求,按照重试策略重试连接失败、超时以及可重试的状态码
+async fn send_with_retry(client: &AsyncHttpClient, request: Request) -> HttpResult<reqwest::Response> {
+    let policy = request.retry.as_ref().unwrap_or(&client.config().retry).clone();
+    let mut attempt = 1;
+    loop {
+        let result = client.send_streaming(request.clone()).await;
+        let delay = match &result {
+            Ok(response) => policy.retry_response(&request.method, &response_head(response), attempt),
+            Err(error) => policy.retry_error(&request.method, error, attempt),
+        };
+        match delay {
+            Some(delay) => {
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+            None => return result,
+        }
+    }
+}
+
+// 流式响应的状态码与响应头
+fn response_head(response: &reqwest::Response) -> HttpResponse<()> {
+    HttpResponse {
+        status: response.status(),
+        headers: response.headers().clone(),
+        url: response.url().clone(),
+        version: response.version(),
+        elapsed: Duration::ZERO,
+        body: (),
+    }
+}
+
+// 断点信息中的ETag或Last-Modified与服务端一致时说明文件未变化.
+// 416响应没有携带这两个响应头时,通过HEAD请求获取
+async fn unchanged(client: &AsyncHttpClient, request: &Request, marker: &PartialMarker,
+                   response: &reqwest::Response) -> HttpResult<bool> {
+    if marker.etag.is_none() && marker.last_modified.is_none() {
+        return Ok(false);
+    }
+    let validators = |response: &reqwest::Response| {
+        (header_string(response, ETAG.as_str()), header_string(response, LAST_MODIFIED.as_str()))
+    };
+    let (etag, last_modified) = match validators(response) {
+        (None, None) => {
+            let mut head = request.clone();
+            head.method = reqwest::Method::HEAD;
+            let response = send_with_retry(client, head).await?;
+            if !response.status().is_success() {
+                return Ok(false);
+            }
+            validators(&response)
+        }
+        validators => validators,
+    };
+    Ok(match &marker.etag {
+        Some(expected) => etag.as_ref() == Some(expected),
+        None => last_modified == marker.last_modified,
+    })
+}
+
+// 校验并将临时文件重命名为目标文件
+async fn finish(path: PathBuf, checksum: Option<Checksum>, part_path: &Path, marker_path: &Path,
+                size: u64, resumed_from: u64, start: Instant) -> HttpResult<DownloadReport> {
+    if let Some(checksum) = checksum {
+        let actual = checksum.compute(part_path).await?;
+        if actual != checksum.expected() {
+            let _ = tokio::fs::remove_file(part_path).await;
+            let _ = tokio::fs::remove_file(marker_path).await;
+            return Err(HttpError::Checksum { expected: checksum.expected().to_string(), actual });
+        }
+    }
+    tokio::fs::rename(part_path, &path).await?;
+    let _ = tokio::fs::remove_file(marker_path).await;
+    Ok(DownloadReport { path, size, resumed_from, elapsed: start.elapsed() })
+}
+
+// 在文件名后追加后缀
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+// 读取断点信息
+async fn load_marker(path: &Path) -> Option<PartialMarker> {
+    let json = tokio::fs::read_to_string(path).await.ok()?;
+    from_json_str(&json).ok()
+}
+
+// 已有部分数据时添加Range请求头,并通过If-Range保证服务端文件未发生变化
+fn range_request(mut request: Request, existing: u64, marker: Option<&PartialMarker>) -> Request {
+    if existing == 0 {
+        return request;
+    }
+    if let Ok(value) = HeaderValue::from_str(&format!("bytes={}-", existing)) {
+        request.headers.insert(RANGE, value);
+    }
+    let validator = marker.and_then(|m| m.etag.clone().or_else(|| m.last_modified.clone()));
+    if let Some(value) = validator.and_then(|v| HeaderValue::from_str(&v).ok()) {
+        request.headers.insert(IF_RANGE, value);
+    }
+    request
+}
+
+fn header_string(response: &reqwest::Response, name: &str) -> Option<String> {
+    response.headers().get(name).and_then(|v| v.to_str().ok()).map(str::to_string)
+}
+
+// 解析`Content-Range: bytes start-end/total`中的起始位置
+fn content_range_start(response: &reqwest::Response) -> Option<u64> {
+    let value = header_string(response, CONTENT_RANGE.as_str())?;
+    value.strip_prefix("bytes ")?.split('-').next()?.trim().parse().ok()
+}
+
+// 计算文件总大小
+fn total_size(response: &reqwest::Response, resumed_from: u64) -> Option<u64> {
+    if response.status() == StatusCode::PARTIAL_CONTENT {
+        let value = header_string(response, CONTENT_RANGE.as_str())?;
+        return value.rsplit('/').next()?.parse().ok();
+    }
+    let length: u64 = header_string(response, CONTENT_LENGTH.as_str())?.parse().ok()?;
+    Some(length + resumed_from)
+}
+
+/// 同步文件下载构建器,由`HttpClient::download`创建,接口与`AsyncDownload`一致
+#[derive(Debug)]
+pub struct Download {
+    inner: AsyncDownload,
+}
+
+impl Download {
+    pub(crate) fn new(client: &HttpClient, url: &str, path: PathBuf) -> Self {
+        Download { inner: AsyncDownload::new(client.as_async().clone(), url, path) }
+    }
+
+    /// 添加请求头
+    pub fn header(self, key: &str, value: &str) -> Self {
+        Download { inner: self.inner.header(key, value) }
+    }
+
+    /// 设置整个下载过程的总超时时间,默认不限制
+    pub fn timeout(self, timeout: Duration) -> Self {
+        Download { inner: self.inner.timeout(timeout) }
+    }
+
+    /// 设置等待响应头阶段的重试策略
+    pub fn retry(self, policy: RetryPolicy) -> Self {
+        Download { inner: self.inner.retry(policy) }
+    }
+
+    /// 设置下载完成后需要验证的校验值
+    pub fn checksum(self, checksum: Checksum) -> Self {
+        Download { inner: self.inner.checksum(checksum) }
+    }
+
+    /// 设置是否断点续传,默认开启
+    pub fn resume(self, resume: bool) -> Self {
+        Download { inner: self.inner.resume(resume) }
+    }
+
+    /// 设置进度回调
+    pub fn on_progress<F>(self, callback: F) -> Self where F: Fn(&Progress) + Send + Sync + 'static {
+        Download { inner: self.inner.on_progress(callback) }
+    }
+
+    /// 开始下载
+    pub fn start(self) -> HttpResult<DownloadReport> {
+        block_on(self.inner.start())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    use sha2::{Digest, Sha256};
+    use crate::networks::http::{AsyncHttpClient, HttpClient, HttpError, Method};
+    use crate::networks::http::download::{Checksum, with_suffix};
+    use crate::networks::http::mock::{MockResponse, MockServer};
+    use crate::networks::http::retry::RetryPolicy;
+
+    // 支持Range请求的文件服务
+    fn file_server(content: &'static [u8]) -> MockServer {
+        let server = MockServer::start();
+        server.mock(Method::GET, "/file").respond_with(move |request| {
+            match request.header("range").and_then(|r| r.strip_prefix("bytes=")?.strip_suffix('-')?.parse::<usize>().ok()) {
+                Some(start) if start >= content.len() => MockResponse::new(416)
+                    .header("Content-Range", &format!("bytes */{}", content.len())),
+                Some(start) => MockResponse::new(206)
+                    .header("Content-Range", &format!("bytes {}-{}/{}", start, content.len() - 1, content.len()))
+                    .header("ETag", "\"v1\"")
+                    .body(&content[start..]),
+                None => MockResponse::ok().header("ETag", "\"v1\"").body(content),
+            }
+        });
+        server.mock(Method::HEAD, "/file").respond(MockResponse::ok().header("ETag", "\"v1\""));
+        server
+    }
+
+    // 每个测试使用独立的临时目录,避免并行执行时互相干扰
+    fn temp_path(test: &str, name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("toys_download_{}_{}", test, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir.join(name)
+    }
+
+    // 写入断点数据与断点信息
+    fn write_partial(path: &Path, url: &str, data: &[u8], etag: &str, total: usize) {
+        std::fs::write(with_suffix(path, ".part"), data).unwrap();
+        std::fs::write(with_suffix(path, ".part.json"),
+                       format!(r#"{{"url":"{}","etag":"\"{}\"","last_modified":null,"total":{}}}"#, url, etag, total)).unwrap();
+    }
+
+    /// 测试完整下载、进度回调与校验
+    #[test]
+    fn test_download() {
+        let content: &'static [u8] = Box::leak(vec![7u8; 100_000].into_boxed_slice());
+        let server = file_server(content);
+        let path = temp_path("full", "file.bin");
+        let progress = Arc::new(Mutex::new(Vec::new()));
+        let recorder = progress.clone();
+        let report = HttpClient::new()
+            .download(&server.url("/file"), &path)
+            .checksum(Checksum::sha256(&hex::encode(Sha256::digest(content))))
+            .on_progress(move |p| recorder.lock().unwrap().push((p.downloaded, p.total)))
+            .start()
+            .unwrap();
+        assert_eq!(report.size, 100_000);
+        assert_eq!(report.resumed_from, 0);
+        assert_eq!(std::fs::read(&path).unwrap(), content);
+        assert_eq!(progress.lock().unwrap().last(), Some(&(100_000, Some(100_000))));
+        assert!(!with_suffix(&path, ".part.json").exists());
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    /// 测试耗时超过客户端总超时的分块下载
+    #[test]
+    fn test_slow_download() {
+        let content: &'static [u8] = b"0123456789abcdef";
+        let server = MockServer::start();
+        server.mock(Method::GET, "/slow").respond(MockResponse::ok().body(content).throttle(4, Duration::from_millis(1100)));
+        let path = temp_path("slow", "file.txt");
+        let report = crate::networks::http::download_to_file(&server.url("/slow"), &path).unwrap();
+        assert!(report.elapsed > crate::networks::http::client::DEFAULT_TIMEOUT);
+        assert_eq!(std::fs::read(&path).unwrap(), content);
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    /// 测试断点续传,以及416响应时重新验证服务端文件是否变化
+    #[tokio::test]
+    async fn test_resume() {
+        let content: &'static [u8] = b"0123456789abcdefghij";
+        let server = file_server(content);
+        let url = server.url("/file");
+        let path = temp_path("resume", "file.txt");
+        write_partial(&path, &url, &content[..8], "v1", 20);
+        let report = AsyncHttpClient::new().download(&url, &path).start().await.unwrap();
+        assert_eq!(report.resumed_from, 8);
+        assert_eq!(std::fs::read(&path).unwrap(), content);
+
+        // 本地已完整且ETag一致,不再下载
+        write_partial(&path, &url, content, "v1", 20);
+        let report = AsyncHttpClient::new().download(&url, &path).start().await.unwrap();
+        assert_eq!((report.size, report.resumed_from), (20, 20));
+        assert_eq!(server.received(Method::HEAD, "/file"), 1);
+
+        // 长度相同但ETag已变化,从头下载
+        write_partial(&path, &url, b"stale-content-000000", "v0", 20);
+        let report = AsyncHttpClient::new().download(&url, &path).start().await.unwrap();
+        assert_eq!(report.resumed_from, 0);
+        assert_eq!(std::fs::read(&path).unwrap(), content);
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    /// 测试校验失败时删除临时文件
+    #[tokio::test]
+    async fn test_checksum_mismatch() {
+        let server = file_server(b"hello");
+        let path = temp_path("checksum", "file.txt");
+        let result = AsyncHttpClient::new()
+            .download(&server.url("/file"), &path)
+            .checksum(Checksum::md5("00000000000000000000000000000000"))
+            .start()
+            .await;
+        match result {
+            Err(HttpError::Checksum { actual, .. }) => assert_eq!(actual, "5d41402abc4b2a76b9719d911017c592"),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert!(!path.exists());
+        assert!(!with_suffix(&path, ".part").exists());
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    /// 测试下载的重试、总超时与错误状态码
+    #[tokio::test]
+    async fn test_retry_and_errors() {
+        let server = MockServer::start();
+        server.mock(Method::GET, "/flaky").times(2).respond(MockResponse::new(503));
+        server.mock(Method::GET, "/flaky").respond(MockResponse::text("done"));
+        server.mock(Method::GET, "/missing").respond(MockResponse::new(404).body("no such file"));
+        server.mock(Method::GET, "/slow").respond(MockResponse::text("late").delay(Duration::from_millis(500)));
+        let path = temp_path("retry", "file.txt");
+        let client = AsyncHttpClient::builder()
+            .retry(RetryPolicy::new(3).backoff(Duration::from_millis(1), Duration::from_millis(5)))
+            .build_async()
+            .unwrap();
+
+        client.download(&server.url("/flaky"), &path).start().await.unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), b"done");
+        assert_eq!(server.received(Method::GET, "/flaky"), 3);
+
+        match client.download(&server.url("/missing"), &path).start().await {
+            Err(HttpError::Status { status, body, .. }) => assert_eq!((status.as_u16(), &body[..]), (404, &b"no such file"[..])),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        let result = client.download(&server.url("/slow"), &path).timeout(Duration::from_millis(100)).retry(RetryPolicy::none()).start().await;
+        assert!(matches!(result, Err(HttpError::Timeout(_))), "{:?}", result);
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
@@ -0,0 +1,131 @@
+//! # HTTP客户端错误类型
+
+use std::fmt::{Display, Formatter};
+use std::time::Duration;
+use bytes::Bytes;
+use reqwest::{StatusCode, Url};
+
+/// HTTP客户端统一的结果类型
+pub type HttpResult<T> = Result<T, HttpError>;
+
+/// HTTP客户端错误
+#[derive(Debug)]
+pub enum HttpError {
+    /// 底层`reqwest`返回的错误(连接失败、总超时、协议错误等)
+    Request(reqwest::Error),
+    /// 客户端构建参数不合法,如非法的请求头、Base URL
+    Builder(String),
+    /// 请求地址不合法
+    InvalidUrl(String),
+    /// 请求超过了总超时时间
+    Timeout(Duration),
+    /// 等待响应数据超过了读超时时间
+    ReadTimeout(Duration),
+    /// 读写本地文件失败
+    Io(std::io::Error),
+    /// 下载内容的校验值与期望值不一致
+    Checksum {
+        expected: String,
+        actual: String,
+    },
+    /// 请求体序列化失败
+    Encode(serde_json::Error),
+    /// 响应体反序列化失败,保留了状态码与原始响应体便于排查
+    Decode {
+        source: serde_json::Error,
+        status: StatusCode,
+        body: Bytes,
+    },
+    /// 服务端返回了4xx或5xx状态码
+    Status {
+        status: StatusCode,
+        url: Url,
+        body: Bytes,
+    },
+    /// 等待限流令牌的时间超过了允许的最长等待时间
+    RateLimited(Duration),
+    /// 熔断器处于打开状态,请求被直接拒绝,附带距离尝试恢复的剩余时间
+    CircuitOpen(Duration),
+    /// 回放模式下没有与请求匹配的录制记录,或读写录制文件失败
+    Cassette(String),
+    /// 重定向次数超过了允许的最大次数
+    TooManyRedirects(Url),
+}
+
+impl HttpError {
+    /// 获取错误相关的响应状态码
+    pub fn status(&self) -> Option<StatusCode> {
+        match self {
+            HttpError::Request(e) => e.status(),
+            HttpError::Decode { status, .. } | HttpError::Status { status, .. } => Some(*status),
+            _ => None,
+        }
+    }
+
+    /// 获取错误相关的原始响应体
+    pub fn body(&self) -> Option<&Bytes> {
+        match self {
+            HttpError::Decode { body, .. } | HttpError::Status { body, .. } => Some(body),
+            _ => None,
+        }
+    }
+
+    /// 是否为错误状态码导致的错误
+    pub fn is_status(&self) -> bool {
+        matches!(self, HttpError::Status { .. })
+    }
+
+    /// 是否为超时错误
+    pub fn is_timeout(&self) -> bool {
+        match self {
+            HttpError::Request(e) => e.is_timeout(),
+            HttpError::Timeout(_) | HttpError::ReadTimeout(_) => true,
+            _ => false,
+        }
+    }
+}
+
+impl Display for HttpError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HttpError::Request(e) => write!(f, "http request error: {}", e),
+            HttpError::Builder(msg) => write!(f, "http client builder error: {}", msg),
+            HttpError::InvalidUrl(url) => write!(f, "invalid url: {}", url),
+            HttpError::Timeout(timeout) => write!(f, "request timed out after {:?}", timeout),
+            HttpError::ReadTimeout(timeout) => write!(f, "read timed out after {:?}", timeout),
+            HttpError::Io(e) => write!(f, "io error: {}", e),
+            HttpError::Checksum { expected, actual } => write!(f, "checksum mismatch: expected {}, got {}", expected, actual),
+            HttpError::Encode(e) => write!(f, "failed to encode request body: {}", e),
+            HttpError::Decode { source, status, .. } => write!(f, "failed to decode response body (status {}): {}", status, source),
+            HttpError::Status { status, url, .. } => write!(f, "http status error ({}) for url ({})", status, url),
+            HttpError::RateLimited(wait) => write!(f, "rate limited, next permit available in {:?}", wait),
+            HttpError::CircuitOpen(remaining) => write!(f, "circuit breaker is open, retry in {:?}", remaining),
+            HttpError::Cassette(msg) => write!(f, "cassette error: {}", msg),
+            HttpError::TooManyRedirects(url) => write!(f, "too many redirects, last url ({})", url),
+        }
+    }
+}
+
+impl std::error::Error for HttpError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            HttpError::Request(e) => Some(e),
+            HttpError::Io(e) => Some(e),
+            HttpError::Encode(e) => Some(e),
+            HttpError::Decode { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for HttpError {
+    fn from(e: reqwest::Error) -> Self {
+        HttpError::Request(e)
+    }
+}
+
+impl From<std::io::Error> for HttpError {
+    fn from(e: std::io::Error) -> Self {
+        HttpError::Io(e)
+    }
+}
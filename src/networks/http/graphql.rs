@@ -0,0 +1,560 @@
+//! # GraphQL客户端
+//!
+//! 发送`query`、`variables`与`operationName`,将`data`反序列化为指定类型,
+//! 服务端返回的`errors`(包括路径与位置)映射为[`GraphQlError::Graphql`],
+//! 并支持按照Relay规范(`pageInfo { hasNextPage endCursor }`)的游标分页.
+//!
+//! # Examples
+//! ```no_run
+//! use serde::Deserialize;
+//! use serde_json::json;
+//! use toys::networks::http::graphql::{GraphQlClient, GraphQlRequest};
+//!
+//! #[derive(Deserialize)]
+//! struct Data { user: User }
+//! #[derive(Deserialize)]
+//! struct User { name: String }
+//!
+//! let client = GraphQlClient::new("https://gateway.example.com/graphql");
+//! let request = GraphQlRequest::new("query GetUser($id: ID!) { user(id: $id) { name } }")
+//!     .variables(&json!({"id": "42"}))
+//!     .operation_name("GetUser");
+//! let data: Data = client.execute(request).unwrap();
+//! println!("{}", data.user.name);
+//!
+//! // 分页查询,游标通过$after变量传递,连接位于data.user.repositories
+//! let query = "query($after: String) { user(id: 42) { repositories(first: 50, after: $after) {
+//!     nodes { name } pageInfo { hasNextPage endCursor } } } }";
+//! for repository in client.paginate::<serde_json::Value>(GraphQlRequest::new(query), "user.repositories", "after") {
+//!     println!("{}", repository.unwrap()["name"]);
+//! }
+//! ```
+
+use std::fmt::{Debug, Display, Formatter};
+use std::marker::PhantomData;
+use futures_util::stream::BoxStream;
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+use crate::networks::http::{default_async_client, AsyncHttpClient, HttpClient, HttpError, HttpResponse};
+use crate::networks::http::client::block_on;
+use crate::networks::http::middleware::BoxFuture;
+use crate::networks::http::paginate::{LazyPages, PageSource};
+
+/// GraphQL请求的结果类型
+pub type GraphQlResult<T> = Result<T, GraphQlError>;
+
+/// GraphQL请求,变量序列化失败的错误会延迟到发送时返回
+#[derive(Debug, Clone, Serialize)]
+pub struct GraphQlRequest {
+    query: String,
+    #[serde(skip_serializing_if = "Map::is_empty")]
+    variables: Map<String, Value>,
+    #[serde(rename = "operationName", skip_serializing_if = "Option::is_none")]
+    operation_name: Option<String>,
+    #[serde(skip)]
+    error: Option<String>,
+}
+
+impl GraphQlRequest {
+    /// 创建请求
+    pub fn new(query: &str) -> Self {
+        GraphQlRequest { query: query.to_string(), variables: Map::new(), operation_name: None, error: None }
+    }
+
+    /// 设置全部变量,`variables`必须序列化为Json对象
+    pub fn variables<V: Serialize + ?Sized>(mut self, variables: &V) -> Self {
+        match serde_json::to_value(variables) {
+            Ok(Value::Object(variables)) => self.variables = variables,
+            Ok(Value::Null) => self.variables.clear(),
+            Ok(other) => self.error = Some(format!("variables must be an object, got {}", other)),
+            Err(e) => self.error = Some(e.to_string()),
+        }
+        self
+    }
+
+    /// 设置一个变量
+    pub fn variable<V: Serialize + ?Sized>(mut self, name: &str, value: &V) -> Self {
+        match serde_json::to_value(value) {
+            Ok(value) => {
+                self.variables.insert(name.to_string(), value);
+            }
+            Err(e) => self.error = Some(e.to_string()),
+        }
+        self
+    }
+
+    /// 设置操作名,查询文档中包含多个操作时必须指定
+    pub fn operation_name(mut self, name: &str) -> Self {
+        self.operation_name = Some(name.to_string());
+        self
+    }
+}
+
+/// 错误在查询文档中的位置
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Location {
+    pub line: u32,
+    pub column: u32,
+}
+
+/// 错误路径中的一段,字段名或列表下标
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum PathSegment {
+    Field(String),
+    Index(usize),
+}
+
+impl Display for PathSegment {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PathSegment::Field(field) => write!(f, "{}", field),
+            PathSegment::Index(index) => write!(f, "{}", index),
+        }
+    }
+}
+
+/// 服务端返回的一个错误
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResponseError {
+    // 错误描述
+    pub message: String,
+    // 错误在查询文档中的位置
+    #[serde(default)]
+    pub locations: Vec<Location>,
+    // 出错字段在响应数据中的路径
+    #[serde(default)]
+    pub path: Vec<PathSegment>,
+    // 服务端扩展信息,如错误码
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extensions: Option<Value>,
+}
+
+impl ResponseError {
+    /// 以`a.b.0`的形式返回路径
+    pub fn path_string(&self) -> String {
+        self.path.iter().map(PathSegment::to_string).collect::<Vec<_>>().join(".")
+    }
+}
+
+impl Display for ResponseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)?;
+        if !self.path.is_empty() {
+            write!(f, " at {}", self.path_string())?;
+        }
+        if let Some(location) = self.locations.first() {
+            write!(f, " (line {}, column {})", location.line, location.column)?;
+        }
+        Ok(())
+    }
+}
+
+/// GraphQL响应
+#[derive(Debug, Clone, Deserialize)]
+pub struct Response<T = Value> {
+    // 查询结果,请求无法执行时为None
+    pub data: Option<T>,
+    // 服务端返回的错误,可能与部分数据同时存在
+    #[serde(default)]
+    pub errors: Vec<ResponseError>,
+    // 服务端扩展信息,如耗时、限流额度
+    pub extensions: Option<Value>,
+}
+
+impl Response<Value> {
+    // 将数据反序列化为`T`
+    fn decode<T: DeserializeOwned>(self) -> GraphQlResult<Response<T>> {
+        let data = match self.data {
+            Some(data) => Some(T::deserialize(&data).map_err(|source| GraphQlError::Decode { source, data })?),
+            None => None,
+        };
+        Ok(Response { data, errors: self.errors, extensions: self.extensions })
+    }
+
+    // 存在错误时返回`GraphQlError::Graphql`,否则返回数据
+    fn into_data(self) -> GraphQlResult<Value> {
+        if !self.errors.is_empty() {
+            return Err(GraphQlError::Graphql { errors: self.errors, data: self.data });
+        }
+        self.data.ok_or_else(|| GraphQlError::MissingData("response contains neither data nor errors".to_string()))
+    }
+}
+
+/// GraphQL请求错误
+#[derive(Debug)]
+pub enum GraphQlError {
+    /// 服务端返回了错误,保留了同时返回的部分数据
+    Graphql {
+        errors: Vec<ResponseError>,
+        data: Option<Value>,
+    },
+    /// HTTP请求失败或响应体不是GraphQL响应
+    Http(Box<HttpError>),
+    /// 变量序列化失败
+    Encode(String),
+    /// 数据无法反序列化为期望的类型,保留了原始数据
+    Decode {
+        source: serde_json::Error,
+        data: Value,
+    },
+    /// 响应中缺少数据,或分页查询时找不到连接对象
+    MissingData(String),
+}
+
+impl GraphQlError {
+    /// 服务端返回的错误
+    pub fn errors(&self) -> &[ResponseError] {
+        match self {
+            GraphQlError::Graphql { errors, .. } => errors,
+            _ => &[],
+        }
+    }
+}
+
+impl Display for GraphQlError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GraphQlError::Graphql { errors, .. } => {
+                let messages: Vec<String> = errors.iter().map(ResponseError::to_string).collect();
+                write!(f, "graphql errors: {}", messages.join("; "))
+            }
+            GraphQlError::Http(e) => write!(f, "graphql transport error: {}", e),
+            GraphQlError::Encode(msg) => write!(f, "failed to encode graphql variables: {}", msg),
+            GraphQlError::Decode { source, .. } => write!(f, "failed to decode graphql data: {}", source),
+            GraphQlError::MissingData(msg) => write!(f, "missing graphql data: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for GraphQlError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            GraphQlError::Http(e) => Some(e.as_ref()),
+            GraphQlError::Decode { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<HttpError> for GraphQlError {
+    fn from(e: HttpError) -> Self {
+        GraphQlError::Http(Box::new(e))
+    }
+}
+
+/// 异步GraphQL客户端
+#[derive(Debug, Clone)]
+pub struct AsyncGraphQlClient {
+    client: AsyncHttpClient,
+    url: String,
+}
+
+impl AsyncGraphQlClient {
+    /// 使用默认异步客户端创建
+    pub fn new(url: &str) -> Self {
+        AsyncGraphQlClient::with_client(default_async_client().clone(), url)
+    }
+
+    /// 使用指定的HTTP客户端创建,认证等请求头可以通过客户端的默认请求头或中间件设置
+    pub fn with_client(client: AsyncHttpClient, url: &str) -> Self {
+        AsyncGraphQlClient { client, url: url.to_string() }
+    }
+
+    /// 服务地址
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// 执行请求并将`data`反序列化为`T`,响应中包含任何错误时返回`GraphQlError::Graphql`
+    pub async fn execute<T: DeserializeOwned>(&self, request: GraphQlRequest) -> GraphQlResult<T> {
+        decode(self.send(&request).await?.into_data()?)
+    }
+
+    /// 执行请求并返回完整响应,用于处理部分数据与错误同时存在的情况
+    pub async fn execute_response<T: DeserializeOwned>(&self, request: GraphQlRequest) -> GraphQlResult<Response<T>> {
+        self.send(&request).await?.decode()
+    }
+
+    /// 游标分页查询.`connection`为连接对象在`data`中的路径(以`.`分隔),
+    /// 下一页的游标通过名为`cursor_variable`的变量传递
+    pub fn paginate<T>(&self, request: GraphQlRequest, connection: &str, cursor_variable: &str) -> Pages<T> where
+        T: DeserializeOwned + Send + 'static
+    {
+        let source = PageState {
+            client: self.clone(),
+            request: Some(request),
+            connection: connection.split('.').filter(|s| !s.is_empty()).map(str::to_string).collect(),
+            cursor_variable: cursor_variable.to_string(),
+        };
+        Pages { pages: LazyPages::new(source, None), _marker: PhantomData }
+    }
+
+    // 发送请求.服务端可能以4xx状态码返回错误,因此优先按GraphQL响应解析
+    async fn send(&self, request: &GraphQlRequest) -> GraphQlResult<Response<Value>> {
+        if let Some(error) = &request.error {
+            return Err(GraphQlError::Encode(error.clone()));
+        }
+        let response = self.client.post(&self.url).json(request).send().await?;
+        match serde_json::from_slice::<Response<Value>>(&response.body) {
+            Ok(body) if body.data.is_some() || !body.errors.is_empty() => Ok(body),
+            Ok(_) => {
+                response.error_for_status()?;
+                Err(GraphQlError::MissingData("response contains neither data nor errors".to_string()))
+            }
+            Err(source) => {
+                let HttpResponse { status, body, .. } = response.error_for_status()?;
+                Err(HttpError::Decode { source, status, body }.into())
+            }
+        }
+    }
+}
+
+/// 同步GraphQL客户端,与`AsyncGraphQlClient`提供相同的接口
+#[derive(Debug, Clone)]
+pub struct GraphQlClient {
+    inner: AsyncGraphQlClient,
+}
+
+impl GraphQlClient {
+    /// 使用默认异步客户端创建
+    pub fn new(url: &str) -> Self {
+        GraphQlClient { inner: AsyncGraphQlClient::new(url) }
+    }
+
+    /// 使用指定的HTTP客户端创建
+    pub fn with_client(client: &HttpClient, url: &str) -> Self {
+        GraphQlClient { inner: AsyncGraphQlClient::with_client(client.as_async().clone(), url) }
+    }
+
+    /// 获取内部使用的异步客户端
+    pub fn as_async(&self) -> &AsyncGraphQlClient {
+        &self.inner
+    }
+
+    /// 执行请求并将`data`反序列化为`T`
+    pub fn execute<T: DeserializeOwned>(&self, request: GraphQlRequest) -> GraphQlResult<T> {
+        decode(self.send(request)?.into_data()?)
+    }
+
+    /// 执行请求并返回完整响应
+    pub fn execute_response<T: DeserializeOwned>(&self, request: GraphQlRequest) -> GraphQlResult<Response<T>> {
+        self.send(request)?.decode()
+    }
+
+    /// 游标分页查询,参数见[`AsyncGraphQlClient::paginate`]
+    pub fn paginate<T>(&self, request: GraphQlRequest, connection: &str, cursor_variable: &str) -> Pages<T> where
+        T: DeserializeOwned + Send + 'static
+    {
+        self.inner.paginate(request, connection, cursor_variable)
+    }
+
+    fn send(&self, request: GraphQlRequest) -> GraphQlResult<Response<Value>> {
+        let inner = self.inner.clone();
+        block_on(async move { inner.send(&request).await })
+    }
+}
+
+/// 游标分页查询的结果,按需逐页请求.
+/// 同步代码可以直接作为迭代器使用,异步代码使用[`Pages::stream`].出错后不再继续请求
+pub struct Pages<T> {
+    pages: LazyPages<PageState>,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Debug for Pages<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Pages").field("fetched", &self.pages.fetched()).finish()
+    }
+}
+
+impl<T: DeserializeOwned + Send + 'static> Pages<T> {
+    /// 设置最多请求的页数,防止服务端游标异常时无限请求
+    pub fn max_pages(mut self, max_pages: usize) -> Self {
+        self.pages.set_max_pages(max_pages);
+        self
+    }
+
+    /// 获取异步的节点流
+    pub fn stream(self) -> BoxStream<'static, GraphQlResult<T>> {
+        self.pages.stream().map(|item| item.and_then(decode)).boxed()
+    }
+}
+
+impl<T: DeserializeOwned + Send + 'static> Iterator for Pages<T> {
+    type Item = GraphQlResult<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.pages.next_blocking().map(|item| item.and_then(decode))
+    }
+}
+
+// 分页查询的状态
+struct PageState {
+    client: AsyncGraphQlClient,
+    // 下一页的请求,None表示已经结束
+    request: Option<GraphQlRequest>,
+    // 连接对象在data中的路径
+    connection: Vec<String>,
+    cursor_variable: String,
+}
+
+impl PageSource for PageState {
+    type Error = GraphQlError;
+
+    fn fetch(&mut self) -> BoxFuture<'_, GraphQlResult<Option<Vec<Value>>>> {
+        Box::pin(async move {
+            match self.request.take() {
+                Some(request) => self.fetch_page(request).await.map(Some),
+                None => Ok(None),
+            }
+        })
+    }
+}
+
+impl PageState {
+    // 请求一页节点,存在下一页时将游标写入下一页请求的变量
+    async fn fetch_page(&mut self, mut request: GraphQlRequest) -> GraphQlResult<Vec<Value>> {
+        let data = self.client.send(&request).await?.into_data()?;
+        let connection = self.connection.iter()
+            .try_fold(&data, |value, field| value.get(field))
+            .filter(|connection| connection.is_object())
+            .ok_or_else(|| GraphQlError::MissingData(format!("connection {} not found", self.connection.join("."))))?;
+        // 支持`nodes`与`edges { node }`两种形式
+        let nodes = match (connection.get("nodes"), connection.get("edges")) {
+            (Some(Value::Array(nodes)), _) => nodes.clone(),
+            (_, Some(Value::Array(edges))) => edges.iter().filter_map(|edge| edge.get("node").cloned()).collect(),
+            _ => return Err(GraphQlError::MissingData("connection has neither nodes nor edges".to_string())),
+        };
+        let page_info = connection.get("pageInfo");
+        let has_next = page_info.and_then(|info| info.get("hasNextPage")).and_then(Value::as_bool).unwrap_or(false);
+        if let Some(cursor) = page_info.and_then(|info| info.get("endCursor")).filter(|cursor| !cursor.is_null()) {
+            if has_next {
+                request.variables.insert(self.cursor_variable.clone(), cursor.clone());
+                self.request = Some(request);
+            }
+        }
+        Ok(nodes)
+    }
+}
+
+fn decode<T: DeserializeOwned>(data: Value) -> GraphQlResult<T> {
+    T::deserialize(&data).map_err(|source| GraphQlError::Decode { source, data })
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::StreamExt;
+    use reqwest::Method;
+    use serde::Deserialize;
+    use serde_json::{json, Value};
+    use crate::networks::http::graphql::{AsyncGraphQlClient, GraphQlClient, GraphQlError, GraphQlRequest, Location, PathSegment};
+    use crate::networks::http::mock::{MockRequest, MockResponse, MockServer};
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct User {
+        name: String,
+    }
+
+    /// 测试请求体、类型化数据与结构化错误
+    #[test]
+    fn test_execute() {
+        #[derive(Debug, Deserialize)]
+        struct Data { user: User }
+
+        let server = MockServer::start();
+        server.mock(Method::POST, "/graphql").respond_with(|request| {
+            let body: Value = request.json().unwrap();
+            match body["variables"]["id"].as_str() {
+                Some("1") => MockResponse::json(&json!({"data": {"user": {"name": "alice"}}})),
+                _ => MockResponse::json(&json!({
+                    "data": {"user": null},
+                    "errors": [{"message": "user not found", "locations": [{"line": 1, "column": 30}],
+                        "path": ["user", 0], "extensions": {"code": "NOT_FOUND"}}]
+                })),
+            }
+        });
+        server.mock(Method::POST, "/invalid").respond(MockResponse::new(400)
+            .header("Content-Type", "application/json")
+            .body(r#"{"errors":[{"message":"Syntax Error: Expected Name"}]}"#));
+
+        let client = GraphQlClient::new(&server.url("/graphql"));
+        let query = "query GetUser($id: ID!) { user(id: $id) { name } }";
+        let request = GraphQlRequest::new(query).variable("id", "1").operation_name("GetUser");
+        let data: Data = client.execute(request).unwrap();
+        assert_eq!(data.user.name, "alice");
+        let body: Value = server.requests()[0].json().unwrap();
+        assert_eq!(body, json!({"query": query, "variables": {"id": "1"}, "operationName": "GetUser"}));
+
+        let error = client.execute::<Data>(GraphQlRequest::new(query).variables(&json!({"id": "2"}))).unwrap_err();
+        let errors = error.errors();
+        assert_eq!(errors[0].path, vec![PathSegment::Field("user".to_string()), PathSegment::Index(0)]);
+        assert_eq!(errors[0].locations, vec![Location { line: 1, column: 30 }]);
+        assert_eq!(error.to_string(), "graphql errors: user not found at user.0 (line 1, column 30)");
+        assert!(matches!(error, GraphQlError::Graphql { data: Some(data), .. } if data["user"].is_null()));
+
+        let partial = client.execute_response::<Value>(GraphQlRequest::new(query).variable("id", "2")).unwrap();
+        assert_eq!(partial.errors[0].extensions, Some(json!({"code": "NOT_FOUND"})));
+
+        let error = GraphQlClient::new(&server.url("/invalid")).execute::<Value>(GraphQlRequest::new("{")).unwrap_err();
+        assert_eq!(error.errors()[0].message, "Syntax Error: Expected Name");
+        let error = client.execute::<Value>(GraphQlRequest::new(query).variables(&[1, 2])).unwrap_err();
+        assert!(matches!(error, GraphQlError::Encode(_)));
+    }
+
+    // 共5个用户,每页2个,游标为下一页起始下标;after为"edges"时以edges形式返回
+    fn users_page(request: &MockRequest) -> MockResponse {
+        let body: Value = request.json().unwrap();
+        let start: usize = body["variables"]["after"].as_str().map_or(0, |cursor| cursor.parse().unwrap());
+        let names: Vec<Value> = (start..5.min(start + 2)).map(|i| json!({"name": format!("user{}", i)})).collect();
+        let end = start + names.len();
+        let page_info = json!({"hasNextPage": end < 5, "endCursor": end.to_string()});
+        let connection = if start == 2 {
+            json!({"edges": names.into_iter().map(|node| json!({"node": node})).collect::<Vec<_>>(), "pageInfo": page_info})
+        } else {
+            json!({"nodes": names, "pageInfo": page_info})
+        };
+        MockResponse::json(&json!({"data": {"org": {"users": connection}}}))
+    }
+
+    /// 测试同步迭代器分页与最大页数限制
+    #[test]
+    fn test_paginate() {
+        let server = MockServer::start();
+        server.mock(Method::POST, "/graphql").respond_with(users_page);
+        let client = GraphQlClient::new(&server.url("/graphql"));
+        let query = "query($after: String) { org { users(first: 2, after: $after) { nodes { name } pageInfo { hasNextPage endCursor } } } }";
+
+        let users: Vec<User> = client.paginate(GraphQlRequest::new(query), "org.users", "after")
+            .collect::<Result<_, _>>()
+            .unwrap();
+        let names: Vec<&str> = users.iter().map(|user| user.name.as_str()).collect();
+        assert_eq!(names, vec!["user0", "user1", "user2", "user3", "user4"]);
+        assert_eq!(server.requests().len(), 3);
+
+        let limited = client.paginate::<User>(GraphQlRequest::new(query), "org.users", "after").max_pages(2);
+        assert_eq!(limited.count(), 4);
+        let mut missing = client.paginate::<User>(GraphQlRequest::new(query), "org.members", "after");
+        assert!(matches!(missing.next(), Some(Err(GraphQlError::MissingData(_)))));
+        assert!(missing.next().is_none());
+    }
+
+    /// 测试异步分页流
+    #[tokio::test]
+    async fn test_paginate_stream() {
+        let server = MockServer::start();
+        server.mock(Method::POST, "/graphql").respond_with(users_page);
+        let client = AsyncGraphQlClient::new(&server.url("/graphql"));
+        let request = GraphQlRequest::new("query($after: String) { org { users { nodes { name } } } }");
+        let users: Vec<User> = client.paginate(request, "org.users", "after")
+            .stream()
+            .map(Result::unwrap)
+            .collect()
+            .await;
+        assert_eq!(users.len(), 5);
+        let cursors: Vec<Value> = server.requests().iter().map(|r| r.json::<Value>().unwrap()["variables"]["after"].clone()).collect();
+        assert_eq!(cursors, vec![Value::Null, json!("2"), json!("4")]);
+    }
+}
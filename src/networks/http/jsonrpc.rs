@@ -0,0 +1,602 @@
+//! # JSON-RPC 2.0客户端
+//!
+//! 基于HTTP客户端的JSON-RPC 2.0实现,支持类型化调用、通知、批量请求,
+//! 响应按照`id`与请求对应,服务端返回的错误对象映射为[`RpcError::Rpc`].
+//!
+//! # Examples
+//! ```no_run
+//! use serde::Deserialize;
+//! use toys::networks::http::jsonrpc::JsonRpcClient;
+//!
+//! #[derive(Deserialize)]
+//! struct Block { number: u64 }
+//!
+//! let client = JsonRpcClient::new("http://127.0.0.1:8545/rpc");
+//! let block: Block = client.call("chain.latestBlock", ()).unwrap();
+//! let sum: i64 = client.call("math.add", (1, 2)).unwrap();
+//! client.notify("log.write", ("started",)).unwrap();
+//!
+//! let mut batch = client.batch();
+//! let a = batch.call::<i64, _>("math.add", (1, 2));
+//! let b = batch.call::<i64, _>("math.add", (3, 4));
+//! let results = client.send_batch(batch).unwrap();
+//! assert_eq!(results.get(&a).unwrap() + results.get(&b).unwrap(), 10);
+//! ```
+
+use std::collections::HashMap;
+use std::fmt::{Debug, Display, Formatter};
+use std::marker::PhantomData;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use serde::{Deserialize, Serialize};
+use serde::de::DeserializeOwned;
+use serde_json::{json, Map, Value};
+use crate::networks::http::{default_async_client, AsyncHttpClient, HttpClient, HttpError, HttpResponse};
+use crate::networks::http::client::block_on;
+
+/// JSON-RPC调用的结果类型
+pub type RpcResult<T> = Result<T, RpcError>;
+
+/// 协议版本
+const VERSION: &str = "2.0";
+
+/// 服务端返回的错误对象
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ErrorObject {
+    // 错误码
+    pub code: i64,
+    // 错误描述
+    pub message: String,
+    // 服务端附加的错误信息
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<Value>,
+}
+
+/// 按照规范划分的错误码类别
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// -32700,服务端无法解析请求Json
+    ParseError,
+    /// -32600,请求不是合法的请求对象
+    InvalidRequest,
+    /// -32601,方法不存在
+    MethodNotFound,
+    /// -32602,参数不合法
+    InvalidParams,
+    /// -32603,服务端内部错误
+    InternalError,
+    /// -32099至-32000,实现自定义的服务端错误
+    ServerError,
+    /// 其余错误码,由应用自行定义
+    Application,
+}
+
+impl ErrorObject {
+    /// 错误码类别
+    pub fn kind(&self) -> ErrorKind {
+        match self.code {
+            -32700 => ErrorKind::ParseError,
+            -32600 => ErrorKind::InvalidRequest,
+            -32601 => ErrorKind::MethodNotFound,
+            -32602 => ErrorKind::InvalidParams,
+            -32603 => ErrorKind::InternalError,
+            -32099..=-32000 => ErrorKind::ServerError,
+            _ => ErrorKind::Application,
+        }
+    }
+
+    /// 将附加的错误信息反序列化为`T`,不存在或格式不符时返回None
+    pub fn data_as<T: DeserializeOwned>(&self) -> Option<T> {
+        self.data.clone().and_then(|data| serde_json::from_value(data).ok())
+    }
+}
+
+impl Display for ErrorObject {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} (code {})", self.message, self.code)
+    }
+}
+
+/// JSON-RPC调用错误
+#[derive(Debug)]
+pub enum RpcError {
+    /// 服务端返回了错误对象
+    Rpc(ErrorObject),
+    /// HTTP请求失败或响应体不是Json
+    Http(Box<HttpError>),
+    /// 请求参数序列化失败
+    Encode(serde_json::Error),
+    /// 调用结果无法反序列化为期望的类型,保留了原始结果
+    Decode {
+        source: serde_json::Error,
+        result: Value,
+    },
+    /// 响应不符合JSON-RPC 2.0规范,如缺少`result`与`error`、`id`与请求不对应
+    InvalidResponse(String),
+}
+
+impl RpcError {
+    /// 服务端返回的错误对象
+    pub fn as_rpc(&self) -> Option<&ErrorObject> {
+        match self {
+            RpcError::Rpc(error) => Some(error),
+            _ => None,
+        }
+    }
+
+    /// 服务端返回的错误码
+    pub fn code(&self) -> Option<i64> {
+        self.as_rpc().map(|error| error.code)
+    }
+}
+
+impl Display for RpcError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RpcError::Rpc(error) => write!(f, "json-rpc error: {}", error),
+            RpcError::Http(e) => write!(f, "json-rpc transport error: {}", e),
+            RpcError::Encode(e) => write!(f, "failed to encode json-rpc params: {}", e),
+            RpcError::Decode { source, .. } => write!(f, "failed to decode json-rpc result: {}", source),
+            RpcError::InvalidResponse(msg) => write!(f, "invalid json-rpc response: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for RpcError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RpcError::Http(e) => Some(e.as_ref()),
+            RpcError::Encode(e) => Some(e),
+            RpcError::Decode { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<HttpError> for RpcError {
+    fn from(e: HttpError) -> Self {
+        RpcError::Http(Box::new(e))
+    }
+}
+
+/// 异步JSON-RPC客户端,克隆后共享请求id计数
+#[derive(Debug, Clone)]
+pub struct AsyncJsonRpcClient {
+    client: AsyncHttpClient,
+    url: String,
+    ids: Arc<AtomicU64>,
+}
+
+impl AsyncJsonRpcClient {
+    /// 使用默认异步客户端创建
+    pub fn new(url: &str) -> Self {
+        AsyncJsonRpcClient::with_client(default_async_client().clone(), url)
+    }
+
+    /// 使用指定的HTTP客户端创建,可以复用客户端的中间件、认证与超时配置
+    pub fn with_client(client: AsyncHttpClient, url: &str) -> Self {
+        AsyncJsonRpcClient { client, url: url.to_string(), ids: Arc::new(AtomicU64::new(1)) }
+    }
+
+    /// 服务地址
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// 调用方法.`params`为元组、数组时按位置传参,为结构体、Map时按名称传参,
+    /// 为`()`时不传参,其余单个值会被包装为只有一个元素的数组
+    pub async fn call<R, P>(&self, method: &str, params: P) -> RpcResult<R> where
+        R: DeserializeOwned,
+        P: Serialize
+    {
+        decode(self.call_value(method, to_params(&params)?).await?)
+    }
+
+    /// 发送通知,服务端不会返回结果
+    pub async fn notify<P: Serialize>(&self, method: &str, params: P) -> RpcResult<()> {
+        self.post(request(method, to_params(&params)?, None)).await.map(|_| ())
+    }
+
+    /// 创建批量请求
+    pub fn batch(&self) -> Batch {
+        Batch { ids: self.ids.clone(), requests: Vec::new(), pending: Vec::new(), error: None }
+    }
+
+    /// 发送批量请求,结果按照id与调用对应,与服务端返回的顺序无关
+    pub async fn send_batch(&self, batch: Batch) -> RpcResult<BatchResults> {
+        if let Some(error) = batch.error {
+            return Err(error);
+        }
+        let mut results = BatchResults { results: HashMap::new() };
+        if batch.requests.is_empty() {
+            return Ok(results);
+        }
+        let responses = match self.post(Value::Array(batch.requests)).await? {
+            Some(Value::Array(responses)) => responses,
+            // 整个批量请求被拒绝时服务端只返回一个错误对象
+            Some(response) => return match parse_response(response)? {
+                (_, Err(error)) => Err(RpcError::Rpc(error)),
+                (_, Ok(_)) => Err(RpcError::InvalidResponse("expected an array in response to batch".to_string())),
+            },
+            None if batch.pending.is_empty() => return Ok(results),
+            None => return Err(RpcError::InvalidResponse("empty response to batch".to_string())),
+        };
+        for response in responses {
+            let (id, result) = parse_response(response)?;
+            match id.as_u64().filter(|id| batch.pending.contains(id)) {
+                Some(id) => {
+                    results.results.insert(id, result);
+                }
+                None if id.is_null() => {}
+                None => return Err(RpcError::InvalidResponse(format!("unexpected response id {}", id))),
+            }
+        }
+        Ok(results)
+    }
+
+    // 调用方法并返回未解码的结果
+    async fn call_value(&self, method: &str, params: Option<Value>) -> RpcResult<Value> {
+        let id = self.ids.fetch_add(1, Ordering::Relaxed);
+        let response = self.post(request(method, params, Some(id))).await?
+            .ok_or_else(|| RpcError::InvalidResponse("empty response".to_string()))?;
+        let (response_id, result) = parse_response(response)?;
+        if response_id != id {
+            // 服务端无法解析请求时返回的错误对象不带id
+            return match result {
+                Err(error) if response_id.is_null() => Err(RpcError::Rpc(error)),
+                _ => Err(RpcError::InvalidResponse(format!("response id {} does not match request id {}", response_id, id))),
+            };
+        }
+        result.map_err(RpcError::Rpc)
+    }
+
+    // 发送请求体,响应体为空时返回None.服务端可能以错误状态码返回错误对象,因此优先按Json解析
+    async fn post(&self, payload: Value) -> RpcResult<Option<Value>> {
+        let response = self.client.post(&self.url).json(&payload).send().await?;
+        if response.body.iter().all(u8::is_ascii_whitespace) {
+            response.error_for_status()?;
+            return Ok(None);
+        }
+        match serde_json::from_slice(&response.body) {
+            Ok(value) => Ok(Some(value)),
+            Err(source) => {
+                let HttpResponse { status, body, .. } = response.error_for_status()?;
+                Err(HttpError::Decode { source, status, body }.into())
+            }
+        }
+    }
+}
+
+/// 同步JSON-RPC客户端,与`AsyncJsonRpcClient`提供相同的接口
+#[derive(Debug, Clone)]
+pub struct JsonRpcClient {
+    inner: AsyncJsonRpcClient,
+}
+
+impl JsonRpcClient {
+    /// 使用默认异步客户端创建
+    pub fn new(url: &str) -> Self {
+        JsonRpcClient { inner: AsyncJsonRpcClient::new(url) }
+    }
+
+    /// 使用指定的HTTP客户端创建
+    pub fn with_client(client: &HttpClient, url: &str) -> Self {
+        JsonRpcClient { inner: AsyncJsonRpcClient::with_client(client.as_async().clone(), url) }
+    }
+
+    /// 获取内部使用的异步客户端
+    pub fn as_async(&self) -> &AsyncJsonRpcClient {
+        &self.inner
+    }
+
+    /// 调用方法,参数规则见[`AsyncJsonRpcClient::call`]
+    pub fn call<R, P>(&self, method: &str, params: P) -> RpcResult<R> where
+        R: DeserializeOwned,
+        P: Serialize
+    {
+        let params = to_params(&params)?;
+        let (inner, method) = (self.inner.clone(), method.to_string());
+        decode(block_on(async move { inner.call_value(&method, params).await })?)
+    }
+
+    /// 发送通知,服务端不会返回结果
+    pub fn notify<P: Serialize>(&self, method: &str, params: P) -> RpcResult<()> {
+        let payload = request(method, to_params(&params)?, None);
+        let inner = self.inner.clone();
+        block_on(async move { inner.post(payload).await }).map(|_| ())
+    }
+
+    /// 创建批量请求
+    pub fn batch(&self) -> Batch {
+        self.inner.batch()
+    }
+
+    /// 发送批量请求
+    pub fn send_batch(&self, batch: Batch) -> RpcResult<BatchResults> {
+        let inner = self.inner.clone();
+        block_on(async move { inner.send_batch(batch).await })
+    }
+}
+
+/// 批量请求,参数序列化失败的错误会延迟到发送时返回
+pub struct Batch {
+    ids: Arc<AtomicU64>,
+    requests: Vec<Value>,
+    // 需要返回结果的请求id
+    pending: Vec<u64>,
+    error: Option<RpcError>,
+}
+
+impl Debug for Batch {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Batch")
+            .field("requests", &self.requests)
+            .field("error", &self.error)
+            .finish()
+    }
+}
+
+impl Batch {
+    /// 添加一次调用,返回用于从结果中取值的句柄
+    pub fn call<R, P>(&mut self, method: &str, params: P) -> BatchCall<R> where
+        R: DeserializeOwned,
+        P: Serialize
+    {
+        let id = self.ids.fetch_add(1, Ordering::Relaxed);
+        self.push(method, &params, Some(id));
+        BatchCall { id, _marker: PhantomData }
+    }
+
+    /// 添加一条通知
+    pub fn notify<P: Serialize>(&mut self, method: &str, params: P) {
+        self.push(method, &params, None);
+    }
+
+    /// 请求数量(包括通知)
+    pub fn len(&self) -> usize {
+        self.requests.len()
+    }
+
+    /// 是否没有任何请求
+    pub fn is_empty(&self) -> bool {
+        self.requests.is_empty()
+    }
+
+    fn push<P: Serialize>(&mut self, method: &str, params: &P, id: Option<u64>) {
+        match to_params(params) {
+            Ok(params) => {
+                self.requests.push(request(method, params, id));
+                self.pending.extend(id);
+            }
+            Err(e) => {
+                self.error.get_or_insert(e);
+            }
+        }
+    }
+}
+
+/// 批量请求中一次调用的句柄,`R`为期望的结果类型
+pub struct BatchCall<R> {
+    id: u64,
+    _marker: PhantomData<fn() -> R>,
+}
+
+impl<R> BatchCall<R> {
+    /// 请求id
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+}
+
+impl<R> Debug for BatchCall<R> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BatchCall").field("id", &self.id).finish()
+    }
+}
+
+impl<R> Clone for BatchCall<R> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<R> Copy for BatchCall<R> {}
+
+/// 批量请求的结果
+#[derive(Debug, Clone, Default)]
+pub struct BatchResults {
+    results: HashMap<u64, Result<Value, ErrorObject>>,
+}
+
+impl BatchResults {
+    /// 获取一次调用的结果,服务端没有返回对应id的响应时返回`RpcError::InvalidResponse`
+    pub fn get<R: DeserializeOwned>(&self, call: &BatchCall<R>) -> RpcResult<R> {
+        match self.results.get(&call.id) {
+            Some(Ok(result)) => decode(result.clone()),
+            Some(Err(error)) => Err(RpcError::Rpc(error.clone())),
+            None => Err(RpcError::InvalidResponse(format!("missing response for id {}", call.id))),
+        }
+    }
+
+    /// 收到的响应数量
+    pub fn len(&self) -> usize {
+        self.results.len()
+    }
+
+    /// 是否没有收到任何响应
+    pub fn is_empty(&self) -> bool {
+        self.results.is_empty()
+    }
+}
+
+// 构造请求对象,没有id的请求为通知
+fn request(method: &str, params: Option<Value>, id: Option<u64>) -> Value {
+    let mut request = Map::new();
+    request.insert("jsonrpc".to_string(), json!(VERSION));
+    request.insert("method".to_string(), json!(method));
+    if let Some(params) = params {
+        request.insert("params".to_string(), params);
+    }
+    if let Some(id) = id {
+        request.insert("id".to_string(), json!(id));
+    }
+    Value::Object(request)
+}
+
+// 规范要求params为数组或对象
+fn to_params<P: Serialize + ?Sized>(params: &P) -> RpcResult<Option<Value>> {
+    match serde_json::to_value(params).map_err(RpcError::Encode)? {
+        Value::Null => Ok(None),
+        params @ (Value::Array(_) | Value::Object(_)) => Ok(Some(params)),
+        param => Ok(Some(Value::Array(vec![param]))),
+    }
+}
+
+fn decode<R: DeserializeOwned>(result: Value) -> RpcResult<R> {
+    R::deserialize(&result).map_err(|source| RpcError::Decode { source, result })
+}
+
+// 解析响应对象,返回id与结果
+fn parse_response(response: Value) -> RpcResult<(Value, Result<Value, ErrorObject>)> {
+    let Value::Object(mut response) = response else {
+        return Err(RpcError::InvalidResponse(format!("expected an object, got {}", response)));
+    };
+    if response.get("jsonrpc").and_then(Value::as_str) != Some(VERSION) {
+        return Err(RpcError::InvalidResponse("missing or unsupported jsonrpc version".to_string()));
+    }
+    let id = response.remove("id").unwrap_or(Value::Null);
+    if let Some(error) = response.remove("error") {
+        let error = serde_json::from_value(error)
+            .map_err(|e| RpcError::InvalidResponse(format!("malformed error object: {}", e)))?;
+        return Ok((id, Err(error)));
+    }
+    match response.remove("result") {
+        Some(result) => Ok((id, Ok(result))),
+        None => Err(RpcError::InvalidResponse("missing both result and error".to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::Method;
+    use serde::Deserialize;
+    use serde_json::{json, Value};
+    use crate::networks::http::jsonrpc::{AsyncJsonRpcClient, ErrorKind, JsonRpcClient, RpcError};
+    use crate::networks::http::mock::{MockRequest, MockResponse, MockServer};
+
+    // 简单的JSON-RPC服务端,批量请求的响应顺序与请求相反
+    fn handle(request: &MockRequest) -> MockResponse {
+        fn dispatch(request: &Value) -> Option<Value> {
+            let id = request.get("id")?.clone();
+            let params = request.get("params").cloned().unwrap_or(Value::Null);
+            let result = match request["method"].as_str().unwrap_or("") {
+                "subtract" => match &params {
+                    Value::Array(args) => Ok(json!(args[0].as_i64().unwrap() - args[1].as_i64().unwrap())),
+                    _ => Ok(json!(params["minuend"].as_i64().unwrap() - params["subtrahend"].as_i64().unwrap())),
+                },
+                "echo" => Ok(params),
+                method => Err(json!({"code": -32601, "message": "Method not found", "data": {"method": method}})),
+            };
+            Some(match result {
+                Ok(result) => json!({"jsonrpc": "2.0", "result": result, "id": id}),
+                Err(error) => json!({"jsonrpc": "2.0", "error": error, "id": id}),
+            })
+        }
+        let response = match request.json::<Value>() {
+            Ok(Value::Array(requests)) => {
+                let responses: Vec<Value> = requests.iter().rev().filter_map(dispatch).collect();
+                (!responses.is_empty()).then_some(Value::Array(responses))
+            }
+            Ok(request) => dispatch(&request),
+            Err(_) => Some(json!({"jsonrpc": "2.0", "error": {"code": -32700, "message": "Parse error"}, "id": null})),
+        };
+        match response {
+            Some(response) => MockResponse::json(&response),
+            None => MockResponse::new(204),
+        }
+    }
+
+    fn server() -> MockServer {
+        let server = MockServer::start();
+        server.mock(Method::POST, "/rpc").respond_with(handle);
+        server
+    }
+
+    /// 测试按位置与按名称传参、id递增以及错误对象映射
+    #[tokio::test]
+    async fn test_call() {
+        #[derive(serde::Serialize)]
+        struct Subtract { minuend: i64, subtrahend: i64 }
+
+        let server = server();
+        let client = AsyncJsonRpcClient::new(&server.url("/rpc"));
+        assert_eq!(client.call::<i64, _>("subtract", (42, 23)).await.unwrap(), 19);
+        let named = Subtract { minuend: 23, subtrahend: 42 };
+        assert_eq!(client.call::<i64, _>("subtract", named).await.unwrap(), -19);
+        assert_eq!(client.call::<Vec<String>, _>("echo", "hello").await.unwrap(), vec!["hello"]);
+        assert_eq!(client.call::<Value, _>("echo", ()).await.unwrap(), Value::Null);
+
+        let ids: Vec<Value> = server.requests().iter().map(|r| r.json::<Value>().unwrap()["id"].clone()).collect();
+        assert_eq!(ids, vec![json!(1), json!(2), json!(3), json!(4)]);
+        assert!(server.requests()[3].json::<Value>().unwrap().get("params").is_none());
+
+        let error = client.call::<Value, _>("missing", ()).await.unwrap_err();
+        let object = error.as_rpc().unwrap();
+        assert_eq!((object.code, object.kind()), (-32601, ErrorKind::MethodNotFound));
+        #[derive(Deserialize)]
+        struct Data { method: String }
+        assert_eq!(object.data_as::<Data>().unwrap().method, "missing");
+
+        let error = client.call::<String, _>("subtract", (1, 2)).await.unwrap_err();
+        assert!(matches!(error, RpcError::Decode { result, .. } if result == json!(-1)));
+    }
+
+    /// 测试通知与批量请求的id对应
+    #[tokio::test]
+    async fn test_batch_and_notify() {
+        let server = server();
+        let client = AsyncJsonRpcClient::new(&server.url("/rpc"));
+        client.notify("update", [1, 2, 3]).await.unwrap();
+        let notification = server.requests()[0].json::<Value>().unwrap();
+        assert_eq!(notification, json!({"jsonrpc": "2.0", "method": "update", "params": [1, 2, 3]}));
+
+        let mut batch = client.batch();
+        let first = batch.call::<i64, _>("subtract", (10, 3));
+        batch.notify("update", ("ignored",));
+        let missing = batch.call::<Value, _>("missing", ());
+        let last = batch.call::<String, _>("echo", json!(["tail"]));
+        assert_eq!(batch.len(), 4);
+        let results = client.send_batch(batch).await.unwrap();
+        assert_eq!(results.len(), 3);
+        assert_eq!(results.get(&first).unwrap(), 7);
+        assert_eq!(results.get(&missing).unwrap_err().code(), Some(-32601));
+        assert!(matches!(results.get(&last), Err(RpcError::Decode { .. })));
+
+        let mut batch = client.batch();
+        batch.notify("update", ());
+        assert!(client.send_batch(batch).await.unwrap().is_empty());
+    }
+
+    /// 测试同步客户端以及不合规范的响应
+    #[test]
+    fn test_invalid_responses() {
+        let server = MockServer::start();
+        server.mock(Method::POST, "/mismatch").respond(MockResponse::json(&json!({"jsonrpc": "2.0", "result": 1, "id": 99})));
+        server.mock(Method::POST, "/incomplete").respond(MockResponse::json(&json!({"jsonrpc": "2.0", "id": 1})));
+        server.mock(Method::POST, "/html").respond(MockResponse::new(502).body("<html>Bad Gateway</html>"));
+        server.mock(Method::POST, "/rejected").respond(
+            MockResponse::new(500).header("Content-Type", "application/json")
+                .body(r#"{"jsonrpc":"2.0","error":{"code":-32603,"message":"Internal error"},"id":null}"#));
+
+        let call = |path: &str| JsonRpcClient::new(&server.url(path)).call::<i64, _>("ping", ()).unwrap_err();
+        assert!(matches!(call("/mismatch"), RpcError::InvalidResponse(msg) if msg.contains("does not match")));
+        assert!(matches!(call("/incomplete"), RpcError::InvalidResponse(_)));
+        assert!(matches!(call("/html"), RpcError::Http(e) if e.is_status()));
+        assert_eq!(call("/rejected").as_rpc().unwrap().kind(), ErrorKind::InternalError);
+
+        let client = JsonRpcClient::new(&server.url("/rejected"));
+        let mut batch = client.batch();
+        batch.call::<i64, _>("ping", ());
+        assert_eq!(client.send_batch(batch).unwrap_err().code(), Some(-32603));
+    }
+}
@@ -0,0 +1,242 @@
+//! # 客户端限流
+//!
+//! 基于令牌桶的限流器,可以限制整个客户端或每个主机的请求速率.
+//! 作为中间件添加到客户端,令牌不足时请求会等待,超过最长等待时间时返回错误.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use reqwest::Url;
+use crate::networks::http::error::{HttpError, HttpResult};
+use crate::networks::http::middleware::{BoxFuture, Middleware, Next};
+use crate::networks::http::request::Request;
+use crate::networks::http::response::HttpResponse;
+
+/// 令牌桶限流器,克隆后共享同一组令牌桶
+///
+/// # Examples
+/// ```
+/// use std::time::Duration;
+/// use toys::networks::http::HttpClient;
+/// use toys::networks::http::limiter::RateLimiter;
+/// let limiter = RateLimiter::per_host(10, Duration::from_secs(1))
+///     .burst(5)
+///     .max_wait(Duration::from_secs(3));
+/// let client = HttpClient::builder().middleware(limiter.clone()).build().unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    // 每秒补充的令牌数
+    rate: f64,
+    // 令牌桶容量,即允许的突发请求数
+    burst: f64,
+    // 是否为每个主机单独限流
+    per_host: bool,
+    // 等待令牌的最长时间,为None时一直等待
+    max_wait: Option<Duration>,
+    buckets: Arc<Mutex<HashMap<String, Bucket>>>,
+}
+
+// 令牌桶,令牌数可以为负数,表示已被预约的令牌
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl RateLimiter {
+    /// 限制整个客户端在`per`时间内最多发起`requests`个请求,突发请求数默认为`requests`
+    pub fn new(requests: u32, per: Duration) -> Self {
+        let requests = requests.max(1) as f64;
+        RateLimiter {
+            rate: requests / per.as_secs_f64().max(f64::EPSILON),
+            burst: requests,
+            per_host: false,
+            max_wait: None,
+            buckets: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// 限制每个主机在`per`时间内最多发起`requests`个请求
+    pub fn per_host(requests: u32, per: Duration) -> Self {
+        RateLimiter { per_host: true, ..RateLimiter::new(requests, per) }
+    }
+
+    /// 设置令牌桶容量,为1时请求会被均匀地错开
+    pub fn burst(mut self, burst: u32) -> Self {
+        self.burst = burst.max(1) as f64;
+        self
+    }
+
+    /// 设置等待令牌的最长时间,超过时返回[`HttpError::RateLimited`]
+    pub fn max_wait(mut self, max_wait: Duration) -> Self {
+        self.max_wait = Some(max_wait);
+        self
+    }
+
+    /// 获取一个令牌,令牌不足时等待
+    pub async fn acquire(&self, url: &Url) -> HttpResult<()> {
+        let wait = self.reserve(url)?;
+        if !wait.is_zero() {
+            let mut guard = Reservation { limiter: self, url, active: true };
+            tokio::time::sleep(wait).await;
+            guard.active = false;
+        }
+        Ok(())
+    }
+
+    /// 当前可用的令牌数,用于监控
+    pub fn available(&self, url: &Url) -> f64 {
+        let key = self.key(url);
+        let mut buckets = self.buckets.lock().unwrap();
+        match buckets.get_mut(&key) {
+            Some(bucket) => {
+                self.refill(bucket, Instant::now());
+                bucket.tokens.max(0.0)
+            }
+            None => self.burst,
+        }
+    }
+
+    // 预约一个令牌,返回需要等待的时间
+    fn reserve(&self, url: &Url) -> HttpResult<Duration> {
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap();
+        let bucket = buckets.entry(self.key(url)).or_insert(Bucket { tokens: self.burst, updated: now });
+        self.refill(bucket, now);
+        bucket.tokens -= 1.0;
+        if bucket.tokens >= 0.0 {
+            return Ok(Duration::ZERO);
+        }
+        let wait = Duration::from_secs_f64(-bucket.tokens / self.rate);
+        match self.max_wait {
+            Some(max_wait) if wait > max_wait => {
+                // 放弃预约,归还令牌
+                bucket.tokens += 1.0;
+                Err(HttpError::RateLimited(wait))
+            }
+            _ => Ok(wait),
+        }
+    }
+
+    // 归还预约的令牌
+    fn release(&self, url: &Url) {
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(bucket) = buckets.get_mut(&self.key(url)) {
+            self.refill(bucket, now);
+            bucket.tokens = (bucket.tokens + 1.0).min(self.burst);
+        }
+    }
+
+    // 按照流逝的时间补充令牌
+    fn refill(&self, bucket: &mut Bucket, now: Instant) {
+        let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.rate).min(self.burst);
+        bucket.updated = now;
+    }
+
+    // 令牌桶的键,不区分主机时所有请求共用一个令牌桶
+    fn key(&self, url: &Url) -> String {
+        if self.per_host {
+            format!("{}:{}", url.host_str().unwrap_or(""), url.port_or_known_default().unwrap_or(0))
+        } else {
+            String::new()
+        }
+    }
+}
+
+// 等待令牌期间被取消时归还预约的令牌
+struct Reservation<'a> {
+    limiter: &'a RateLimiter,
+    url: &'a Url,
+    active: bool,
+}
+
+impl Drop for Reservation<'_> {
+    fn drop(&mut self) {
+        if self.active {
+            self.limiter.release(self.url);
+        }
+    }
+}
+
+impl Middleware for RateLimiter {
+    fn prepare<'a>(&'a self, request: Request) -> BoxFuture<'a, HttpResult<Request>> {
+        Box::pin(async move {
+            self.acquire(&request.url).await?;
+            Ok(request)
+        })
+    }
+
+    fn handle<'a>(&'a self, request: Request, next: Next<'a>) -> BoxFuture<'a, HttpResult<HttpResponse>> {
+        Box::pin(async move {
+            self.acquire(&request.url).await?;
+            next.run(request).await
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+    use reqwest::{Method, Url};
+    use crate::networks::http::AsyncHttpClient;
+    use crate::networks::http::error::HttpError;
+    use crate::networks::http::limiter::RateLimiter;
+    use crate::networks::http::mock::{MockResponse, MockServer};
+    use crate::networks::http::retry::RetryPolicy;
+
+    /// 测试突发请求与令牌补充
+    #[tokio::test]
+    async fn test_burst_and_refill() {
+        let limiter = RateLimiter::new(20, Duration::from_secs(1)).burst(2);
+        let url = Url::parse("http://a.local/").unwrap();
+        let start = Instant::now();
+        for _ in 0..4 {
+            limiter.acquire(&url).await.unwrap();
+        }
+        // 前两个令牌立即可用,后两个各需等待50ms
+        assert!(start.elapsed() >= Duration::from_millis(100));
+        assert!(limiter.available(&url) < 1.0);
+        tokio::time::sleep(Duration::from_millis(120)).await;
+        assert_eq!(limiter.available(&url), 2.0);
+    }
+
+    /// 测试按主机限流与最长等待时间
+    #[tokio::test]
+    async fn test_per_host_and_max_wait() {
+        let limiter = RateLimiter::per_host(1, Duration::from_secs(10)).max_wait(Duration::from_millis(10));
+        let a = Url::parse("http://a.local/").unwrap();
+        let b = Url::parse("http://b.local/").unwrap();
+        limiter.acquire(&a).await.unwrap();
+        limiter.acquire(&b).await.unwrap();
+        assert!(matches!(limiter.acquire(&a).await, Err(HttpError::RateLimited(_))));
+        // 克隆后共享令牌桶
+        assert!(limiter.clone().available(&b) < 1.0);
+    }
+
+    /// 测试取消等待时归还令牌,以及每次重试都会获取令牌
+    #[tokio::test]
+    async fn test_cancel_and_retry() {
+        let limiter = RateLimiter::new(1, Duration::from_millis(200)).burst(1);
+        let url = Url::parse("http://a.local/").unwrap();
+        limiter.acquire(&url).await.unwrap();
+        assert!(tokio::time::timeout(Duration::from_millis(20), limiter.acquire(&url)).await.is_err());
+        let start = Instant::now();
+        limiter.acquire(&url).await.unwrap();
+        assert!(start.elapsed() < Duration::from_millis(300), "{:?}", start.elapsed());
+
+        let server = MockServer::start();
+        server.mock(Method::GET, "/").respond(MockResponse::new(503));
+        let limiter = RateLimiter::new(1, Duration::from_secs(10)).burst(10);
+        let client = AsyncHttpClient::builder()
+            .middleware(limiter.clone())
+            .retry(RetryPolicy::new(3).backoff(Duration::from_millis(1), Duration::from_millis(1)).jitter(false))
+            .build_async()
+            .unwrap();
+        client.get(&server.url("/")).send().await.unwrap();
+        let url = Url::parse(&server.url("/")).unwrap();
+        assert!(limiter.available(&url) < 7.5, "{}", limiter.available(&url));
+    }
+}
@@ -0,0 +1,378 @@
+//! # 请求中间件
+//!
+//! 中间件按照添加顺序组成调用链: 先添加的中间件最先处理请求、最后处理响应.
+//! 同步客户端与异步客户端共用同一套中间件.重试在调用链之外进行,每次尝试都会重新经过全部中间件.
+//! 下载、事件流等不读取完整响应体的流式请求不经过`handle`,只按顺序调用各中间件的`prepare`.
+
+use std::fmt::{Debug, Formatter};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::{Method, StatusCode, Url};
+use crate::networks::http::client::AsyncHttpClient;
+use crate::networks::http::error::{HttpError, HttpResult};
+use crate::networks::http::request::Request;
+use crate::networks::http::response::HttpResponse;
+
+/// 中间件使用的装箱Future
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// 请求中间件
+///
+/// 只需要修改请求或响应时实现`on_request`/`on_response`即可;
+/// 需要异步逻辑、包裹整个请求或直接返回响应时重写`handle`,
+/// 其中作用于请求的部分(如认证、限流)还需要重写`prepare`,以便同样作用于流式请求.
+pub trait Middleware: Send + Sync + 'static {
+    /// 请求发出前调用,返回错误时请求不会发出
+    fn on_request(&self, _request: &mut Request) -> HttpResult<()> {
+        Ok(())
+    }
+
+    /// 收到响应后调用
+    fn on_response(&self, _response: &mut HttpResponse) -> HttpResult<()> {
+        Ok(())
+    }
+
+    /// 流式请求发出前调用,默认调用`on_request`
+    fn prepare<'a>(&'a self, mut request: Request) -> BoxFuture<'a, HttpResult<Request>> {
+        Box::pin(async move {
+            self.on_request(&mut request)?;
+            Ok(request)
+        })
+    }
+
+    /// 处理一次请求,通过`next.run(request)`将请求交给后续的中间件
+    fn handle<'a>(&'a self, mut request: Request, next: Next<'a>) -> BoxFuture<'a, HttpResult<HttpResponse>> {
+        Box::pin(async move {
+            self.on_request(&mut request)?;
+            let mut response = next.run(request).await?;
+            self.on_response(&mut response)?;
+            Ok(response)
+        })
+    }
+}
+
+/// 中间件调用链中剩余的部分,可以复制后多次执行(如重试)
+#[derive(Clone, Copy)]
+pub struct Next<'a> {
+    client: &'a AsyncHttpClient,
+    middlewares: &'a [Arc<dyn Middleware>],
+}
+
+impl<'a> Next<'a> {
+    pub(crate) fn new(client: &'a AsyncHttpClient, middlewares: &'a [Arc<dyn Middleware>]) -> Self {
+        Next { client, middlewares }
+    }
+
+    /// 获取发送请求的客户端
+    pub fn client(&self) -> &'a AsyncHttpClient {
+        self.client
+    }
+
+    /// 执行后续的中间件,全部执行完后发送请求
+    pub fn run(self, request: Request) -> BoxFuture<'a, HttpResult<HttpResponse>> {
+        match self.middlewares.split_first() {
+            Some((first, rest)) => first.handle(request, Next::new(self.client, rest)),
+            None => Box::pin(self.client.send_after_middlewares(request)),
+        }
+    }
+}
+
+/// 请求日志中间件,默认通过`println!`输出
+pub struct LoggingMiddleware {
+    logger: Box<dyn Fn(&str) + Send + Sync>,
+}
+
+impl Default for LoggingMiddleware {
+    fn default() -> Self {
+        LoggingMiddleware::with_logger(|line| println!("{}", line))
+    }
+}
+
+impl LoggingMiddleware {
+    /// 创建输出到标准输出的日志中间件
+    pub fn new() -> Self {
+        LoggingMiddleware::default()
+    }
+
+    /// 使用自定义的日志输出函数
+    pub fn with_logger<F>(logger: F) -> Self where F: Fn(&str) + Send + Sync + 'static {
+        LoggingMiddleware { logger: Box::new(logger) }
+    }
+}
+
+impl Middleware for LoggingMiddleware {
+    fn handle<'a>(&'a self, request: Request, next: Next<'a>) -> BoxFuture<'a, HttpResult<HttpResponse>> {
+        Box::pin(async move {
+            let method = request.method.clone();
+            let url = request.url.clone();
+            (self.logger)(&format!("--> {} {}", method, url));
+            let start = Instant::now();
+            let result = next.run(request).await;
+            match &result {
+                Ok(response) => (self.logger)(&format!("<-- {} {} {} ({:?}, {} bytes)",
+                                                       method, url, response.status, start.elapsed(), response.body.len())),
+                Err(error) => (self.logger)(&format!("<-- {} {} failed ({:?}): {}", method, url, start.elapsed(), error)),
+            }
+            result
+        })
+    }
+}
+
+// 动态生成请求头值的函数
+type HeaderGenerator = Box<dyn Fn() -> String + Send + Sync>;
+
+/// 请求头注入中间件,可以注入固定值或每次请求动态生成的值(如Trace ID)
+///
+/// # Examples
+/// ```
+/// use toys::networks::http::middleware::HeaderMiddleware;
+/// let middleware = HeaderMiddleware::new()
+///     .header("Authorization", "Bearer token")
+///     .dynamic("X-Request-Id", || "generated-id".to_string());
+/// ```
+#[derive(Default)]
+pub struct HeaderMiddleware {
+    headers: HeaderMap,
+    generators: Vec<(HeaderName, HeaderGenerator)>,
+    // 是否覆盖请求中已经存在的同名请求头
+    overwrite: bool,
+    // 配置过程中出现的第一个错误,在发送时返回
+    error: Option<String>,
+}
+
+impl HeaderMiddleware {
+    /// 创建一个不覆盖已有请求头的注入中间件
+    pub fn new() -> Self {
+        HeaderMiddleware::default()
+    }
+
+    /// 注入固定值的请求头,名称或值不合法时发送请求会返回`HttpError::Builder`
+    pub fn header(mut self, key: &str, value: &str) -> Self {
+        match (HeaderName::from_bytes(key.as_bytes()), HeaderValue::from_str(value)) {
+            (Ok(name), Ok(value)) => {
+                self.headers.insert(name, value);
+            }
+            _ => self.set_error(format!("invalid header: {}: {}", key, value)),
+        }
+        self
+    }
+
+    /// 注入每次请求时动态生成的请求头,名称不合法时发送请求会返回`HttpError::Builder`
+    pub fn dynamic<F>(mut self, key: &str, generator: F) -> Self where F: Fn() -> String + Send + Sync + 'static {
+        match HeaderName::from_bytes(key.as_bytes()) {
+            Ok(name) => self.generators.push((name, Box::new(generator))),
+            Err(_) => self.set_error(format!("invalid header name: {}", key)),
+        }
+        self
+    }
+
+    /// 设置是否覆盖请求中已经存在的同名请求头
+    pub fn overwrite(mut self, overwrite: bool) -> Self {
+        self.overwrite = overwrite;
+        self
+    }
+
+    // 只保留第一个错误
+    fn set_error(&mut self, error: String) {
+        self.error.get_or_insert(error);
+    }
+
+    // 写入一个请求头
+    fn insert(&self, request: &mut Request, name: &HeaderName, value: HeaderValue) {
+        if self.overwrite || !request.headers.contains_key(name) {
+            request.headers.insert(name.clone(), value);
+        }
+    }
+}
+
+impl Middleware for HeaderMiddleware {
+    fn on_request(&self, request: &mut Request) -> HttpResult<()> {
+        if let Some(error) = &self.error {
+            return Err(HttpError::Builder(error.clone()));
+        }
+        for (name, value) in &self.headers {
+            self.insert(request, name, value.clone());
+        }
+        for (name, generator) in &self.generators {
+            let value = HeaderValue::from_str(&generator())
+                .map_err(|_| HttpError::Builder(format!("invalid generated value for header {}", name)))?;
+            self.insert(request, name, value);
+        }
+        Ok(())
+    }
+}
+
+/// 请求耗时统计
+#[derive(Debug, Clone, Default)]
+pub struct TimingStats {
+    // 已完成的请求数
+    pub count: u64,
+    // 失败的请求数(未收到响应)
+    pub failures: u64,
+    // 累计耗时
+    pub total: Duration,
+    // 最长耗时
+    pub max: Duration,
+}
+
+impl TimingStats {
+    /// 平均耗时
+    pub fn average(&self) -> Duration {
+        if self.count == 0 {
+            Duration::ZERO
+        } else {
+            self.total / self.count as u32
+        }
+    }
+}
+
+// 每个请求完成后的回调: 请求方法、地址、状态码(失败时为None)、耗时(包含重试)
+type TimingCallback = Box<dyn Fn(&Method, &Url, Option<StatusCode>, Duration) + Send + Sync>;
+
+/// 计时中间件,统计经过的请求耗时(包含重试),也可以为每个请求注册回调
+#[derive(Default)]
+pub struct TimingMiddleware {
+    stats: Arc<Mutex<TimingStats>>,
+    callback: Option<TimingCallback>,
+}
+
+impl TimingMiddleware {
+    /// 创建计时中间件
+    pub fn new() -> Self {
+        TimingMiddleware::default()
+    }
+
+    /// 每个请求完成后调用回调
+    pub fn on_complete<F>(mut self, callback: F) -> Self
+        where F: Fn(&Method, &Url, Option<StatusCode>, Duration) + Send + Sync + 'static
+    {
+        self.callback = Some(Box::new(callback));
+        self
+    }
+
+    /// 获取统计数据的共享句柄,中间件添加到客户端后仍可读取
+    pub fn stats(&self) -> Arc<Mutex<TimingStats>> {
+        self.stats.clone()
+    }
+}
+
+impl Middleware for TimingMiddleware {
+    fn handle<'a>(&'a self, request: Request, next: Next<'a>) -> BoxFuture<'a, HttpResult<HttpResponse>> {
+        Box::pin(async move {
+            let method = request.method.clone();
+            let url = request.url.clone();
+            let start = Instant::now();
+            let result = next.run(request).await;
+            let elapsed = start.elapsed();
+            {
+                let mut stats = self.stats.lock().unwrap();
+                stats.count += 1;
+                stats.total += elapsed;
+                stats.max = stats.max.max(elapsed);
+                if result.is_err() {
+                    stats.failures += 1;
+                }
+            }
+            if let Some(callback) = &self.callback {
+                callback(&method, &url, result.as_ref().ok().map(|r| r.status), elapsed);
+            }
+            result
+        })
+    }
+}
+
+impl Debug for dyn Middleware {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Middleware")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use bytes::Bytes;
+    use crate::networks::http::{AsyncHttpClient, HttpClient, HttpError, HttpResult, Method};
+    use crate::networks::http::middleware::{BoxFuture, HeaderMiddleware, LoggingMiddleware, Middleware, Next, TimingMiddleware};
+    use crate::networks::http::request::Request;
+    use crate::networks::http::response::HttpResponse;
+    use crate::networks::http::mock::{MockResponse, MockServer};
+
+    // 记录调用顺序的中间件
+    struct Recorder(&'static str, Arc<Mutex<Vec<String>>>);
+
+    impl Middleware for Recorder {
+        fn on_request(&self, _request: &mut Request) -> HttpResult<()> {
+            self.1.lock().unwrap().push(format!("{} request", self.0));
+            Ok(())
+        }
+
+        fn on_response(&self, _response: &mut HttpResponse) -> HttpResult<()> {
+            self.1.lock().unwrap().push(format!("{} response", self.0));
+            Ok(())
+        }
+    }
+
+    /// 测试中间件的调用顺序与请求头注入
+    #[test]
+    fn test_chain_order() {
+        let server = MockServer::start();
+        server.mock(Method::GET, "/").respond_with(|request| {
+            MockResponse::text(request.header("X-Trace-Id").unwrap_or(""))
+        });
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let logs = Arc::new(Mutex::new(Vec::new()));
+        let sink = logs.clone();
+        let client = HttpClient::builder()
+            .middleware(Recorder("a", events.clone()))
+            .middleware(Recorder("b", events.clone()))
+            .middleware(HeaderMiddleware::new().dynamic("X-Trace-Id", || "trace-1".to_string()))
+            .middleware(LoggingMiddleware::with_logger(move |line| sink.lock().unwrap().push(line.to_string())))
+            .build()
+            .unwrap();
+        let response = client.get(&server.url("/")).send().unwrap();
+        assert_eq!(response.body, "trace-1");
+        assert_eq!(*events.lock().unwrap(), vec!["a request", "b request", "b response", "a response"]);
+        assert_eq!(logs.lock().unwrap().len(), 2);
+
+        // 不合法的请求头在发送时返回错误
+        let client = HttpClient::builder().middleware(HeaderMiddleware::new().header("X-Bad", "a\nb")).build().unwrap();
+        assert!(matches!(client.get(&server.url("/")).send(), Err(HttpError::Builder(_))));
+    }
+
+    // 直接返回响应、不发送请求的中间件
+    struct ShortCircuit;
+
+    impl Middleware for ShortCircuit {
+        fn handle<'a>(&'a self, request: Request, _next: Next<'a>) -> BoxFuture<'a, HttpResult<HttpResponse>> {
+            Box::pin(async move {
+                Ok(HttpResponse {
+                    status: reqwest::StatusCode::OK,
+                    headers: Default::default(),
+                    url: request.url,
+                    version: reqwest::Version::HTTP_11,
+                    elapsed: Default::default(),
+                    body: Bytes::from_static(b"cached"),
+                })
+            })
+        }
+    }
+
+    /// 测试中间件短路与计时统计
+    #[tokio::test]
+    async fn test_short_circuit_and_timing() {
+        let timing = TimingMiddleware::new();
+        let stats = timing.stats();
+        let client = AsyncHttpClient::builder()
+            .middleware(timing)
+            .middleware(ShortCircuit)
+            .build_async()
+            .unwrap();
+        let response = client.get("http://127.0.0.1:1/unreachable").send().await.unwrap();
+        assert_eq!(response.body, "cached");
+        assert_eq!(stats.lock().unwrap().count, 1);
+        assert_eq!(stats.lock().unwrap().failures, 0);
+    }
+}
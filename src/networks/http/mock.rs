@@ -0,0 +1,418 @@
+//! # Mock HTTP服务
+//!
+//! 开启`testing`特性后可用.服务绑定在本地的随机端口上,运行在独立的线程中,
+//! 可以注册返回固定响应或由闭包生成响应的路由,模拟延迟与连接故障,并记录收到的请求用于断言.
+
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use bytes::Bytes;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::Body;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::Method;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// Mock HTTP服务,离开作用域时自动停止
+///
+/// # Examples
+/// ```
+/// # #[cfg(feature = "testing")] {
+/// use toys::networks::http::{HttpClient, Method};
+/// use toys::networks::http::mock::{MockResponse, MockServer};
+/// let server = MockServer::start();
+/// server.mock(Method::GET, "/users").query("id", "1").respond(MockResponse::text(r#"{"name":"满城雪"}"#));
+/// let response = HttpClient::new().get(&server.url("/users?id=1")).send().unwrap();
+/// assert_eq!(response.text(), r#"{"name":"满城雪"}"#);
+/// assert_eq!(server.received(Method::GET, "/users"), 1);
+/// # }
+/// ```
+pub struct MockServer {
+    addr: SocketAddr,
+    state: Arc<State>,
+    shutdown: Option<tokio::sync::oneshot::Sender<()>>,
+}
+
+#[derive(Default)]
+struct State {
+    routes: Mutex<Vec<Route>>,
+    requests: Mutex<Vec<MockRequest>>,
+}
+
+// 根据请求生成响应的闭包
+type Responder = Arc<dyn Fn(&MockRequest) -> MockResponse + Send + Sync>;
+
+struct Route {
+    method: Method,
+    path: String,
+    query: Vec<(String, String)>,
+    headers: Vec<(HeaderName, String)>,
+    // 剩余可响应次数,为None时不限次数
+    remaining: Option<usize>,
+    responder: Responder,
+}
+
+impl Route {
+    fn matches(&self, request: &MockRequest) -> bool {
+        self.remaining != Some(0)
+            && self.method == request.method
+            && self.path == request.path
+            && self.query.iter().all(|(key, value)| request.query_param(key) == Some(value.as_str()))
+            && self.headers.iter().all(|(name, value)| request.header(name.as_str()) == Some(value.as_str()))
+    }
+}
+
+impl MockServer {
+    /// 在`127.0.0.1`的随机端口上启动服务
+    pub fn start() -> Self {
+        let state = Arc::new(State::default());
+        let shared = state.clone();
+        let (addr_tx, addr_rx) = std::sync::mpsc::channel();
+        let (shutdown_tx, shutdown_rx) = tokio::sync::oneshot::channel::<()>();
+        std::thread::spawn(move || {
+            let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build()
+                .expect("failed to build mock server runtime");
+            runtime.block_on(async move {
+                let make_service = make_service_fn(move |_| {
+                    let state = shared.clone();
+                    async move {
+                        Ok::<_, Infallible>(service_fn(move |request| handle(state.clone(), request)))
+                    }
+                });
+                let server = hyper::Server::bind(&SocketAddr::from(([127, 0, 0, 1], 0))).serve(make_service);
+                let _ = addr_tx.send(server.local_addr());
+                let _ = server.with_graceful_shutdown(async { let _ = shutdown_rx.await; }).await;
+            });
+        });
+        MockServer {
+            addr: addr_rx.recv().expect("mock server failed to start"),
+            state,
+            shutdown: Some(shutdown_tx),
+        }
+    }
+
+    /// 服务监听的地址
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// 拼接完整的请求地址
+    pub fn url(&self, path: &str) -> String {
+        format!("http://{}{}", self.addr, path)
+    }
+
+    /// 注册一个路由,按注册顺序匹配,没有匹配的路由时返回404
+    pub fn mock(&self, method: Method, path: &str) -> MockBuilder<'_> {
+        MockBuilder {
+            server: self,
+            route: Route {
+                method,
+                path: path.to_string(),
+                query: Vec::new(),
+                headers: Vec::new(),
+                remaining: None,
+                responder: Arc::new(|_| MockResponse::ok()),
+            },
+        }
+    }
+
+    /// 已收到的全部请求
+    pub fn requests(&self) -> Vec<MockRequest> {
+        self.state.requests.lock().unwrap().clone()
+    }
+
+    /// 收到的指定方法与路径的请求数
+    pub fn received(&self, method: Method, path: &str) -> usize {
+        self.state.requests.lock().unwrap().iter()
+            .filter(|request| request.method == method && request.path == path)
+            .count()
+    }
+
+    /// 清空路由与请求记录
+    pub fn reset(&self) {
+        self.state.routes.lock().unwrap().clear();
+        self.state.requests.lock().unwrap().clear();
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+    }
+}
+
+/// 路由构建器
+pub struct MockBuilder<'a> {
+    server: &'a MockServer,
+    route: Route,
+}
+
+impl MockBuilder<'_> {
+    /// 要求请求带有指定的Query参数
+    pub fn query(mut self, key: &str, value: &str) -> Self {
+        self.route.query.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    /// 要求请求带有指定的请求头,名称不合法时会panic
+    pub fn header(mut self, key: &str, value: &str) -> Self {
+        let name = HeaderName::from_bytes(key.as_bytes()).expect("invalid header name");
+        self.route.headers.push((name, value.to_string()));
+        self
+    }
+
+    /// 最多响应`times`次,之后继续匹配后面的路由
+    pub fn times(mut self, times: usize) -> Self {
+        self.route.remaining = Some(times);
+        self
+    }
+
+    /// 返回固定的响应
+    pub fn respond(self, response: MockResponse) {
+        self.respond_with(move |_| response.clone())
+    }
+
+    /// 由闭包根据请求生成响应
+    pub fn respond_with<F>(mut self, responder: F) where F: Fn(&MockRequest) -> MockResponse + Send + Sync + 'static {
+        self.route.responder = Arc::new(responder);
+        self.server.state.routes.lock().unwrap().push(self.route);
+    }
+}
+
+/// 服务收到的请求
+#[derive(Debug, Clone)]
+pub struct MockRequest {
+    pub method: Method,
+    pub path: String,
+    pub query: Vec<(String, String)>,
+    pub headers: HeaderMap,
+    pub body: Bytes,
+}
+
+impl MockRequest {
+    /// 获取一个请求头的文本值
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).and_then(|value| value.to_str().ok())
+    }
+
+    /// 获取一个Query参数的值
+    pub fn query_param(&self, name: &str) -> Option<&str> {
+        self.query.iter().find(|(key, _)| key == name).map(|(_, value)| value.as_str())
+    }
+
+    /// 以文本格式获取请求体
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+
+    /// 将Json请求体反序列化为`T`
+    pub fn json<T: DeserializeOwned>(&self) -> serde_json::Result<T> {
+        serde_json::from_slice(&self.body)
+    }
+}
+
+/// 模拟的连接故障
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+    /// 不返回响应,直接关闭连接
+    CloseConnection,
+    /// 返回响应头与一半响应体后中断连接
+    TruncatedBody,
+}
+
+/// Mock响应
+#[derive(Debug, Clone)]
+pub struct MockResponse {
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: Bytes,
+    delay: Option<Duration>,
+    // 分块发送响应体时每块的大小与间隔
+    throttle: Option<(usize, Duration)>,
+    fault: Option<Fault>,
+}
+
+impl MockResponse {
+    /// 指定状态码的空响应
+    pub fn new(status: u16) -> Self {
+        MockResponse { status, headers: Vec::new(), body: Bytes::new(), delay: None, throttle: None, fault: None }
+    }
+
+    /// 200空响应
+    pub fn ok() -> Self {
+        MockResponse::new(200)
+    }
+
+    /// 200文本响应
+    pub fn text<S: Into<String>>(body: S) -> Self {
+        MockResponse::ok().header("Content-Type", "text/plain; charset=utf-8").body(body.into())
+    }
+
+    /// 200 Json响应,序列化失败时会panic
+    pub fn json<T: Serialize + ?Sized>(body: &T) -> Self {
+        let bytes = serde_json::to_vec(body).expect("failed to serialize mock response");
+        MockResponse::ok().header("Content-Type", "application/json").body(bytes)
+    }
+
+    /// 模拟连接故障
+    pub fn fault(fault: Fault) -> Self {
+        MockResponse { fault: Some(fault), ..MockResponse::ok() }
+    }
+
+    /// 设置状态码
+    pub fn status(mut self, status: u16) -> Self {
+        self.status = status;
+        self
+    }
+
+    /// 添加响应头
+    pub fn header(mut self, key: &str, value: &str) -> Self {
+        self.headers.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    /// 设置响应体
+    pub fn body<B: Into<Bytes>>(mut self, body: B) -> Self {
+        self.body = body.into();
+        self
+    }
+
+    /// 延迟返回响应
+    pub fn delay(mut self, delay: Duration) -> Self {
+        self.delay = Some(delay);
+        self
+    }
+
+    /// 将响应体按`chunk_size`字节分块发送,两块之间间隔`interval`,用于模拟慢速下载
+    pub fn throttle(mut self, chunk_size: usize, interval: Duration) -> Self {
+        self.throttle = Some((chunk_size.max(1), interval));
+        self
+    }
+}
+
+// 记录请求并返回匹配路由生成的响应
+async fn handle(state: Arc<State>, request: hyper::Request<Body>) -> Result<hyper::Response<Body>, std::io::Error> {
+    let (parts, body) = request.into_parts();
+    let request = MockRequest {
+        method: parts.method,
+        path: parts.uri.path().to_string(),
+        query: parts.uri.query().and_then(|query| serde_urlencoded::from_str(query).ok()).unwrap_or_default(),
+        headers: parts.headers,
+        body: hyper::body::to_bytes(body).await.unwrap_or_default(),
+    };
+    state.requests.lock().unwrap().push(request.clone());
+    let responder = {
+        let mut routes = state.routes.lock().unwrap();
+        routes.iter_mut().find(|route| route.matches(&request)).map(|route| {
+            route.remaining = route.remaining.map(|remaining| remaining - 1);
+            route.responder.clone()
+        })
+    };
+    let response = match responder {
+        Some(responder) => responder(&request),
+        None => MockResponse::new(404).body(format!("no mock for {} {}", request.method, request.path)),
+    };
+
+    if let Some(delay) = response.delay {
+        tokio::time::sleep(delay).await;
+    }
+    let body = match response.fault {
+        Some(Fault::CloseConnection) => {
+            return Err(std::io::Error::new(std::io::ErrorKind::ConnectionAborted, "mock fault: connection closed"));
+        }
+        Some(Fault::TruncatedBody) => {
+            let (mut sender, body) = Body::channel();
+            let half = response.body.slice(..response.body.len() / 2);
+            tokio::spawn(async move {
+                let _ = sender.send_data(half).await;
+                sender.abort();
+            });
+            body
+        }
+        None => match response.throttle {
+            Some((chunk_size, interval)) => {
+                let (mut sender, body) = Body::channel();
+                let content = response.body.clone();
+                tokio::spawn(async move {
+                    for (index, start) in (0..content.len()).step_by(chunk_size).enumerate() {
+                        if index > 0 {
+                            tokio::time::sleep(interval).await;
+                        }
+                        let end = content.len().min(start + chunk_size);
+                        if sender.send_data(content.slice(start..end)).await.is_err() {
+                            return;
+                        }
+                    }
+                });
+                body
+            }
+            None => Body::from(response.body.clone()),
+        },
+    };
+    let mut builder = hyper::Response::builder().status(response.status);
+    for (key, value) in &response.headers {
+        if let (Ok(name), Ok(value)) = (HeaderName::from_bytes(key.as_bytes()), HeaderValue::from_str(value)) {
+            builder = builder.header(name, value);
+        }
+    }
+    builder.body(body).map_err(std::io::Error::other)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::time::Duration;
+    use reqwest::Method;
+    use crate::networks::http::{AsyncHttpClient, HttpClient, HttpError};
+    use crate::networks::http::mock::{Fault, MockResponse, MockServer};
+
+    /// 测试路由匹配与请求记录
+    #[test]
+    fn test_routes_and_recording() {
+        let server = MockServer::start();
+        server.mock(Method::GET, "/users").query("id", "1").times(1).respond(MockResponse::json(&HashMap::from([("id", 1)])));
+        server.mock(Method::GET, "/users").respond(MockResponse::new(410));
+        server.mock(Method::POST, "/users").header("x-token", "t").respond_with(|request| {
+            MockResponse::text(format!("created {}", request.text())).status(201)
+        });
+        let client = HttpClient::new();
+
+        assert_eq!(client.get(&server.url("/users?id=1")).send().unwrap().text(), r#"{"id":1}"#);
+        assert_eq!(client.get(&server.url("/users?id=1")).send().unwrap().status, 410);
+        let created = client.post(&server.url("/users")).header("X-Token", "t").body("张三").send().unwrap();
+        assert_eq!((created.status.as_u16(), created.text()), (201, "created 张三".to_string()));
+        assert_eq!(client.post(&server.url("/users")).send().unwrap().status, 404);
+
+        assert_eq!(server.received(Method::GET, "/users"), 2);
+        let requests = server.requests();
+        assert_eq!(requests[0].query_param("id"), Some("1"));
+        assert_eq!(requests[2].header("x-token"), Some("t"));
+        server.reset();
+        assert!(server.requests().is_empty());
+    }
+
+    /// 测试延迟、分块发送与连接故障
+    #[tokio::test]
+    async fn test_delay_and_faults() {
+        let server = MockServer::start();
+        server.mock(Method::GET, "/slow").respond(MockResponse::ok().delay(Duration::from_millis(300)));
+        server.mock(Method::GET, "/close").respond(MockResponse::fault(Fault::CloseConnection));
+        server.mock(Method::GET, "/truncated").respond(MockResponse::fault(Fault::TruncatedBody).body(vec![b'x'; 1024]));
+        server.mock(Method::GET, "/trickle").respond(MockResponse::text("abcdef").throttle(2, Duration::from_millis(60)));
+        let client = AsyncHttpClient::builder()
+            .timeout(Duration::from_millis(100))
+            .build_async()
+            .unwrap();
+
+        assert!(matches!(client.get(&server.url("/slow")).send().await, Err(HttpError::Timeout(_))));
+        assert!(matches!(client.get(&server.url("/close")).send().await, Err(HttpError::Request(_))));
+        assert!(matches!(client.get(&server.url("/truncated")).send().await, Err(HttpError::Request(_))));
+        assert!(matches!(client.get(&server.url("/trickle")).send().await, Err(HttpError::Timeout(_))));
+        let trickle = AsyncHttpClient::new().get(&server.url("/trickle")).send_text().await.unwrap();
+        assert_eq!(trickle.body, "abcdef");
+    }
+}
@@ -0,0 +1,411 @@
+//! # HTTP请求函数模块
+//!
+//! 提供可配置的同步客户端[`HttpClient`]与异步客户端[`AsyncHttpClient`],
+//! 以及基于默认客户端实例的快捷请求函数.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::OnceLock;
+use serde::{Deserialize, Serialize};
+use serde::de::DeserializeOwned;
+
+pub mod auth;
+pub mod batch;
+pub mod bench;
+pub mod breaker;
+pub mod cache;
+pub mod cassette;
+pub mod client;
+pub mod download;
+pub mod error;
+pub mod graphql;
+pub mod jsonrpc;
+pub mod limiter;
+pub mod middleware;
+#[cfg(any(test, feature = "testing"))]
+pub mod mock;
+pub mod multipart;
+pub mod paginate;
+pub mod percent;
+pub mod proxy;
+pub mod request;
+pub mod response;
+pub mod rest;
+pub mod retry;
+pub mod session;
+pub mod signing;
+pub mod sse;
+
+pub use client::{AsyncHttpClient, ClientConfig, HttpClient, HttpClientBuilder};
+pub use error::{HttpError, HttpResult};
+pub use middleware::{Middleware, Next};
+pub use request::{AsyncRequestBuilder, Body, Request, RequestBuilder};
+pub use response::HttpResponse;
+pub use retry::RetryPolicy;
+pub use session::HttpSession;
+pub use reqwest::Method;
+#[cfg(feature = "macros")]
+pub use toys_macros::rest_client;
+use client::block_on;
+
+// 全局静态属性,第一次使用时使用默认配置初始化
+// HTTP同步客户端与异步客户端,放在同一个单元中保证二者总是一起初始化
+static CLIENTS: OnceLock<(HttpClient, AsyncHttpClient)> = OnceLock::new();
+
+// 获取默认客户端,未初始化时使用默认配置
+fn default_clients() -> &'static (HttpClient, AsyncHttpClient) {
+    CLIENTS.get_or_init(|| (HttpClient::default(), AsyncHttpClient::default()))
+}
+
+/// 获取快捷请求函数所使用的默认同步客户端
+pub fn default_client() -> &'static HttpClient {
+    &default_clients().0
+}
+
+/// 获取快捷请求函数所使用的默认异步客户端
+pub fn default_async_client() -> &'static AsyncHttpClient {
+    &default_clients().1
+}
+
+/// 配置快捷请求函数所使用的默认客户端(代理、证书等),必须在第一次发送请求之前调用.
+/// `configure`会被调用两次,分别用于构建同步与异步客户端;默认客户端已初始化时返回错误.
+///
+/// # Examples
+/// ```no_run
+/// use toys::networks::http::{HttpClient, init_default_client};
+/// use toys::networks::http::proxy::Proxy;
+/// init_default_client(|| HttpClient::builder()
+///     .proxy(Proxy::all("http://proxy.corp.example.com:3128").no_proxy_from_env())
+///     .root_certificate_file("/etc/pki/corp-root-ca.pem"))
+///     .unwrap();
+/// ```
+pub fn init_default_client<F>(configure: F) -> HttpResult<()> where F: Fn() -> HttpClientBuilder {
+    let client = configure().build()?;
+    let async_client = configure().build_async()?;
+    CLIENTS.set((client, async_client))
+        .map_err(|_| HttpError::Builder("default client is already initialized".to_string()))
+}
+
+
+/// 发送GET请求(同步)
+/// query Query请求参数
+/// `R` 表示响应载体类型
+/// # Examples
+pub fn get<R: DeserializeOwned>(url: &str, query: &HashMap<String,String>) -> reqwest::Result<R> {
+    let client = default_client().as_async().inner().clone();
+    let url = url.to_string();
+    let query = query.clone();
+    // 在默认客户端的后台运行时上读取响应,再回到调用线程反序列化
+    block_on(async move {
+        let response = client.get(url)
+            // 指定Query参数
+            .query(&query)
+            // 发送请求
+            .send().await?
+            // 非2xx状态码直接返回错误,避免将错误页面当作Json解码
+            .error_for_status()?;
+        buffer(response).await
+    })?.json::<R>()
+}
+
+
+/// 发送GET请求(异步)
+/// query Query请求参数
+/// `R` 表示响应载体类型
+pub async fn get_async<R: DeserializeOwned>(url: &str,query: &HashMap<String,String>) -> reqwest::Result<R> {
+    default_async_client().inner().get(url)
+        .query(query)
+        // 发送请求
+        .send().await?
+        .error_for_status()?
+        // 以text格式获取结果
+        .json::<R>().await
+}
+
+/// 发送Post请求(同步)
+/// `T` 表示请求体类型,该类型必须实现`Serialize `trait才能将其序列化为Json
+/// `R` 表示响应体载体类型,该类型必须实现了`Deserialize` trait才能将其反序列为结构体
+pub fn post<T,R>(url: &str,request_body: &T) -> Result<R,reqwest::Error> where
+T: Serialize,
+R: DeserializeOwned
+{
+    // 先在当前线程构建请求,避免要求请求体满足'static
+    let request = default_client().as_async().inner().post(url)
+        .json(request_body)
+        .build()?;
+    let client = default_client().as_async().inner().clone();
+    block_on(async move {
+        buffer(client.execute(request).await?.error_for_status()?).await
+    })?.json::<R>()
+}
+
+// 在后台运行时上读取完整的响应体,转换为可在调用线程上解码的同步响应
+async fn buffer(response: reqwest::Response) -> reqwest::Result<reqwest::blocking::Response> {
+    let mut builder = http::Response::builder()
+        .status(response.status())
+        .version(response.version());
+    if let Some(headers) = builder.headers_mut() {
+        *headers = response.headers().clone();
+    }
+    let body = response.bytes().await?;
+    Ok(builder.body(body).expect("response parts are valid").into())
+}
+
+/// 发送Post请求(异步)
+/// `T` 表示请求体类型,该类型必须实现`Serialize `trait才能将其序列化为Json
+/// `R` 表示响应体载体类型,该类型必须实现了`Deserialize` trait才能将其反序列为结构体
+pub async fn post_async<T,R>(url: &str,request_body: &T) -> Result<R, reqwest::Error> where
+    T: Serialize,
+    R: DeserializeOwned
+{
+    let entity = default_async_client().inner().post(url)
+        .json(request_body)
+        .send().await?
+        .error_for_status()?
+        .json::<R>().await?;
+    Ok(entity)
+}
+
+/// 使用默认客户端创建一个同步请求构建器,可用于任意请求方法
+/// # Examples
+/// ```no_run
+/// use toys::networks::http::{Method, request};
+/// let response = request(Method::OPTIONS, "http://127.0.0.1:13001/example/index").send().unwrap();
+/// println!("{} {:?}", response.status, response.header("allow"));
+/// ```
+pub fn request(method: Method, url: &str) -> RequestBuilder {
+    default_client().request(method, url)
+}
+
+/// 使用默认客户端创建一个异步请求构建器,可用于任意请求方法
+pub fn request_async(method: Method, url: &str) -> AsyncRequestBuilder {
+    default_async_client().request(method, url)
+}
+
+/// 发送Put请求(同步),请求体以Json格式发送
+/// `R` 表示响应体载体类型
+pub fn put<T,R>(url: &str, request_body: &T) -> HttpResult<R> where
+    T: Serialize + ?Sized,
+    R: DeserializeOwned + Send + 'static
+{
+    default_client().put(url).json(request_body).send_json().map(HttpResponse::into_body)
+}
+
+/// 发送Put请求(异步),请求体以Json格式发送
+pub async fn put_async<T,R>(url: &str, request_body: &T) -> HttpResult<R> where
+    T: Serialize + ?Sized,
+    R: DeserializeOwned
+{
+    default_async_client().put(url).json(request_body).send_json().await.map(HttpResponse::into_body)
+}
+
+/// 发送Patch请求(同步),请求体以Json格式发送
+pub fn patch<T,R>(url: &str, request_body: &T) -> HttpResult<R> where
+    T: Serialize + ?Sized,
+    R: DeserializeOwned + Send + 'static
+{
+    default_client().patch(url).json(request_body).send_json().map(HttpResponse::into_body)
+}
+
+/// 发送Patch请求(异步),请求体以Json格式发送
+pub async fn patch_async<T,R>(url: &str, request_body: &T) -> HttpResult<R> where
+    T: Serialize + ?Sized,
+    R: DeserializeOwned
+{
+    default_async_client().patch(url).json(request_body).send_json().await.map(HttpResponse::into_body)
+}
+
+/// 发送Delete请求(同步)
+/// query Query请求参数
+pub fn delete<R>(url: &str, query: &HashMap<String,String>) -> HttpResult<R> where
+    R: DeserializeOwned + Send + 'static
+{
+    default_client().delete(url).query(query).send_json().map(HttpResponse::into_body)
+}
+
+/// 发送Delete请求(异步)
+/// query Query请求参数
+pub async fn delete_async<R: DeserializeOwned>(url: &str, query: &HashMap<String,String>) -> HttpResult<R> {
+    default_async_client().delete(url).query(query).send_json().await.map(HttpResponse::into_body)
+}
+
+/// 以`application/x-www-form-urlencoded`格式发送Post请求(同步)
+/// `T` 表示表单类型,可以是`HashMap`、`&[(K, V)]`或实现了`Serialize`的结构体
+/// `R` 表示响应体载体类型
+pub fn post_form<T,R>(url: &str, form: &T) -> HttpResult<R> where
+    T: Serialize + ?Sized,
+    R: DeserializeOwned + Send + 'static
+{
+    default_client().post(url).form(form).send_json().map(HttpResponse::into_body)
+}
+
+/// 以`application/x-www-form-urlencoded`格式发送Post请求(异步)
+pub async fn post_form_async<T,R>(url: &str, form: &T) -> HttpResult<R> where
+    T: Serialize + ?Sized,
+    R: DeserializeOwned
+{
+    default_async_client().post(url).form(form).send_json().await.map(HttpResponse::into_body)
+}
+
+/// 以`multipart/form-data`格式发送Post请求(同步),用于上传文件
+pub fn post_multipart<R>(url: &str, form: multipart::Form) -> HttpResult<R> where
+    R: DeserializeOwned + Send + 'static
+{
+    default_client().post(url).multipart(form).send_json().map(HttpResponse::into_body)
+}
+
+/// 以`multipart/form-data`格式发送Post请求(异步),用于上传文件
+pub async fn post_multipart_async<R: DeserializeOwned>(url: &str, form: multipart::Form) -> HttpResult<R> {
+    default_async_client().post(url).multipart(form).send_json().await.map(HttpResponse::into_body)
+}
+
+/// 将`url`下载到本地文件`path`(同步),以流的方式写入磁盘并支持断点续传.
+/// 下载不受总超时限制,需要进度回调、校验值或总超时时使用`HttpClient::download`
+/// # Examples
+/// ```no_run
+/// use toys::networks::http::download_to_file;
+/// let report = download_to_file("https://example.com/big.iso", "big.iso").unwrap();
+/// println!("{} bytes in {:?}", report.size, report.elapsed);
+/// ```
+pub fn download_to_file<P: AsRef<Path>>(url: &str, path: P) -> HttpResult<download::DownloadReport> {
+    default_client().download(url, path).start()
+}
+
+/// 将`url`下载到本地文件`path`(异步),以流的方式写入磁盘并支持断点续传
+pub async fn download_to_file_async<P: AsRef<Path>>(url: &str, path: P) -> HttpResult<download::DownloadReport> {
+    default_async_client().download(url, path).start().await
+}
+
+/// Http请求体
+/// 使用serde的Serialize特征,让其支持结构体序列化为Json
+#[derive(Serialize,Debug)]
+#[allow(dead_code)]// 避免未使用字段警告
+pub struct RequestBody{
+    name: String,
+    age: u8,
+    locked: bool,
+    scope: f64
+}
+
+/// Http响应体
+/// 使用了serde的Deserialize特性，以便将JSON格式的字符串反序列化为结构体对象。
+#[derive(Deserialize,Debug)]
+#[allow(dead_code)] // 避免未使用字段警告
+pub struct ResponseBody{
+    name: String,
+    age: u8,
+    locked: bool,
+    scope: f64
+}
+
+#[cfg(test)]
+mod tests{
+    use std::collections::HashMap;
+    use crate::networks::http::*;
+    use crate::networks::http::mock::{MockResponse, MockServer};
+
+    // 启动模拟/example/index与/example/index/post接口的本地服务
+    fn example_server() -> MockServer {
+        let server = MockServer::start();
+        server.mock(Method::GET, "/example/index").respond_with(|request| {
+            let query: HashMap<String, String> = request.query.iter().cloned().collect();
+            MockResponse::json(&query)
+        });
+        server.mock(Method::POST, "/example/index/post").respond_with(|request| {
+            MockResponse::ok().header("Content-Type", "application/json").body(request.body.clone())
+        });
+        server
+    }
+
+    /// 单元测试,同步Get请求
+    #[test]
+    fn test_get(){
+        let server = example_server();
+        // 构建Query参数
+        let mut query = HashMap::new();
+        query.insert("name".to_string(),"张三".to_string());
+        // 执行同步GET请求
+        let result_map :HashMap<String,String> = get(&server.url("/example/index"), &query).unwrap();
+        assert_eq!(result_map,query);
+    }
+
+    /// 异步函数单元测试,异步Get请求
+    #[tokio::test]
+    async fn test_get_async() -> Result<(),Box<dyn std::error::Error>>{
+        let server = example_server();
+        // 构建Query参数
+        let mut query = HashMap::new();
+        query.insert("name".to_string(),"王五".to_string());
+        // 执行异步GET请求
+        let result_map: HashMap<String,String> = get_async(&server.url("/example/index"),&query).await.unwrap();
+        assert_eq!(result_map["name"],"王五");
+        assert_eq!(server.received(Method::GET,"/example/index"),1);
+        Ok(())
+    }
+
+    /// 测试服务端返回错误页面时快捷函数返回状态码错误而不是解码错误
+    #[test]
+    fn test_error_status() {
+        let server = MockServer::start();
+        let page = || MockResponse::text("<html><body>Internal Server Error</body></html>")
+            .status(500)
+            .header("Content-Type", "text/html");
+        server.mock(Method::GET, "/error").respond_with(move |_| page());
+        server.mock(Method::POST, "/error").respond_with(move |_| page());
+
+        let error = get::<HashMap<String, String>>(&server.url("/error"), &HashMap::new()).unwrap_err();
+        assert_eq!(error.status().map(|status| status.as_u16()), Some(500));
+        let error = post::<_, HashMap<String, String>>(&server.url("/error"), &HashMap::<String, String>::new()).unwrap_err();
+        assert!(error.is_status());
+
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let error = runtime.block_on(get_async::<HashMap<String, String>>(&server.url("/error"), &HashMap::new())).unwrap_err();
+        assert!(error.is_status());
+        let error = runtime.block_on(post_async::<_, HashMap<String, String>>(&server.url("/error"), &HashMap::<String, String>::new())).unwrap_err();
+        assert!(error.is_status());
+    }
+
+    /// 测试同步Post请求
+    #[test]
+    fn test_post(){
+        let server = example_server();
+        // 创建请求体
+        let request_body = RequestBody{
+            name: "满城雪".to_string(),
+            age: 23,
+            locked: true,
+            scope: 188.88,
+        };
+        // 发起请求,指定返回体为 ResponseBody类型
+        let result: ResponseBody = post(&server.url("/example/index/post"),&request_body).unwrap();
+        assert_eq!((result.name.as_str(),result.age,result.locked,result.scope),("满城雪",23,true,188.88));
+        assert_eq!(server.requests()[0].header("content-type"),Some("application/json"));
+    }
+
+    /// 测试异步Post请求
+    #[tokio::test]
+    async fn test_post_async() -> Result<(),std::io::Error>{
+        let server = example_server();
+        // 创建Post请求体
+        let request_body = RequestBody{
+            name: "满城雪".to_string(),
+            age: 23,
+            locked: true,
+            scope: 188.88,
+        };
+
+        // 发起请求,指定返回映射体的类型
+        let result:Result<ResponseBody,reqwest::Error> = post_async(&server.url("/example/index/post"),&request_body).await;
+        // 处理结果
+        match result {
+            Ok(value)=> {
+                assert_eq!(value.name,"满城雪");
+                Ok(())
+            },
+            Err(error)=> {
+                println!("{}",error);
+                Err(std::io::Error::other("发送Post请求失败"))
+            }
+        }
+    }
+}
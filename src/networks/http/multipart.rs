@@ -0,0 +1,307 @@
+//! # multipart/form-data 请求体
+//!
+//! 支持文本字段、本地文件、内存字节以及任意`Read`数据流,
+//! 文件与数据流在发送时以流的方式读取,不会整体读入内存.
+
+use std::fmt::{Debug, Formatter};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use bytes::Bytes;
+use futures_util::{Stream, StreamExt, TryStreamExt};
+use futures_util::stream;
+use rand::Rng;
+use tokio_util::io::ReaderStream;
+
+// 请求体数据流
+pub(crate) type ByteStream = Pin<Box<dyn Stream<Item = std::io::Result<Bytes>> + Send>>;
+
+// 读取数据流时每次读取的字节数
+const READ_CHUNK_SIZE: usize = 8 * 1024;
+
+/// multipart/form-data 表单
+///
+/// # Examples
+/// ```no_run
+/// use toys::networks::http::multipart::{Form, Part};
+/// let form = Form::new()
+///     .text("name", "满城雪")
+///     .part("avatar", Part::bytes(vec![0u8; 16]).file_name("avatar.png"))
+///     .file("report", "report.pdf").unwrap()
+///     .part("log", Part::reader(std::io::empty()).file_name("app.log").mime("text/plain"));
+/// let response = toys::networks::http::default_client()
+///     .post("http://127.0.0.1:13001/example/upload")
+///     .multipart(form)
+///     .send()
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct Form {
+    boundary: String,
+    parts: Vec<(String, Part)>,
+}
+
+impl Default for Form {
+    fn default() -> Self {
+        let mut rng = rand::thread_rng();
+        Form {
+            boundary: format!("{:016x}-{:016x}", rng.gen::<u64>(), rng.gen::<u64>()),
+            parts: Vec::new(),
+        }
+    }
+}
+
+impl Form {
+    /// 创建一个空表单,分隔符随机生成
+    pub fn new() -> Self {
+        Form::default()
+    }
+
+    /// 获取分隔符
+    pub fn boundary(&self) -> &str {
+        &self.boundary
+    }
+
+    /// 获取Content-Type请求头的值
+    pub fn content_type(&self) -> String {
+        format!("multipart/form-data; boundary={}", self.boundary)
+    }
+
+    /// 添加文本字段
+    pub fn text<V: Into<String>>(self, name: &str, value: V) -> Self {
+        self.part(name, Part::text(value))
+    }
+
+    /// 添加本地文件,文件名与类型根据路径推断
+    pub fn file<P: AsRef<Path>>(self, name: &str, path: P) -> std::io::Result<Self> {
+        Ok(self.part(name, Part::file(path)?))
+    }
+
+    /// 添加一个字段
+    pub fn part(mut self, name: &str, part: Part) -> Self {
+        self.parts.push((name.to_string(), part));
+        self
+    }
+
+    /// 所有字段都在内存中时,将表单编码为完整的字节
+    pub fn to_bytes(&self) -> Option<Bytes> {
+        let mut buf = Vec::new();
+        for (name, part) in &self.parts {
+            let PartData::Bytes(data) = &part.data else { return None; };
+            buf.extend_from_slice(&part.header(&self.boundary, name));
+            buf.extend_from_slice(data);
+            buf.extend_from_slice(b"\r\n");
+        }
+        buf.extend_from_slice(self.footer().as_bytes());
+        Some(Bytes::from(buf))
+    }
+
+    /// 将表单编码为数据流
+    pub(crate) fn into_stream(self) -> ByteStream {
+        let footer = Bytes::from(self.footer());
+        let mut segments: Vec<ByteStream> = Vec::new();
+        for (name, part) in self.parts {
+            let header = part.header(&self.boundary, &name);
+            segments.push(Box::pin(stream::once(async move { Ok(header) })));
+            segments.push(part.data.into_stream());
+            segments.push(Box::pin(stream::once(async { Ok(Bytes::from_static(b"\r\n")) })));
+        }
+        segments.push(Box::pin(stream::once(async move { Ok(footer) })));
+        Box::pin(stream::iter(segments).flatten())
+    }
+
+    // 结束分隔符
+    fn footer(&self) -> String {
+        format!("--{}--\r\n", self.boundary)
+    }
+}
+
+/// 表单中的一个字段
+#[derive(Debug, Clone)]
+pub struct Part {
+    data: PartData,
+    file_name: Option<String>,
+    mime: Option<String>,
+}
+
+// 字段数据
+#[derive(Clone)]
+enum PartData {
+    Bytes(Bytes),
+    File(PathBuf),
+    // 数据流只能读取一次,克隆后共享同一个数据流
+    Reader(Arc<Mutex<Option<Box<dyn Read + Send>>>>),
+}
+
+impl Debug for PartData {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PartData::Bytes(bytes) => write!(f, "Bytes({} bytes)", bytes.len()),
+            PartData::File(path) => write!(f, "File({:?})", path),
+            PartData::Reader(_) => f.write_str("Reader"),
+        }
+    }
+}
+
+impl PartData {
+    fn into_stream(self) -> ByteStream {
+        match self {
+            PartData::Bytes(bytes) => Box::pin(stream::once(async move { Ok(bytes) })),
+            PartData::File(path) => Box::pin(
+                stream::once(async move { tokio::fs::File::open(path).await })
+                    .map_ok(ReaderStream::new)
+                    .try_flatten()
+            ),
+            PartData::Reader(reader) => match reader.lock().unwrap().take() {
+                Some(reader) => read_in_background(reader),
+                None => Box::pin(stream::once(async {
+                    Err(std::io::Error::other("multipart reader has already been consumed"))
+                })),
+            },
+        }
+    }
+}
+
+// 在阻塞线程池中读取数据流,通过通道转换为异步数据流
+fn read_in_background(mut reader: Box<dyn Read + Send>) -> ByteStream {
+    let (tx, rx) = tokio::sync::mpsc::channel::<std::io::Result<Bytes>>(4);
+    tokio::task::spawn_blocking(move || {
+        let mut buf = vec![0u8; READ_CHUNK_SIZE];
+        loop {
+            let chunk = match reader.read(&mut buf) {
+                Ok(0) => return,
+                Ok(n) => Ok(Bytes::copy_from_slice(&buf[..n])),
+                Err(e) => Err(e),
+            };
+            let failed = chunk.is_err();
+            if tx.blocking_send(chunk).is_err() || failed {
+                return;
+            }
+        }
+    });
+    Box::pin(stream::unfold(rx, |mut rx| async move {
+        rx.recv().await.map(|chunk| (chunk, rx))
+    }))
+}
+
+impl Part {
+    /// 文本字段
+    pub fn text<V: Into<String>>(value: V) -> Self {
+        Part::new(PartData::Bytes(Bytes::from(value.into())))
+    }
+
+    /// 内存中的字节数据
+    pub fn bytes<B: Into<Bytes>>(bytes: B) -> Self {
+        Part::new(PartData::Bytes(bytes.into()))
+    }
+
+    /// 本地文件,发送时才会打开文件.文件名与类型根据路径推断
+    pub fn file<P: AsRef<Path>>(path: P) -> std::io::Result<Self> {
+        let path = path.as_ref();
+        if !std::fs::metadata(path)?.is_file() {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("{:?} is not a file", path)));
+        }
+        let mime = mime_guess::from_path(path).first_or_octet_stream().to_string();
+        let file_name = path.file_name().map(|name| name.to_string_lossy().into_owned());
+        Ok(Part { data: PartData::File(path.to_path_buf()), file_name, mime: Some(mime) })
+    }
+
+    /// 任意数据流,只能发送一次,重试时会返回错误
+    pub fn reader<R: Read + Send + 'static>(reader: R) -> Self {
+        Part::new(PartData::Reader(Arc::new(Mutex::new(Some(Box::new(reader))))))
+    }
+
+    /// 设置文件名
+    pub fn file_name<S: Into<String>>(mut self, file_name: S) -> Self {
+        self.file_name = Some(file_name.into());
+        self
+    }
+
+    /// 设置字段的Content-Type
+    pub fn mime<S: Into<String>>(mut self, mime: S) -> Self {
+        self.mime = Some(mime.into());
+        self
+    }
+
+    fn new(data: PartData) -> Self {
+        Part { data, file_name: None, mime: None }
+    }
+
+    // 字段头部,包含分隔符、Content-Disposition与Content-Type
+    fn header(&self, boundary: &str, name: &str) -> Bytes {
+        let mut header = format!("--{}\r\nContent-Disposition: form-data; name=\"{}\"", boundary, escape(name));
+        if let Some(file_name) = &self.file_name {
+            header.push_str(&format!("; filename=\"{}\"", escape(file_name)));
+        }
+        header.push_str("\r\n");
+        if let Some(mime) = &self.mime {
+            header.push_str(&format!("Content-Type: {}\r\n", mime));
+        } else if self.file_name.is_some() {
+            header.push_str("Content-Type: application/octet-stream\r\n");
+        }
+        header.push_str("\r\n");
+        Bytes::from(header)
+    }
+}
+
+// 按照HTML规范转义字段名与文件名中的引号与换行
+fn escape(value: &str) -> String {
+    value.replace('"', "%22").replace('\r', "%0D").replace('\n', "%0A")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::io::Cursor;
+    use crate::networks::http::{HttpClient, Method, post_form_async};
+    use crate::networks::http::mock::{MockResponse, MockServer};
+    use crate::networks::http::multipart::{Form, Part};
+
+    /// 测试内存表单编码
+    #[test]
+    fn test_encode_in_memory() {
+        let form = Form::new()
+            .text("name", "满城雪")
+            .part("avatar", Part::bytes(&b"PNG"[..]).file_name("a\"b.png").mime("image/png"));
+        let boundary = form.boundary().to_string();
+        let body = String::from_utf8(form.to_bytes().unwrap().to_vec()).unwrap();
+        let expected = format!("--{b}\r\nContent-Disposition: form-data; name=\"name\"\r\n\r\n满城雪\r\n\
+            --{b}\r\nContent-Disposition: form-data; name=\"avatar\"; filename=\"a%22b.png\"\r\nContent-Type: image/png\r\n\r\nPNG\r\n\
+            --{b}--\r\n", b = boundary);
+        assert_eq!(body, expected);
+        assert!(Form::new().part("log", Part::reader(std::io::empty())).to_bytes().is_none());
+    }
+
+    /// 测试上传文件与数据流
+    #[test]
+    fn test_upload_stream() {
+        let server = MockServer::start();
+        server.mock(Method::POST, "/upload").respond_with(|request| {
+            MockResponse::text(format!("{}\n{}", request.header("content-type").unwrap_or(""), request.text()))
+        });
+        let path = std::env::temp_dir().join("toys_multipart_test.txt");
+        std::fs::write(&path, "file content").unwrap();
+        let form = Form::new()
+            .file("report", &path).unwrap()
+            .part("log", Part::reader(Cursor::new(vec![b'x'; 20000])).file_name("app.log"));
+        let boundary = form.boundary().to_string();
+        let text = HttpClient::new().post(&server.url("/upload")).multipart(form).send_text().unwrap().body;
+        assert!(text.starts_with(&format!("multipart/form-data; boundary={}", boundary)));
+        assert!(text.contains("filename=\"toys_multipart_test.txt\"\r\nContent-Type: text/plain\r\n\r\nfile content\r\n"));
+        assert!(text.contains(&format!("filename=\"app.log\"\r\nContent-Type: application/octet-stream\r\n\r\n{}\r\n", "x".repeat(20000))));
+        assert!(text.ends_with(&format!("--{}--\r\n", boundary)));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    /// 测试表单请求
+    #[tokio::test]
+    async fn test_post_form() {
+        let server = MockServer::start();
+        server.mock(Method::POST, "/form").respond_with(|request| MockResponse::json(&HashMap::from([("body", request.text())])));
+        let result: HashMap<String, String> = post_form_async(&server.url("/form"), &HashMap::from([("name", "张 三")]))
+            .await
+            .unwrap();
+        assert_eq!(result["body"], "name=%E5%BC%A0+%E4%B8%89");
+    }
+}
@@ -0,0 +1,540 @@
+//! # 分页请求
+//!
+//! 按照页码、偏移量、游标或RFC 5988 `Link`响应头自动翻页,逐条返回Json列表中的元素.
+//! 只有取完当前页后才会请求下一页,同步代码直接作为迭代器使用,异步代码使用[`Paginator::stream`].
+//!
+//! # Examples
+//! ```no_run
+//! use serde::Deserialize;
+//! use toys::networks::http::HttpClient;
+//! use toys::networks::http::paginate::Pagination;
+//!
+//! #[derive(Deserialize)]
+//! struct Repository { name: String }
+//!
+//! let client = HttpClient::new();
+//! let repositories = client.get("https://api.github.com/orgs/rust-lang/repos")
+//!     .query(&[("per_page", "100")])
+//!     .paginate::<Repository>(Pagination::link_header())
+//!     .max_pages(5);
+//! for repository in repositories {
+//!     println!("{}", repository.unwrap().name);
+//! }
+//!
+//! // {"data": [...], "meta": {"next_cursor": "..."}}
+//! let events = client.get("https://api.example.com/events")
+//!     .paginate::<serde_json::Value>(Pagination::cursor("cursor", "meta.next_cursor"))
+//!     .items("data");
+//! ```
+
+use std::collections::VecDeque;
+use std::fmt::{Debug, Formatter};
+use std::marker::PhantomData;
+use futures_util::stream::{self, BoxStream};
+use futures_util::StreamExt;
+use reqwest::header::LINK;
+use reqwest::Url;
+use serde::de::{DeserializeOwned, Error};
+use serde_json::Value;
+use crate::networks::http::client::{block_on, AsyncHttpClient};
+use crate::networks::http::error::{HttpError, HttpResult};
+use crate::networks::http::middleware::BoxFuture;
+use crate::networks::http::request::{AsyncRequestBuilder, Request};
+
+/// 默认最多请求的页数
+pub const DEFAULT_MAX_PAGES: usize = 1000;
+
+/// 分页方式
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Pagination {
+    /// 页码分页,页码从`start`开始;`size`为每页数量的参数名与取值,返回数量不足时视为最后一页
+    Page {
+        param: String,
+        start: u64,
+        size: Option<(String, u64)>,
+    },
+    /// 偏移量分页,返回数量少于`limit`时视为最后一页
+    Offset {
+        offset_param: String,
+        limit_param: String,
+        limit: u64,
+    },
+    /// 游标分页,`field`为下一页游标在响应体中的路径(以`.`分隔),游标为空时结束
+    Cursor {
+        param: String,
+        field: String,
+    },
+    /// 请求`Link`响应头中`rel="next"`的地址,不存在时结束
+    LinkHeader,
+}
+
+impl Pagination {
+    /// 页码分页,页码从1开始,返回空列表时结束
+    pub fn page(param: &str) -> Self {
+        Pagination::Page { param: param.to_string(), start: 1, size: None }
+    }
+
+    /// 设置页码分页的起始页码
+    pub fn start(mut self, page: u64) -> Self {
+        if let Pagination::Page { start, .. } = &mut self {
+            *start = page;
+        }
+        self
+    }
+
+    /// 设置页码分页的每页数量参数
+    pub fn per_page(mut self, param: &str, per_page: u64) -> Self {
+        if let Pagination::Page { size, .. } = &mut self {
+            *size = Some((param.to_string(), per_page));
+        }
+        self
+    }
+
+    /// 偏移量分页
+    pub fn offset(offset_param: &str, limit_param: &str, limit: u64) -> Self {
+        Pagination::Offset { offset_param: offset_param.to_string(), limit_param: limit_param.to_string(), limit }
+    }
+
+    /// 游标分页
+    pub fn cursor(param: &str, field: &str) -> Self {
+        Pagination::Cursor { param: param.to_string(), field: field.to_string() }
+    }
+
+    /// `Link`响应头分页
+    pub fn link_header() -> Self {
+        Pagination::LinkHeader
+    }
+}
+
+/// 分页请求的结果,由`paginate`请求方法创建.出错后不再继续请求
+pub struct Paginator<T> {
+    pages: LazyPages<PageRequests>,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Debug for Paginator<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Paginator")
+            .field("pagination", &self.pages.source().map(|source| &source.pagination))
+            .field("fetched", &self.pages.fetched())
+            .finish()
+    }
+}
+
+impl<T: DeserializeOwned + Send + 'static> Paginator<T> {
+    pub(crate) fn new(builder: AsyncRequestBuilder, pagination: Pagination) -> Self {
+        let (client, request) = builder.into_parts();
+        let request = request.map(|mut request| {
+            match &pagination {
+                Pagination::Page { param, start, size } => {
+                    set_query(&mut request.url, param, &start.to_string());
+                    if let Some((param, size)) = size {
+                        set_query(&mut request.url, param, &size.to_string());
+                    }
+                }
+                Pagination::Offset { offset_param, limit_param, limit } => {
+                    set_query(&mut request.url, offset_param, "0");
+                    set_query(&mut request.url, limit_param, &limit.to_string());
+                }
+                Pagination::Cursor { .. } | Pagination::LinkHeader => {}
+            }
+            request
+        });
+        let position = match &pagination {
+            Pagination::Page { start, .. } => *start,
+            _ => 0,
+        };
+        let source = PageRequests { client, next: Some(request), pagination, items: Vec::new(), position };
+        Paginator { pages: LazyPages::new(source, Some(DEFAULT_MAX_PAGES)), _marker: PhantomData }
+    }
+
+    /// 设置列表在响应体中的路径(以`.`分隔),默认响应体本身就是列表
+    pub fn items(mut self, path: &str) -> Self {
+        if let Some(source) = self.pages.source_mut() {
+            source.items = path.split('.').filter(|s| !s.is_empty()).map(str::to_string).collect();
+        }
+        self
+    }
+
+    /// 设置最多请求的页数,防止服务端分页异常时无限请求,默认为[`DEFAULT_MAX_PAGES`]
+    pub fn max_pages(mut self, max_pages: usize) -> Self {
+        self.pages.set_max_pages(max_pages);
+        self
+    }
+
+    /// 获取异步的元素流
+    pub fn stream(self) -> BoxStream<'static, HttpResult<T>> {
+        self.pages.stream().map(|item| item.and_then(decode)).boxed()
+    }
+}
+
+impl<T: DeserializeOwned + Send + 'static> Iterator for Paginator<T> {
+    type Item = HttpResult<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.pages.next_blocking().map(|item| item.and_then(decode))
+    }
+}
+
+// 逐页获取元素的数据来源
+pub(crate) trait PageSource: Send + 'static {
+    type Error: Send + 'static;
+
+    // 请求下一页的元素,返回None表示已经没有下一页
+    fn fetch(&mut self) -> BoxFuture<'_, Result<Option<Vec<Value>>, Self::Error>>;
+}
+
+// 按需逐页请求的元素序列,只有取完当前页后才会请求下一页,出错后不再继续请求.
+// 供分页请求与GraphQL游标分页共用
+pub(crate) struct LazyPages<S> {
+    // 同步迭代时移入运行时执行,执行期间为None
+    state: Option<LazyState<S>>,
+}
+
+struct LazyState<S> {
+    source: S,
+    max_pages: Option<usize>,
+    // 已经请求的页数
+    fetched: usize,
+    done: bool,
+    buffer: VecDeque<Value>,
+}
+
+impl<S: PageSource> LazyPages<S> {
+    pub(crate) fn new(source: S, max_pages: Option<usize>) -> Self {
+        let state = LazyState { source, max_pages, fetched: 0, done: false, buffer: VecDeque::new() };
+        LazyPages { state: Some(state) }
+    }
+
+    pub(crate) fn source(&self) -> Option<&S> {
+        self.state.as_ref().map(|state| &state.source)
+    }
+
+    pub(crate) fn source_mut(&mut self) -> Option<&mut S> {
+        self.state.as_mut().map(|state| &mut state.source)
+    }
+
+    pub(crate) fn fetched(&self) -> Option<usize> {
+        self.state.as_ref().map(|state| state.fetched)
+    }
+
+    pub(crate) fn set_max_pages(&mut self, max_pages: usize) {
+        if let Some(state) = &mut self.state {
+            state.max_pages = Some(max_pages);
+        }
+    }
+
+    // 同步获取下一个元素
+    pub(crate) fn next_blocking(&mut self) -> Option<Result<Value, S::Error>> {
+        let mut state = self.state.take()?;
+        // 缓冲区中还有元素时无需切换到运行时
+        let item = match state.buffer.pop_front() {
+            Some(item) => Some(Ok(item)),
+            None => {
+                let (state_back, item) = block_on(async move {
+                    let item = state.next().await;
+                    (state, item)
+                });
+                state = state_back;
+                item
+            }
+        };
+        self.state = Some(state);
+        item
+    }
+
+    // 异步的元素流
+    pub(crate) fn stream(self) -> BoxStream<'static, Result<Value, S::Error>> {
+        stream::unfold(self.state, |state| async move {
+            let mut state = state?;
+            let item = state.next().await?;
+            Some((item, Some(state)))
+        }).boxed()
+    }
+}
+
+impl<S: PageSource> LazyState<S> {
+    // 获取下一个元素,当前页已经取完时请求下一页
+    async fn next(&mut self) -> Option<Result<Value, S::Error>> {
+        loop {
+            if let Some(item) = self.buffer.pop_front() {
+                return Some(Ok(item));
+            }
+            if self.done || self.max_pages.is_some_and(|max| self.fetched >= max) {
+                return None;
+            }
+            match self.source.fetch().await {
+                Ok(Some(items)) => {
+                    self.fetched += 1;
+                    self.buffer.extend(items);
+                }
+                Ok(None) => self.done = true,
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e));
+                }
+            }
+        }
+    }
+}
+
+// 按照分页方式生成每一页的请求
+struct PageRequests {
+    client: AsyncHttpClient,
+    // 下一页的请求,None表示已经结束
+    next: Option<HttpResult<Request>>,
+    pagination: Pagination,
+    // 列表在响应体中的路径
+    items: Vec<String>,
+    // 当前的页码或偏移量
+    position: u64,
+}
+
+impl PageSource for PageRequests {
+    type Error = HttpError;
+
+    fn fetch(&mut self) -> BoxFuture<'_, HttpResult<Option<Vec<Value>>>> {
+        Box::pin(async move {
+            match self.next.take() {
+                Some(request) => self.fetch_page(request?).await.map(Some),
+                None => Ok(None),
+            }
+        })
+    }
+}
+
+impl PageRequests {
+    // 请求一页并准备下一页的请求
+    async fn fetch_page(&mut self, mut request: Request) -> HttpResult<Vec<Value>> {
+        let response = self.client.execute(request.clone()).await?.error_for_status()?;
+        let body: Value = serde_json::from_slice(&response.body).map_err(|source| HttpError::Decode {
+            source,
+            status: response.status,
+            body: response.body.clone(),
+        })?;
+        let items = match lookup(&body, &self.items) {
+            Some(Value::Array(items)) => items.clone(),
+            _ => return Err(HttpError::Decode {
+                source: serde_json::Error::custom(format!("expected an array at `{}`", self.items.join("."))),
+                status: response.status,
+                body: response.body,
+            }),
+        };
+        let count = items.len() as u64;
+
+        let has_next = match &self.pagination {
+            Pagination::Page { param, size, .. } => {
+                let full = size.as_ref().map_or(count > 0, |(_, size)| count >= *size);
+                self.position += 1;
+                set_query(&mut request.url, param, &self.position.to_string());
+                full
+            }
+            Pagination::Offset { offset_param, limit, .. } => {
+                self.position += count;
+                set_query(&mut request.url, offset_param, &self.position.to_string());
+                count > 0 && count >= *limit
+            }
+            Pagination::Cursor { param, field } => {
+                let path: Vec<String> = field.split('.').map(str::to_string).collect();
+                let cursor = match lookup(&body, &path) {
+                    Some(Value::String(cursor)) => Some(cursor.clone()),
+                    Some(Value::Number(cursor)) => Some(cursor.to_string()),
+                    _ => None,
+                };
+                match cursor.filter(|cursor| !cursor.is_empty()) {
+                    Some(cursor) => {
+                        set_query(&mut request.url, param, &cursor);
+                        true
+                    }
+                    None => false,
+                }
+            }
+            Pagination::LinkHeader => {
+                let next = response.headers.get_all(LINK).iter()
+                    .filter_map(|value| value.to_str().ok())
+                    .find_map(next_link)
+                    .map(|link| response.url.join(link));
+                match next {
+                    Some(Ok(url)) => {
+                        request.follow(url);
+                        true
+                    }
+                    Some(Err(e)) => return Err(HttpError::InvalidUrl(format!("invalid next link: {}", e))),
+                    None => false,
+                }
+            }
+        };
+        if has_next {
+            self.next = Some(Ok(request));
+        }
+        Ok(items)
+    }
+}
+
+// 按路径查找Json值,路径为空时返回自身
+fn lookup<'a>(value: &'a Value, path: &[String]) -> Option<&'a Value> {
+    path.iter().try_fold(value, |value, field| value.get(field))
+}
+
+// 设置Query参数,覆盖同名参数
+fn set_query(url: &mut Url, key: &str, value: &str) {
+    let pairs: Vec<(String, String)> = url.query_pairs()
+        .filter(|(name, _)| name != key)
+        .map(|(name, value)| (name.into_owned(), value.into_owned()))
+        .collect();
+    url.query_pairs_mut().clear().extend_pairs(pairs).append_pair(key, value);
+}
+
+// 从`Link`响应头中找到`rel="next"`的地址.
+// 地址中可能包含逗号,因此先取出`<...>`再解析其后直到下一个未被引号包含的逗号之前的参数
+fn next_link(header: &str) -> Option<&str> {
+    let mut rest = header;
+    loop {
+        let (url, after) = rest.trim_start_matches(|c: char| c == ',' || c.is_whitespace())
+            .strip_prefix('<')?
+            .split_once('>')?;
+        let mut quoted = false;
+        let end = after.find(|c| {
+            if c == '"' {
+                quoted = !quoted;
+            }
+            c == ',' && !quoted
+        }).unwrap_or(after.len());
+        let is_next = after[..end].split(';')
+            .filter_map(|param| param.split_once('='))
+            .any(|(name, value)| name.trim().eq_ignore_ascii_case("rel")
+                && value.trim().trim_matches('"').split_whitespace().any(|rel| rel.eq_ignore_ascii_case("next")));
+        if is_next {
+            return Some(url);
+        }
+        rest = &after[end..];
+    }
+}
+
+fn decode<T: DeserializeOwned>(item: Value) -> HttpResult<T> {
+    T::deserialize(&item).map_err(|source| HttpError::Decode {
+        source,
+        status: reqwest::StatusCode::OK,
+        body: item.to_string().into(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::StreamExt;
+    use reqwest::Method;
+    use serde_json::json;
+    use crate::networks::http::{AsyncHttpClient, HttpClient, HttpError};
+    use crate::networks::http::mock::{MockResponse, MockServer};
+    use crate::networks::http::paginate::{next_link, Pagination};
+
+    // 共7个元素的列表中的一段
+    fn slice(start: u64, len: u64) -> Vec<u64> {
+        (start..7.min(start + len)).collect()
+    }
+
+    /// 测试页码分页、列表路径与最大页数
+    #[test]
+    fn test_page() {
+        let server = MockServer::start();
+        server.mock(Method::GET, "/items").respond_with(|request| {
+            let page: u64 = request.query_param("page").unwrap().parse().unwrap();
+            let size: u64 = request.query_param("size").unwrap().parse().unwrap();
+            MockResponse::json(&json!({"page": page, "data": slice((page - 1) * size, size)}))
+        });
+        let client = HttpClient::new();
+        let items: Vec<u64> = client.get(&server.url("/items?page=9&sort=id"))
+            .paginate(Pagination::page("page").per_page("size", 3))
+            .items("data")
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(items, (0..7).collect::<Vec<_>>());
+        assert_eq!(server.requests().len(), 3);
+        assert_eq!(server.requests()[2].query_param("sort"), Some("id"));
+
+        let limited = client.get(&server.url("/items"))
+            .paginate::<u64>(Pagination::page("page").per_page("size", 2))
+            .items("data")
+            .max_pages(2);
+        assert_eq!(limited.count(), 4);
+
+        let mut wrong_path = client.get(&server.url("/items")).paginate::<u64>(Pagination::page("page").per_page("size", 2));
+        assert!(matches!(wrong_path.next(), Some(Err(HttpError::Decode { .. }))));
+        assert!(wrong_path.next().is_none());
+    }
+
+    /// 测试偏移量分页与游标分页的异步流
+    #[tokio::test]
+    async fn test_offset_and_cursor() {
+        let server = MockServer::start();
+        server.mock(Method::GET, "/offset").respond_with(|request| {
+            let offset: u64 = request.query_param("offset").unwrap().parse().unwrap();
+            MockResponse::json(&slice(offset, request.query_param("limit").unwrap().parse().unwrap()))
+        });
+        server.mock(Method::GET, "/cursor").respond_with(|request| {
+            let start: u64 = request.query_param("after").map_or(0, |cursor| cursor.parse().unwrap());
+            let next = (start + 4 < 7).then(|| (start + 4).to_string());
+            MockResponse::json(&json!({"items": slice(start, 4), "meta": {"next": next}}))
+        });
+        let client = AsyncHttpClient::new();
+        let items: Vec<u64> = client.get(&server.url("/offset"))
+            .paginate::<u64>(Pagination::offset("offset", "limit", 5))
+            .stream()
+            .map(Result::unwrap)
+            .collect()
+            .await;
+        assert_eq!(items, (0..7).collect::<Vec<_>>());
+
+        let items: Vec<u64> = client.get(&server.url("/cursor"))
+            .paginate::<u64>(Pagination::cursor("after", "meta.next"))
+            .items("items")
+            .stream()
+            .map(Result::unwrap)
+            .collect()
+            .await;
+        assert_eq!(items, (0..7).collect::<Vec<_>>());
+        let requests = server.requests();
+        let cursors: Vec<Option<&str>> = requests.iter()
+            .filter(|request| request.path == "/cursor")
+            .map(|request| request.query_param("after"))
+            .collect();
+        assert_eq!(cursors, vec![None, Some("4")]);
+    }
+
+    /// 测试Link响应头分页与错误状态码
+    #[test]
+    fn test_link_header() {
+        assert_eq!(next_link(r#"<https://a.test/?page=1>; rel="prev", <https://a.test/?page=3>; rel="next""#),
+                   Some("https://a.test/?page=3"));
+        assert_eq!(next_link("<https://a.test/?page=3>; rel=\"last first\""), None);
+        assert_eq!(next_link(r#"<https://a.test/?ids=1,2>; rel="prev"; title="a, b", <https://a.test/?ids=3,4>; rel="next""#),
+                   Some("https://a.test/?ids=3,4"));
+
+        let server = MockServer::start();
+        let next = server.url("/second");
+        server.mock(Method::GET, "/first").respond(MockResponse::json(&[1, 2])
+            .header("Link", &format!("<{}>; rel=\"next\", </first>; rel=\"first\"", next)));
+        server.mock(Method::GET, "/second").respond(MockResponse::json(&[3]).header("Link", "</third>; rel=next"));
+        server.mock(Method::GET, "/third").respond(MockResponse::new(500));
+
+        let mut pages = HttpClient::new().get(&server.url("/first")).paginate::<u64>(Pagination::link_header());
+        let items: Vec<u64> = pages.by_ref().take(3).map(Result::unwrap).collect();
+        assert_eq!(items, vec![1, 2, 3]);
+        assert!(matches!(pages.next(), Some(Err(e)) if e.status().map(|s| s.as_u16()) == Some(500)));
+        assert!(pages.next().is_none());
+
+        // 跟随跨域的下一页地址时不携带Authorization
+        let other = MockServer::start();
+        other.mock(Method::GET, "/more").respond_with(|request| MockResponse::json(&[request.header("authorization").is_some()]));
+        let link = format!("<{}>; rel=next", other.url("/more"));
+        server.mock(Method::GET, "/local").respond_with(move |request| {
+            MockResponse::json(&[request.header("authorization").is_some()]).header("Link", &link)
+        });
+        server.mock(Method::GET, "/start").respond(MockResponse::json(&[true]).header("Link", "</local>; rel=next"));
+        let sent: Vec<bool> = HttpClient::new().get(&server.url("/start")).bearer_auth("t")
+            .paginate::<bool>(Pagination::link_header())
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(sent, vec![true, true, false]);
+    }
+}
@@ -0,0 +1,65 @@
+//! # 百分号编码
+//!
+//! 按照RFC 3986对路径、查询参数进行百分号编码与解码,供请求签名、REST客户端、服务端路由等模块共用.
+
+/// 按照RFC 3986进行百分号编码,除非保留字符(`A-Z a-z 0-9 - _ . ~`)外全部编码
+/// # Examples
+/// ```
+/// use toys::networks::http::percent::encode;
+/// assert_eq!(encode("a b/张"), "a%20b%2F%E5%BC%A0");
+/// ```
+pub fn encode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => encoded.push(byte as char),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+/// 解码百分号编码,非法的编码序列原样保留,解码结果不是UTF-8时以替换字符代替
+/// # Examples
+/// ```
+/// use toys::networks::http::percent::decode;
+/// assert_eq!(decode("a%20b%2F%E5%BC%A0"), "a b/张");
+/// ```
+pub fn decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 3 <= bytes.len() && bytes[i + 1..i + 3].iter().all(u8::is_ascii_hexdigit) {
+            let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).expect("hex digits are ascii");
+            decoded.push(u8::from_str_radix(hex, 16).expect("hex digits are valid"));
+            i += 3;
+            continue;
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::networks::http::percent::{decode, encode};
+
+    /// 测试百分号编码
+    #[test]
+    fn test_encode() {
+        assert_eq!(encode("a-b_c.d~e"), "a-b_c.d~e");
+        assert_eq!(encode("a b/c?d#e%"), "a%20b%2Fc%3Fd%23e%25");
+        assert_eq!(encode("张三"), "%E5%BC%A0%E4%B8%89");
+    }
+
+    /// 测试百分号解码
+    #[test]
+    fn test_decode() {
+        assert_eq!(decode("%E5%BC%A0%E4%B8%89"), "张三");
+        assert_eq!(decode("a%2fb%41"), "a/bA");
+        assert_eq!(decode("100%"), "100%");
+        assert_eq!(decode("%zz%+1%4"), "%zz%+1%4");
+    }
+}
@@ -0,0 +1,166 @@
+//! # 代理配置
+//!
+//! 支持HTTP、HTTPS以及SOCKS5(`socks5://`本地解析域名,`socks5h://`由代理解析域名)代理,
+//! 通过[`HttpClientBuilder::proxy`](crate::networks::http::HttpClientBuilder::proxy)添加到客户端.
+//! 未添加代理时客户端会使用`HTTP_PROXY`、`HTTPS_PROXY`、`ALL_PROXY`与`NO_PROXY`环境变量.
+
+use std::fmt::{Debug, Formatter};
+use reqwest::NoProxy;
+use crate::networks::http::error::{HttpError, HttpResult};
+
+/// 代理作用的请求协议
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProxyScope {
+    /// 全部请求
+    All,
+    /// 只代理http请求
+    Http,
+    /// 只代理https请求
+    Https,
+}
+
+/// 代理
+///
+/// # Examples
+/// ```
+/// use toys::networks::http::HttpClient;
+/// use toys::networks::http::proxy::Proxy;
+/// let client = HttpClient::builder()
+///     .proxy(Proxy::all("socks5h://127.0.0.1:1080").no_proxy("localhost, .internal.example.com, 10.0.0.0/8"))
+///     .build()
+///     .unwrap();
+/// ```
+#[derive(Clone)]
+pub struct Proxy {
+    scope: ProxyScope,
+    url: String,
+    // 代理认证的用户名与密码
+    basic_auth: Option<(String, String)>,
+    // 不经过代理的主机列表,逗号分隔
+    no_proxy: Option<String>,
+}
+
+impl Debug for Proxy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Proxy")
+            .field("scope", &self.scope)
+            .field("url", &self.url)
+            .field("basic_auth", &self.basic_auth.as_ref().map(|(user, _)| user))
+            .field("no_proxy", &self.no_proxy)
+            .finish()
+    }
+}
+
+impl Proxy {
+    /// 代理全部请求
+    pub fn all(url: &str) -> Self {
+        Proxy::new(ProxyScope::All, url)
+    }
+
+    /// 只代理http请求
+    pub fn http(url: &str) -> Self {
+        Proxy::new(ProxyScope::Http, url)
+    }
+
+    /// 只代理https请求
+    pub fn https(url: &str) -> Self {
+        Proxy::new(ProxyScope::Https, url)
+    }
+
+    fn new(scope: ProxyScope, url: &str) -> Self {
+        Proxy { scope, url: url.to_string(), basic_auth: None, no_proxy: None }
+    }
+
+    /// 设置代理认证,也可以直接写在代理地址中
+    pub fn basic_auth(mut self, username: &str, password: &str) -> Self {
+        self.basic_auth = Some((username.to_string(), password.to_string()));
+        self
+    }
+
+    /// 设置不经过代理的主机,逗号分隔.支持域名(同时匹配子域名)、IP地址、网段以及`*`
+    pub fn no_proxy(mut self, hosts: &str) -> Self {
+        self.no_proxy = Some(hosts.to_string());
+        self
+    }
+
+    /// 使用`NO_PROXY`或`no_proxy`环境变量设置不经过代理的主机
+    pub fn no_proxy_from_env(mut self) -> Self {
+        self.no_proxy = std::env::var("NO_PROXY").or_else(|_| std::env::var("no_proxy")).ok();
+        self
+    }
+
+    /// 代理作用的请求协议
+    pub fn scope(&self) -> ProxyScope {
+        self.scope
+    }
+
+    /// 代理地址
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    // 转换为reqwest的代理
+    pub(crate) fn to_reqwest(&self) -> HttpResult<reqwest::Proxy> {
+        let proxy = match self.scope {
+            ProxyScope::All => reqwest::Proxy::all(&self.url),
+            ProxyScope::Http => reqwest::Proxy::http(&self.url),
+            ProxyScope::Https => reqwest::Proxy::https(&self.url),
+        };
+        let mut proxy = proxy.map_err(|e| HttpError::Builder(format!("invalid proxy {}: {}", self.url, e)))?;
+        if let Some((username, password)) = &self.basic_auth {
+            proxy = proxy.basic_auth(username, password);
+        }
+        Ok(proxy.no_proxy(self.no_proxy.as_deref().and_then(NoProxy::from_string)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, Ipv4Addr};
+    use reqwest::Method;
+    use crate::networks::http::HttpClient;
+    use crate::networks::http::error::HttpError;
+    use crate::networks::http::mock::{MockResponse, MockServer};
+    use crate::networks::http::proxy::Proxy;
+
+    /// 测试HTTP代理、代理认证以及NO_PROXY例外
+    #[test]
+    fn test_http_proxy() {
+        let proxy = MockServer::start();
+        proxy.mock(Method::GET, "/hello").respond_with(|request| {
+            MockResponse::text(format!("proxied {}", request.header("host").unwrap_or("")))
+        });
+        let target = MockServer::start();
+        target.mock(Method::GET, "/hello").respond(MockResponse::text("direct"));
+
+        let client = HttpClient::builder()
+            .proxy(Proxy::http(&proxy.url("")).basic_auth("user", "pass").no_proxy("localhost"))
+            .build()
+            .unwrap();
+        let body = client.get("http://intranet.example.test/hello").send_text().unwrap().body;
+        assert_eq!(body, "proxied intranet.example.test");
+        assert_eq!(proxy.requests()[0].header("proxy-authorization"), Some("Basic dXNlcjpwYXNz"));
+        let direct = target.url("/hello").replace("127.0.0.1", "localhost");
+        assert_eq!(client.get(&direct).send_text().unwrap().body, "direct");
+        assert_eq!(proxy.requests().len(), 1);
+    }
+
+    /// 测试DNS解析覆盖与配置错误
+    #[test]
+    fn test_resolve_and_errors() {
+        let server = MockServer::start();
+        server.mock(Method::GET, "/").respond(MockResponse::text("resolved"));
+        let client = HttpClient::builder()
+            .no_proxy()
+            .resolve("api.example.test", IpAddr::V4(Ipv4Addr::LOCALHOST))
+            .build()
+            .unwrap();
+        let url = format!("http://api.example.test:{}/", server.addr().port());
+        assert_eq!(client.get(&url).send_text().unwrap().body, "resolved");
+
+        let error = HttpClient::builder().proxy(Proxy::all("ftp://127.0.0.1:21")).build().unwrap_err();
+        assert!(matches!(error, HttpError::Builder(message) if message.contains("invalid proxy")));
+        assert!(HttpClient::builder().root_certificate(b"not a certificate").build().is_err());
+        assert!(HttpClient::builder().identity_pkcs12(b"not a pkcs12 archive", "").build().is_err());
+    }
+}
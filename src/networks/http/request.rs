@@ -0,0 +1,434 @@
+//! # HTTP请求与链式请求构建器
+
+use std::time::Duration;
+use bytes::Bytes;
+use reqwest::header::{AUTHORIZATION, CONTENT_TYPE, COOKIE, HeaderMap, HeaderName, HeaderValue, PROXY_AUTHORIZATION};
+use reqwest::{Method, Url};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use crate::networks::http::auth::basic_header;
+use crate::networks::http::client::{AsyncHttpClient, block_on};
+use crate::networks::http::error::{HttpError, HttpResult};
+use crate::networks::http::multipart::Form;
+use crate::networks::http::paginate::{Pagination, Paginator};
+use crate::networks::http::response::HttpResponse;
+use crate::networks::http::retry::RetryPolicy;
+use crate::networks::http::sse::EventSource;
+
+/// 请求体
+#[derive(Debug, Clone, Default)]
+pub enum Body {
+    /// 无请求体
+    #[default]
+    Empty,
+    /// 内存中的字节数据
+    Bytes(Bytes),
+    /// multipart/form-data 表单,包含文件或数据流时以流的方式发送
+    Multipart(Form),
+}
+
+impl Body {
+    /// 获取内存中的请求体字节,无请求体或请求体为表单时返回None
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            Body::Bytes(bytes) => Some(bytes),
+            _ => None,
+        }
+    }
+}
+
+/// 一个待发送的HTTP请求
+#[derive(Debug, Clone)]
+pub struct Request {
+    // 请求方法
+    pub method: Method,
+    // 完整的请求地址
+    pub url: Url,
+    // 请求头,不包含客户端的默认请求头
+    pub headers: HeaderMap,
+    // 请求体
+    pub body: Body,
+    // 本次请求的总超时时间,覆盖客户端配置
+    pub timeout: Option<Duration>,
+    // 本次请求的重试策略,覆盖客户端配置
+    pub retry: Option<RetryPolicy>,
+}
+
+impl Request {
+    /// 创建一个无请求头、无请求体的请求
+    pub fn new(method: Method, url: Url) -> Self {
+        Request { method, url, headers: HeaderMap::new(), body: Body::Empty, timeout: None, retry: None }
+    }
+
+    // 将请求改为发往`url`,跨域时移除认证与Cookie请求头,用于跟随重定向与分页链接
+    pub(crate) fn follow(&mut self, url: Url) {
+        if url.origin() != self.url.origin() {
+            self.headers.remove(AUTHORIZATION);
+            self.headers.remove(PROXY_AUTHORIZATION);
+            self.headers.remove(COOKIE);
+        }
+        self.url = url;
+    }
+}
+
+/// 异步链式请求构建器,由`AsyncHttpClient`的请求方法创建
+///
+/// # Examples
+/// ```no_run
+/// use std::collections::HashMap;
+/// use toys::networks::http::AsyncHttpClient;
+/// # async fn run() -> toys::networks::http::HttpResult<()> {
+/// let client = AsyncHttpClient::new();
+/// let response = client.put("http://127.0.0.1:13001/example/index")
+///     .header("X-Trace", "1")
+///     .query(&[("name", "张三")])
+///     .json(&HashMap::from([("age", 18)]))
+///     .send_json::<HashMap<String, String>>()
+///     .await?;
+/// println!("{} {:?} {:?}", response.status, response.elapsed, response.body);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct AsyncRequestBuilder {
+    client: AsyncHttpClient,
+    // 构建过程中出现的第一个错误会保存下来,在发送时返回
+    request: HttpResult<Request>,
+}
+
+impl AsyncRequestBuilder {
+    pub(crate) fn new(client: AsyncHttpClient, request: HttpResult<Request>) -> Self {
+        AsyncRequestBuilder { client, request }
+    }
+
+    /// 添加一个请求头
+    pub fn header(self, key: &str, value: &str) -> Self {
+        self.and_then(|request| {
+            match (HeaderName::from_bytes(key.as_bytes()), HeaderValue::from_str(value)) {
+                (Ok(name), Ok(value)) => {
+                    request.headers.insert(name, value);
+                    Ok(())
+                }
+                _ => Err(HttpError::Builder(format!("invalid header: {}: {}", key, value))),
+            }
+        })
+    }
+
+    /// 设置Bearer Token认证请求头
+    pub fn bearer_auth(self, token: &str) -> Self {
+        self.and_then(|request| {
+            let mut value = HeaderValue::from_str(&format!("Bearer {}", token))
+                .map_err(|_| HttpError::Builder("invalid bearer token".into()))?;
+            value.set_sensitive(true);
+            request.headers.insert(AUTHORIZATION, value);
+            Ok(())
+        })
+    }
+
+    /// 设置Basic认证请求头
+    pub fn basic_auth(self, username: &str, password: &str) -> Self {
+        self.and_then(|request| {
+            request.headers.insert(AUTHORIZATION, basic_header(username, password));
+            Ok(())
+        })
+    }
+
+    /// 批量添加请求头
+    pub fn headers(self, headers: HeaderMap) -> Self {
+        self.and_then(|request| {
+            request.headers.extend(headers);
+            Ok(())
+        })
+    }
+
+    /// 追加Query参数,参数可以是任何能够序列化为键值对的类型,如`HashMap`、`&[(K, V)]`或结构体
+    pub fn query<T: Serialize + ?Sized>(self, query: &T) -> Self {
+        self.and_then(|request| {
+            let result = {
+                let mut pairs = request.url.query_pairs_mut();
+                query.serialize(serde_urlencoded::Serializer::new(&mut pairs)).map(|_| ())
+            };
+            // 没有参数时去掉多余的'?'
+            if request.url.query() == Some("") {
+                request.url.set_query(None);
+            }
+            result.map_err(|e| HttpError::Builder(format!("invalid query: {}", e)))
+        })
+    }
+
+    /// 以Json格式设置请求体
+    pub fn json<T: Serialize + ?Sized>(self, body: &T) -> Self {
+        self.and_then(|request| {
+            let bytes = serde_json::to_vec(body).map_err(HttpError::Encode)?;
+            set_body(request, Bytes::from(bytes), "application/json");
+            Ok(())
+        })
+    }
+
+    /// 以`application/x-www-form-urlencoded`格式设置请求体
+    pub fn form<T: Serialize + ?Sized>(self, form: &T) -> Self {
+        self.and_then(|request| {
+            let encoded = serde_urlencoded::to_string(form)
+                .map_err(|e| HttpError::Builder(format!("invalid form: {}", e)))?;
+            set_body(request, Bytes::from(encoded), "application/x-www-form-urlencoded");
+            Ok(())
+        })
+    }
+
+    /// 以`multipart/form-data`格式设置请求体
+    pub fn multipart(self, form: Form) -> Self {
+        self.and_then(|request| {
+            let content_type = HeaderValue::from_str(&form.content_type())
+                .map_err(|_| HttpError::Builder("invalid multipart boundary".to_string()))?;
+            request.headers.insert(CONTENT_TYPE, content_type);
+            request.body = Body::Multipart(form);
+            Ok(())
+        })
+    }
+
+    /// 设置原始请求体,不会修改Content-Type
+    pub fn body<B: Into<Bytes>>(self, body: B) -> Self {
+        self.and_then(|request| {
+            request.body = Body::Bytes(body.into());
+            Ok(())
+        })
+    }
+
+    /// 设置本次请求的总超时时间
+    pub fn timeout(self, timeout: Duration) -> Self {
+        self.and_then(|request| {
+            request.timeout = Some(timeout);
+            Ok(())
+        })
+    }
+
+    /// 设置本次请求的重试策略
+    pub fn retry(self, policy: RetryPolicy) -> Self {
+        self.and_then(|request| {
+            request.retry = Some(policy);
+            Ok(())
+        })
+    }
+
+    /// 构建请求但不发送
+    pub fn build(self) -> HttpResult<Request> {
+        self.request
+    }
+
+    // 拆分为客户端与请求
+    pub(crate) fn into_parts(self) -> (AsyncHttpClient, HttpResult<Request>) {
+        (self.client, self.request)
+    }
+
+    /// 发送请求,返回带有原始响应体的响应,不会检查响应状态码
+    pub async fn send(self) -> HttpResult<HttpResponse> {
+        self.client.execute(self.request?).await
+    }
+
+    /// 发送请求,以文本格式返回响应体.状态码为4xx或5xx时返回`HttpError::Status`
+    pub async fn send_text(self) -> HttpResult<HttpResponse<String>> {
+        let response = self.send().await?.error_for_status()?;
+        let text = response.text();
+        Ok(response.map(|_| text))
+    }
+
+    /// 发送请求,将Json响应体反序列化为`R`.状态码为4xx或5xx时返回`HttpError::Status`
+    pub async fn send_json<R: DeserializeOwned>(self) -> HttpResult<HttpResponse<R>> {
+        self.send().await?.error_for_status()?.json()
+    }
+
+    /// 以Server-Sent Events事件流的方式接收响应
+    pub fn sse(self) -> EventSource {
+        EventSource::new(self)
+    }
+
+    /// 按照分页方式自动翻页,逐条返回列表元素,通过`Paginator::stream`异步读取
+    pub fn paginate<T: DeserializeOwned + Send + 'static>(self, pagination: Pagination) -> Paginator<T> {
+        Paginator::new(self, pagination)
+    }
+
+    // 仅在之前没有出错时修改请求
+    fn and_then<F>(mut self, f: F) -> Self where F: FnOnce(&mut Request) -> HttpResult<()> {
+        if let Ok(request) = &mut self.request {
+            if let Err(e) = f(request) {
+                self.request = Err(e);
+            }
+        }
+        self
+    }
+}
+
+// 设置请求体,未指定Content-Type时使用默认值
+fn set_body(request: &mut Request, bytes: Bytes, content_type: &'static str) {
+    request.headers.entry(CONTENT_TYPE).or_insert(HeaderValue::from_static(content_type));
+    request.body = Body::Bytes(bytes);
+}
+
+/// 同步链式请求构建器,由`HttpClient`的请求方法创建,接口与`AsyncRequestBuilder`一致
+///
+/// # Examples
+/// ```no_run
+/// use std::collections::HashMap;
+/// use toys::networks::http::HttpClient;
+/// let client = HttpClient::new();
+/// let result: HashMap<String, String> = client.get("http://127.0.0.1:13001/example/index")
+///     .query(&[("name", "张三")])
+///     .send_json()
+///     .unwrap()
+///     .body;
+/// ```
+#[derive(Debug)]
+pub struct RequestBuilder {
+    inner: AsyncRequestBuilder,
+}
+
+impl RequestBuilder {
+    pub(crate) fn new(inner: AsyncRequestBuilder) -> Self {
+        RequestBuilder { inner }
+    }
+
+    /// 添加一个请求头
+    pub fn header(self, key: &str, value: &str) -> Self {
+        RequestBuilder::new(self.inner.header(key, value))
+    }
+
+    /// 设置Bearer Token认证请求头
+    pub fn bearer_auth(self, token: &str) -> Self {
+        RequestBuilder::new(self.inner.bearer_auth(token))
+    }
+
+    /// 设置Basic认证请求头
+    pub fn basic_auth(self, username: &str, password: &str) -> Self {
+        RequestBuilder::new(self.inner.basic_auth(username, password))
+    }
+
+    /// 批量添加请求头
+    pub fn headers(self, headers: HeaderMap) -> Self {
+        RequestBuilder::new(self.inner.headers(headers))
+    }
+
+    /// 追加Query参数
+    pub fn query<T: Serialize + ?Sized>(self, query: &T) -> Self {
+        RequestBuilder::new(self.inner.query(query))
+    }
+
+    /// 以Json格式设置请求体
+    pub fn json<T: Serialize + ?Sized>(self, body: &T) -> Self {
+        RequestBuilder::new(self.inner.json(body))
+    }
+
+    /// 以`application/x-www-form-urlencoded`格式设置请求体
+    pub fn form<T: Serialize + ?Sized>(self, form: &T) -> Self {
+        RequestBuilder::new(self.inner.form(form))
+    }
+
+    /// 以`multipart/form-data`格式设置请求体
+    pub fn multipart(self, form: Form) -> Self {
+        RequestBuilder::new(self.inner.multipart(form))
+    }
+
+    /// 设置原始请求体,不会修改Content-Type
+    pub fn body<B: Into<Bytes>>(self, body: B) -> Self {
+        RequestBuilder::new(self.inner.body(body))
+    }
+
+    /// 设置本次请求的总超时时间
+    pub fn timeout(self, timeout: Duration) -> Self {
+        RequestBuilder::new(self.inner.timeout(timeout))
+    }
+
+    /// 设置本次请求的重试策略
+    pub fn retry(self, policy: RetryPolicy) -> Self {
+        RequestBuilder::new(self.inner.retry(policy))
+    }
+
+    /// 构建请求但不发送
+    pub fn build(self) -> HttpResult<Request> {
+        self.inner.build()
+    }
+
+    // 转换为异步请求构建器
+    pub(crate) fn into_async(self) -> AsyncRequestBuilder {
+        self.inner
+    }
+
+    /// 发送请求,返回带有原始响应体的响应,不会检查响应状态码
+    pub fn send(self) -> HttpResult<HttpResponse> {
+        block_on(self.inner.send())
+    }
+
+    /// 发送请求,以文本格式返回响应体.状态码为4xx或5xx时返回`HttpError::Status`
+    pub fn send_text(self) -> HttpResult<HttpResponse<String>> {
+        block_on(self.inner.send_text())
+    }
+
+    /// 发送请求,将Json响应体反序列化为`R`.状态码为4xx或5xx时返回`HttpError::Status`
+    pub fn send_json<R: DeserializeOwned + Send + 'static>(self) -> HttpResult<HttpResponse<R>> {
+        block_on(self.inner.send_json())
+    }
+
+    /// 以Server-Sent Events事件流的方式接收响应,通过`EventSource::iter`同步读取事件
+    pub fn sse(self) -> EventSource {
+        EventSource::new(self.inner)
+    }
+
+    /// 按照分页方式自动翻页,返回逐条读取列表元素的迭代器
+    pub fn paginate<T: DeserializeOwned + Send + 'static>(self, pagination: Pagination) -> Paginator<T> {
+        Paginator::new(self.inner, pagination)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::time::Duration;
+    use reqwest::Method;
+    use crate::networks::http::{AsyncHttpClient, HttpClient, HttpError};
+    use crate::networks::http::request::Body;
+
+    /// 测试构建请求
+    #[test]
+    fn test_build_request() {
+        let client = HttpClient::builder().base_url("http://127.0.0.1:13001/example").build().unwrap();
+        let request = client.patch("index")
+            .header("X-Trace", "1")
+            .query(&[("name", "张三")])
+            .query(&HashMap::from([("age", "18")]))
+            .json(&HashMap::from([("locked", true)]))
+            .timeout(Duration::from_secs(10))
+            .build()
+            .unwrap();
+        assert_eq!(request.method, Method::PATCH);
+        assert_eq!(request.url.as_str(), "http://127.0.0.1:13001/example/index?name=%E5%BC%A0%E4%B8%89&age=18");
+        assert_eq!(request.headers["X-Trace"], "1");
+        assert_eq!(request.headers["Content-Type"], "application/json");
+        assert_eq!(request.body.as_bytes(), Some(r#"{"locked":true}"#.as_bytes()));
+        assert_eq!(request.timeout, Some(Duration::from_secs(10)));
+    }
+
+    /// 测试表单请求体以及所有请求方法
+    #[test]
+    fn test_form_and_methods() {
+        let client = AsyncHttpClient::new();
+        let request = client.post("http://localhost/form").form(&[("a", "1"), ("b", "x y")]).build().unwrap();
+        assert_eq!(request.headers["Content-Type"], "application/x-www-form-urlencoded");
+        assert_eq!(request.body.as_bytes(), Some("a=1&b=x+y".as_bytes()));
+
+        let url = "http://localhost/";
+        assert_eq!(client.get(url).build().unwrap().method, Method::GET);
+        assert_eq!(client.put(url).build().unwrap().method, Method::PUT);
+        assert_eq!(client.delete(url).build().unwrap().method, Method::DELETE);
+        assert_eq!(client.head(url).build().unwrap().method, Method::HEAD);
+        assert_eq!(client.options(url).build().unwrap().method, Method::OPTIONS);
+        assert!(matches!(client.get(url).build().unwrap().body, Body::Empty));
+    }
+
+    /// 测试构建错误会延迟到发送时返回
+    #[test]
+    fn test_builder_error() {
+        let result = HttpClient::new().get("index").header("X", "1").send();
+        assert!(matches!(result, Err(HttpError::InvalidUrl(_))));
+        let result = HttpClient::new().get("http://localhost/").header("bad header", "1").build();
+        assert!(matches!(result, Err(HttpError::Builder(_))));
+    }
+}
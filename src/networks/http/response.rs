@@ -0,0 +1,155 @@
+//! # HTTP响应
+
+use std::time::Duration;
+use bytes::Bytes;
+use encoding_rs::{Encoding, UTF_8};
+use reqwest::header::{CONTENT_TYPE, HeaderMap};
+use reqwest::{StatusCode, Url, Version};
+use serde::de::DeserializeOwned;
+use crate::networks::http::error::{HttpError, HttpResult};
+
+/// HTTP响应,除响应体外还携带状态码、响应头、耗时等元数据
+/// `B` 表示响应体类型,默认为原始字节
+#[derive(Debug, Clone)]
+pub struct HttpResponse<B = Bytes> {
+    // 响应状态码
+    pub status: StatusCode,
+    // 响应头
+    pub headers: HeaderMap,
+    // 最终的请求地址,发生重定向时与原始地址不同
+    pub url: Url,
+    // HTTP协议版本
+    pub version: Version,
+    // 从发送请求到读取完响应体的耗时
+    pub elapsed: Duration,
+    // 响应体
+    pub body: B,
+}
+
+impl<B> HttpResponse<B> {
+    /// 状态码是否为2xx
+    pub fn is_success(&self) -> bool {
+        self.status.is_success()
+    }
+
+    /// 获取一个响应头的文本值,不存在或不是合法文本时返回None
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).and_then(|value| value.to_str().ok())
+    }
+
+    /// 获取响应体
+    pub fn into_body(self) -> B {
+        self.body
+    }
+
+    /// 转换响应体,保留响应元数据
+    pub fn map<T, F>(self, f: F) -> HttpResponse<T> where F: FnOnce(B) -> T {
+        HttpResponse {
+            status: self.status,
+            headers: self.headers,
+            url: self.url,
+            version: self.version,
+            elapsed: self.elapsed,
+            body: f(self.body),
+        }
+    }
+}
+
+impl HttpResponse<Bytes> {
+    /// 获取原始响应体字节
+    pub fn bytes(&self) -> &Bytes {
+        &self.body
+    }
+
+    /// 以文本格式获取响应体,按照Content-Type中的charset解码(默认UTF-8),非法字符会被替换
+    pub fn text(&self) -> String {
+        let encoding = self.header(CONTENT_TYPE.as_str())
+            .and_then(|content_type| content_type.split(';').find_map(|param| {
+                let (key, value) = param.split_once('=')?;
+                key.trim().eq_ignore_ascii_case("charset").then(|| value.trim().trim_matches('"'))
+            }))
+            .and_then(|label| Encoding::for_label(label.as_bytes()))
+            .unwrap_or(UTF_8);
+        encoding.decode(&self.body).0.into_owned()
+    }
+
+    /// 状态码为4xx或5xx时返回`HttpError::Status`,错误中保留了响应体
+    pub fn error_for_status(self) -> HttpResult<Self> {
+        if self.status.is_client_error() || self.status.is_server_error() {
+            Err(HttpError::Status { status: self.status, url: self.url, body: self.body })
+        } else {
+            Ok(self)
+        }
+    }
+
+    /// 将Json响应体反序列化为`R`,失败时返回的`HttpError::Decode`中保留了原始响应体
+    pub fn json<R: DeserializeOwned>(self) -> HttpResult<HttpResponse<R>> {
+        let body = match serde_json::from_slice(&self.body) {
+            Ok(body) => body,
+            Err(source) => return Err(HttpError::Decode { source, status: self.status, body: self.body }),
+        };
+        Ok(self.map(|_| body))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::time::Duration;
+    use bytes::Bytes;
+    use reqwest::header::{CONTENT_TYPE, HeaderMap, HeaderValue};
+    use reqwest::{StatusCode, Url, Version};
+    use crate::networks::http::error::HttpError;
+    use crate::networks::http::response::HttpResponse;
+
+    // 构建一个测试用的响应
+    fn response(status: u16, body: &'static str) -> HttpResponse {
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        HttpResponse {
+            status: StatusCode::from_u16(status).unwrap(),
+            headers,
+            url: Url::parse("http://127.0.0.1:13001/example/index").unwrap(),
+            version: Version::HTTP_11,
+            elapsed: Duration::from_millis(5),
+            body: Bytes::from_static(body.as_bytes()),
+        }
+    }
+
+    /// 测试Json反序列化保留响应元数据
+    #[test]
+    fn test_json() {
+        let resp = response(200, r#"{"name":"张三"}"#).json::<HashMap<String, String>>().unwrap();
+        assert!(resp.is_success());
+        assert_eq!(resp.header("content-type"), Some("application/json"));
+        assert_eq!(resp.elapsed, Duration::from_millis(5));
+        assert_eq!(resp.body["name"], "张三");
+    }
+
+    /// 测试错误状态码与反序列化失败时可以获取原始响应体
+    #[test]
+    fn test_errors() {
+        match response(500, "<html>Internal Server Error</html>").error_for_status() {
+            Err(HttpError::Status { status, body, .. }) => {
+                assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+                assert_eq!(body, "<html>Internal Server Error</html>");
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+        let error = response(200, "not json").json::<HashMap<String, String>>().unwrap_err();
+        assert_eq!(error.status(), Some(StatusCode::OK));
+        assert_eq!(error.body().unwrap(), "not json");
+        assert!(response(404, "").error_for_status().unwrap_err().is_status());
+    }
+
+    /// 测试按照charset解码文本
+    #[test]
+    fn test_text_charset() {
+        let mut resp = response(200, "");
+        resp.body = Bytes::from_static(&[0xcf, 0xe3, 0xb8, 0xdb]);
+        resp.headers.insert(CONTENT_TYPE, HeaderValue::from_static("text/html; charset=GBK"));
+        assert_eq!(resp.text(), "香港");
+        resp.headers.remove(CONTENT_TYPE);
+        assert!(resp.text().starts_with(char::REPLACEMENT_CHARACTER));
+    }
+}
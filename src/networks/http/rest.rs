@@ -0,0 +1,148 @@
+//! # 声明式REST客户端
+//!
+//! [`rest_client`](crate::networks::http::rest_client)宏(需要开启`macros`特性)生成的客户端所使用的运行时支持.
+//!
+//! # Examples
+//! ```no_run
+//! # #[cfg(feature = "macros")] {
+//! use serde::{Deserialize, Serialize};
+//! use toys::networks::http::{rest_client, HttpResult};
+//!
+//! #[derive(Deserialize)]
+//! pub struct User { pub id: u64, pub name: String }
+//! #[derive(Serialize)]
+//! pub struct NewUser { pub name: String }
+//!
+//! #[rest_client]
+//! pub trait UserApi {
+//!     #[get("/users/{id}")]
+//!     async fn get_user(&self, id: u64) -> HttpResult<User>;
+//!
+//!     #[get("/users")]
+//!     async fn list_users(&self, #[query] page: u32, #[query("per_page")] size: Option<u32>) -> HttpResult<Vec<User>>;
+//!
+//!     #[post("/users")]
+//!     async fn create_user(&self, #[body] user: &NewUser, #[header("X-Request-Id")] request_id: &str) -> HttpResult<User>;
+//!
+//!     #[delete("/users/{id}")]
+//!     async fn delete_user(&self, id: u64) -> HttpResult<()>;
+//! }
+//!
+//! # async fn run() -> HttpResult<()> {
+//! let api = UserApiClient::new("https://api.example.com/v1");
+//! let user = api.get_user(42).await?;
+//! println!("{} {}", user.id, user.name);
+//! # Ok(())
+//! # }
+//! # }
+//! ```
+
+use crate::networks::http::{default_async_client, percent, AsyncHttpClient, AsyncRequestBuilder, Method};
+
+/// 生成的客户端共用的部分:HTTP客户端与服务地址
+#[derive(Debug, Clone)]
+pub struct RestClient {
+    client: AsyncHttpClient,
+    base_url: String,
+}
+
+impl RestClient {
+    /// 使用默认异步客户端创建
+    pub fn new(base_url: &str) -> Self {
+        RestClient::with_client(default_async_client().clone(), base_url)
+    }
+
+    /// 使用指定的HTTP客户端创建
+    pub fn with_client(client: AsyncHttpClient, base_url: &str) -> Self {
+        RestClient { client, base_url: base_url.trim_end_matches('/').to_string() }
+    }
+
+    /// 获取内部使用的HTTP客户端
+    pub fn client(&self) -> &AsyncHttpClient {
+        &self.client
+    }
+
+    /// 服务地址
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// 创建请求,地址为服务地址与`path`的拼接
+    pub fn request(&self, method: Method, path: &str) -> AsyncRequestBuilder {
+        self.client.request(method, &format!("{}{}", self.base_url, path))
+    }
+}
+
+/// 对路径参数进行百分号编码,除RFC 3986中的非保留字符外全部编码
+pub fn encode_path(segment: &str) -> String {
+    percent::encode(segment)
+}
+
+#[cfg(all(test, feature = "macros"))]
+mod tests {
+    use reqwest::Method;
+    use serde::{Deserialize, Serialize};
+    use serde_json::json;
+    use crate::networks::http::{rest_client, AsyncHttpClient, HttpResponse, HttpResult};
+    use crate::networks::http::mock::{MockResponse, MockServer};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    pub struct User {
+        id: u64,
+        name: String,
+    }
+
+    #[rest_client]
+    pub trait UserApi {
+        /// 获取用户
+        #[get("/users/{id}")]
+        async fn get_user(&self, id: &str) -> HttpResult<User>;
+
+        #[get("/users")]
+        async fn list_users(&self, #[query] page: u32, #[query("per_page")] size: Option<u32>) -> HttpResult<Vec<User>>;
+
+        #[post("/orgs/{org}/users")]
+        async fn create_user(&self, org: &str, #[body] user: &User, #[header("X-Request-Id")] request_id: u64) -> HttpResult<User>;
+
+        #[delete("/users/{id}")]
+        async fn delete_user(&self, id: u64) -> HttpResult<()>;
+
+        #[get("/health")]
+        async fn health(&self) -> HttpResult<String>;
+
+        #[head("/users/{id}")]
+        async fn exists(&self, id: u64) -> HttpResult<HttpResponse>;
+    }
+
+    /// 测试宏生成的客户端:路径参数编码、Query参数、请求头、请求体与各种返回值
+    #[tokio::test]
+    async fn test_rest_client() {
+        let server = MockServer::start();
+        server.mock(Method::GET, "/api/users/a%2Fb").respond(MockResponse::json(&json!({"id": 1, "name": "a/b"})));
+        server.mock(Method::GET, "/api/users").respond(MockResponse::json(&json!([{"id": 2, "name": "bob"}])));
+        server.mock(Method::POST, "/api/orgs/rust/users").respond_with(|request| MockResponse::new(201)
+            .header("Content-Type", "application/json")
+            .body(request.body.clone()));
+        server.mock(Method::DELETE, "/api/users/3").respond(MockResponse::new(204));
+        server.mock(Method::DELETE, "/api/users/4").respond(MockResponse::new(404));
+        server.mock(Method::GET, "/api/health").respond(MockResponse::text("ok"));
+        server.mock(Method::HEAD, "/api/users/5").respond(MockResponse::new(404));
+
+        let api = UserApiClient::with_client(AsyncHttpClient::new(), &server.url("/api/"));
+        assert_eq!(api.get_user("a/b").await.unwrap().name, "a/b");
+        assert_eq!(api.list_users(1, None).await.unwrap()[0].name, "bob");
+        api.list_users(2, Some(50)).await.unwrap();
+        let user = User { id: 9, name: "carol".to_string() };
+        assert_eq!(api.create_user("rust", &user, 7).await.unwrap(), user);
+        api.delete_user(3).await.unwrap();
+        assert!(api.delete_user(4).await.unwrap_err().is_status());
+        assert_eq!(api.health().await.unwrap(), "ok");
+        assert_eq!(api.exists(5).await.unwrap().status.as_u16(), 404);
+
+        let requests = server.requests();
+        assert_eq!(requests[1].query, vec![("page".to_string(), "1".to_string())]);
+        assert_eq!(requests[2].query_param("per_page"), Some("50"));
+        assert_eq!(requests[3].header("x-request-id"), Some("7"));
+        assert_eq!(requests[3].header("content-type"), Some("application/json"));
+    }
+}
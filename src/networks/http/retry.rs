@@ -0,0 +1,230 @@
+//! # 请求重试策略
+//!
+//! 支持指数退避与随机抖动、可重试状态码、`Retry-After`响应头,
+//! 默认只重试幂等的请求方法.
+
+use std::collections::HashSet;
+use std::time::{Duration, SystemTime};
+use rand::Rng;
+use reqwest::header::RETRY_AFTER;
+use reqwest::{Method, StatusCode};
+use crate::networks::http::error::HttpError;
+use crate::networks::http::response::HttpResponse;
+
+/// 重试策略
+///
+/// # Examples
+/// ```
+/// use std::time::Duration;
+/// use toys::networks::http::retry::RetryPolicy;
+/// let policy = RetryPolicy::new(3)
+///     .backoff(Duration::from_millis(100), Duration::from_secs(2))
+///     .retry_non_idempotent(true);
+/// assert_eq!(policy.max_attempts, 3);
+/// ```
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    // 最多尝试次数,包含第一次请求,为1时表示不重试
+    pub max_attempts: u32,
+    // 第一次重试前的等待时间
+    pub initial_backoff: Duration,
+    // 单次等待时间的上限
+    pub max_backoff: Duration,
+    // 每次重试等待时间的增长倍数
+    pub multiplier: f64,
+    // 是否为等待时间加上随机抖动,避免大量客户端同时重试
+    pub jitter: bool,
+    // 需要重试的响应状态码
+    pub retryable_statuses: HashSet<StatusCode>,
+    // 是否重试POST、PATCH等非幂等请求
+    pub retry_non_idempotent: bool,
+    // 是否按照响应头`Retry-After`指定的时间等待
+    pub respect_retry_after: bool,
+    // `Retry-After`允许的最长等待时间,超过时不再重试
+    pub max_retry_after: Duration,
+}
+
+impl Default for RetryPolicy {
+    /// 默认不重试
+    fn default() -> Self {
+        RetryPolicy::new(1)
+    }
+}
+
+impl RetryPolicy {
+    /// 创建一个最多尝试`max_attempts`次的重试策略
+    pub fn new(max_attempts: u32) -> Self {
+        RetryPolicy {
+            max_attempts: max_attempts.max(1),
+            initial_backoff: Duration::from_millis(200),
+            max_backoff: Duration::from_secs(10),
+            multiplier: 2.0,
+            jitter: true,
+            retryable_statuses: [429, 502, 503, 504].into_iter()
+                .map(|code| StatusCode::from_u16(code).unwrap())
+                .collect(),
+            retry_non_idempotent: false,
+            respect_retry_after: true,
+            max_retry_after: Duration::from_secs(60),
+        }
+    }
+
+    /// 不重试的策略
+    pub fn none() -> Self {
+        RetryPolicy::default()
+    }
+
+    /// 设置初始等待时间与等待时间上限
+    pub fn backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max;
+        self
+    }
+
+    /// 设置等待时间的增长倍数
+    pub fn multiplier(mut self, multiplier: f64) -> Self {
+        self.multiplier = multiplier;
+        self
+    }
+
+    /// 设置是否启用随机抖动
+    pub fn jitter(mut self, jitter: bool) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// 设置需要重试的状态码,覆盖默认的429/502/503/504
+    pub fn retry_on_statuses(mut self, statuses: &[StatusCode]) -> Self {
+        self.retryable_statuses = statuses.iter().copied().collect();
+        self
+    }
+
+    /// 设置是否重试非幂等请求
+    pub fn retry_non_idempotent(mut self, enable: bool) -> Self {
+        self.retry_non_idempotent = enable;
+        self
+    }
+
+    /// 设置是否遵循`Retry-After`响应头
+    pub fn respect_retry_after(mut self, enable: bool) -> Self {
+        self.respect_retry_after = enable;
+        self
+    }
+
+    /// 计算第`attempt`次请求失败后的等待时间,`attempt`从1开始
+    pub fn backoff_for(&self, attempt: u32) -> Duration {
+        let exp = self.multiplier.powi(attempt.saturating_sub(1) as i32);
+        let backoff = self.initial_backoff.mul_f64(exp).min(self.max_backoff);
+        if self.jitter {
+            // 在[backoff/2, backoff]之间随机取值
+            let half = backoff / 2;
+            half + half.mul_f64(rand::thread_rng().gen::<f64>())
+        } else {
+            backoff
+        }
+    }
+
+    /// 根据第`attempt`次请求的响应判断是否需要重试,需要时返回等待时间
+    pub fn retry_response<B>(&self, method: &Method, response: &HttpResponse<B>, attempt: u32) -> Option<Duration> {
+        if !self.can_retry(method, attempt) || !self.retryable_statuses.contains(&response.status) {
+            return None;
+        }
+        if self.respect_retry_after {
+            if let Some(delay) = response.header(RETRY_AFTER.as_str()).and_then(parse_retry_after) {
+                return if delay <= self.max_retry_after { Some(delay) } else { None };
+            }
+        }
+        Some(self.backoff_for(attempt))
+    }
+
+    /// 根据第`attempt`次请求的错误判断是否需要重试,只重试连接失败和超时
+    pub fn retry_error(&self, method: &Method, error: &HttpError, attempt: u32) -> Option<Duration> {
+        let retryable = match error {
+            HttpError::Request(e) => e.is_connect() || e.is_timeout(),
+            HttpError::Timeout(_) | HttpError::ReadTimeout(_) => true,
+            _ => false,
+        };
+        if retryable && self.can_retry(method, attempt) {
+            Some(self.backoff_for(attempt))
+        } else {
+            None
+        }
+    }
+
+    // 是否还有重试次数,并且请求方法允许重试
+    fn can_retry(&self, method: &Method, attempt: u32) -> bool {
+        attempt < self.max_attempts && (self.retry_non_idempotent || is_idempotent(method))
+    }
+}
+
+/// 请求方法是否幂等
+pub fn is_idempotent(method: &Method) -> bool {
+    matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS | Method::PUT | Method::DELETE | Method::TRACE)
+}
+
+/// 解析`Retry-After`响应头,支持秒数与HTTP日期两种格式
+pub fn parse_retry_after(value: &str) -> Option<Duration> {
+    if let Ok(seconds) = value.trim().parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = httpdate::parse_http_date(value.trim()).ok()?;
+    Some(date.duration_since(SystemTime::now()).unwrap_or(Duration::ZERO))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use reqwest::{Method, StatusCode};
+    use crate::networks::http::{AsyncHttpClient, HttpClient};
+    use crate::networks::http::mock::{MockResponse, MockServer};
+    use crate::networks::http::retry::{parse_retry_after, RetryPolicy};
+
+    /// 测试退避时间计算
+    #[test]
+    fn test_backoff() {
+        let policy = RetryPolicy::new(5)
+            .backoff(Duration::from_millis(100), Duration::from_millis(300))
+            .jitter(false);
+        assert_eq!(policy.backoff_for(1), Duration::from_millis(100));
+        assert_eq!(policy.backoff_for(2), Duration::from_millis(200));
+        assert_eq!(policy.backoff_for(3), Duration::from_millis(300));
+        let jittered = policy.jitter(true).backoff_for(2);
+        assert!(jittered >= Duration::from_millis(100) && jittered <= Duration::from_millis(200));
+        assert_eq!(parse_retry_after("3"), Some(Duration::from_secs(3)));
+        assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"), Some(Duration::ZERO));
+        assert_eq!(parse_retry_after("soon"), None);
+    }
+
+    /// 测试服务端恢复前的503响应会被重试
+    #[test]
+    fn test_retry_until_success() {
+        let server = MockServer::start();
+        server.mock(Method::GET, "/").times(1).respond(MockResponse::new(503));
+        server.mock(Method::GET, "/").times(1).respond(MockResponse::new(429).header("Retry-After", "0"));
+        server.mock(Method::GET, "/").respond(MockResponse::json(&serde_json::json!({"ok": "yes"})));
+        let client = HttpClient::builder()
+            .retry(RetryPolicy::new(3).backoff(Duration::from_millis(10), Duration::from_millis(50)))
+            .build()
+            .unwrap();
+        let response = client.get(&server.url("/")).send().unwrap();
+        assert_eq!(response.status, StatusCode::OK);
+        assert_eq!(server.received(Method::GET, "/"), 3);
+    }
+
+    /// 测试POST默认不重试,单个请求可以开启重试
+    #[tokio::test]
+    async fn test_non_idempotent() {
+        let server = MockServer::start();
+        server.mock(Method::POST, "/").respond(MockResponse::new(502));
+        let policy = RetryPolicy::new(2).backoff(Duration::from_millis(1), Duration::from_millis(1));
+        let client = AsyncHttpClient::builder().retry(policy.clone()).build_async().unwrap();
+        let response = client.post(&server.url("/")).send().await.unwrap();
+        assert_eq!(response.status, StatusCode::BAD_GATEWAY);
+        assert_eq!(server.received(Method::POST, "/"), 1);
+
+        client.request(Method::POST, &server.url("/"))
+            .retry(policy.retry_non_idempotent(true))
+            .send().await.unwrap();
+        assert_eq!(server.received(Method::POST, "/"), 3);
+    }
+}
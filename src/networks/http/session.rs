@@ -0,0 +1,628 @@
+//! # 会话与Cookie
+//!
+//! [`CookieJar`]按照RFC 6265的域名、路径与过期规则保存`Set-Cookie`并在请求时发送`Cookie`,
+//! 可以保存到Json文件后再次加载.[`HttpSession`]是带有Cookie Jar的同步客户端,
+//! 适合需要登录的场景.下载、事件流等流式请求同样会携带与保存Cookie.
+
+use std::future::Future;
+use std::net::IpAddr;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use chrono::{DateTime, Utc};
+use reqwest::header::{CONTENT_LENGTH, CONTENT_TYPE, COOKIE, HeaderMap, HeaderValue, LOCATION, SET_COOKIE};
+use reqwest::{Method, StatusCode, Url};
+use serde::{Deserialize, Serialize};
+use crate::data::json::{from_json_str, to_json_pretty};
+use crate::networks::http::client::{AsyncHttpClient, HttpClient, HttpClientBuilder};
+use crate::networks::http::error::{HttpError, HttpResult};
+use crate::networks::http::middleware::Middleware;
+use crate::networks::http::request::{Body, Request, RequestBuilder};
+use crate::networks::http::response::HttpResponse;
+
+/// 一个Cookie
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Cookie {
+    pub name: String,
+    pub value: String,
+    // 小写且不含前导点的域名
+    pub domain: String,
+    // 为true时只发送给与域名完全相同的主机,未设置Domain属性时为true
+    #[serde(default)]
+    pub host_only: bool,
+    pub path: String,
+    // 过期时间,为None时为会话Cookie
+    #[serde(default)]
+    pub expires: Option<DateTime<Utc>>,
+    // 只通过HTTPS发送
+    #[serde(default)]
+    pub secure: bool,
+    #[serde(default)]
+    pub http_only: bool,
+}
+
+impl Cookie {
+    /// 创建发送给`domain`及其子域名、路径为`/`的会话Cookie
+    pub fn new(name: &str, value: &str, domain: &str) -> Self {
+        Cookie {
+            name: name.to_string(),
+            value: value.to_string(),
+            domain: domain.trim_start_matches('.').to_ascii_lowercase(),
+            host_only: false,
+            path: "/".to_string(),
+            expires: None,
+            secure: false,
+            http_only: false,
+        }
+    }
+
+    /// 设置路径
+    pub fn path(mut self, path: &str) -> Self {
+        self.path = path.to_string();
+        self
+    }
+
+    /// 设置过期时间
+    pub fn expires(mut self, expires: DateTime<Utc>) -> Self {
+        self.expires = Some(expires);
+        self
+    }
+
+    /// 设置是否只通过HTTPS发送
+    pub fn secure(mut self, secure: bool) -> Self {
+        self.secure = secure;
+        self
+    }
+
+    /// 设置是否只发送给与域名完全相同的主机
+    pub fn host_only(mut self, host_only: bool) -> Self {
+        self.host_only = host_only;
+        self
+    }
+
+    /// 解析`url`的响应中的一个`Set-Cookie`值,格式不合法或域名不匹配时返回None
+    pub fn parse(url: &Url, set_cookie: &str) -> Option<Cookie> {
+        let host = url.host_str()?.to_ascii_lowercase();
+        let mut attributes = set_cookie.split(';');
+        let (name, value) = attributes.next()?.split_once('=')?;
+        let name = name.trim();
+        if name.is_empty() {
+            return None;
+        }
+        let mut cookie = Cookie::new(name, value.trim(), &host);
+        let mut domain = None;
+        let mut path = None;
+        let mut max_age = None;
+        for attribute in attributes {
+            let (key, value) = attribute.split_once('=').unwrap_or((attribute, ""));
+            let value = value.trim();
+            match key.trim().to_ascii_lowercase().as_str() {
+                "domain" if !value.trim_start_matches('.').is_empty() => {
+                    domain = Some(value.trim_start_matches('.').to_ascii_lowercase())
+                }
+                "path" if value.starts_with('/') => path = Some(value.to_string()),
+                "expires" => cookie.expires = parse_expires(value).or(cookie.expires),
+                "max-age" => max_age = value.parse::<i64>().ok().or(max_age),
+                "secure" => cookie.secure = true,
+                "httponly" => cookie.http_only = true,
+                _ => {}
+            }
+        }
+        // 不含点的域名(如`com`)视为公共后缀,与主机名相同时按仅主机处理,否则拒绝
+        match domain {
+            Some(domain) if !domain.contains('.') => {
+                if domain != host {
+                    return None;
+                }
+                cookie.host_only = true;
+            }
+            Some(domain) if domain_match(&host, &domain) => cookie.domain = domain,
+            Some(_) => return None,
+            None => cookie.host_only = true,
+        }
+        cookie.path = path.unwrap_or_else(|| default_path(url.path()));
+        // Max-Age优先于Expires,小于等于0时立即过期
+        if let Some(seconds) = max_age {
+            cookie.expires = Some(if seconds <= 0 {
+                DateTime::UNIX_EPOCH
+            } else {
+                chrono::Duration::try_seconds(seconds)
+                    .and_then(|duration| Utc::now().checked_add_signed(duration))
+                    .unwrap_or(DateTime::<Utc>::MAX_UTC)
+            });
+        }
+        Some(cookie)
+    }
+
+    /// 是否已过期
+    pub fn is_expired(&self) -> bool {
+        self.expires.is_some_and(|expires| expires <= Utc::now())
+    }
+
+    /// 请求`url`时是否应当发送该Cookie
+    pub fn matches(&self, url: &Url) -> bool {
+        let host = match url.host_str() {
+            Some(host) => host.to_ascii_lowercase(),
+            None => return false,
+        };
+        let domain_ok = if self.host_only { host == self.domain } else { domain_match(&host, &self.domain) };
+        domain_ok
+            && path_match(url.path(), &self.path)
+            && (!self.secure || matches!(url.scheme(), "https" | "wss"))
+            && !self.is_expired()
+    }
+
+    // 名称、域名与路径相同的Cookie会相互覆盖
+    fn same_key(&self, other: &Cookie) -> bool {
+        self.name == other.name && self.domain == other.domain && self.path == other.path
+    }
+}
+
+// 解析Expires属性,兼容日期中使用`-`分隔的写法
+fn parse_expires(value: &str) -> Option<DateTime<Utc>> {
+    httpdate::parse_http_date(value)
+        .or_else(|_| httpdate::parse_http_date(&value.replace('-', " ")))
+        .ok()
+        .map(DateTime::<Utc>::from)
+        .or_else(|| DateTime::parse_from_rfc2822(value).ok().map(|time| time.with_timezone(&Utc)))
+}
+
+// 主机名是否与域名相同或为其子域名,IP地址只能完全相同
+fn domain_match(host: &str, domain: &str) -> bool {
+    host == domain
+        || (host.ends_with(domain)
+            && host[..host.len() - domain.len()].ends_with('.')
+            && host.parse::<IpAddr>().is_err())
+}
+
+// 未设置Path属性时使用请求路径的目录部分
+fn default_path(path: &str) -> String {
+    match path.rfind('/') {
+        Some(0) | None => "/".to_string(),
+        Some(index) => path[..index].to_string(),
+    }
+}
+
+// 请求路径是否与Cookie路径相同或位于其下
+fn path_match(request_path: &str, cookie_path: &str) -> bool {
+    request_path == cookie_path
+        || (request_path.starts_with(cookie_path)
+            && (cookie_path.ends_with('/') || request_path[cookie_path.len()..].starts_with('/')))
+}
+
+/// Cookie Jar,克隆后共享同一组Cookie.
+/// 应当通过[`HttpClientBuilder::cookie_jar`]添加,此时由Cookie Jar在所有中间件之后跟随重定向,
+/// 以便保存重定向响应中的Cookie;跨域重定向时会移除认证请求头.
+/// 直接作为中间件添加时只添加与保存Cookie,重定向中间响应的Cookie不会被保存.
+///
+/// # Examples
+/// ```
+/// use reqwest::Url;
+/// use toys::networks::http::HttpClient;
+/// use toys::networks::http::session::{Cookie, CookieJar};
+/// let jar = CookieJar::new();
+/// jar.insert(Cookie::new("lang", "zh-CN", "example.com"));
+/// let client = HttpClient::builder().cookie_jar(jar.clone()).build().unwrap();
+/// let url = Url::parse("https://www.example.com/").unwrap();
+/// assert_eq!(jar.header_value(&url), Some("lang=zh-CN".to_string()));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CookieJar {
+    cookies: Arc<Mutex<Vec<Cookie>>>,
+}
+
+impl CookieJar {
+    pub fn new() -> Self {
+        CookieJar::default()
+    }
+
+    /// 从Json文件加载Cookie,已过期的Cookie会被丢弃
+    pub fn load<P: AsRef<Path>>(path: P) -> std::io::Result<Self> {
+        let json = std::fs::read_to_string(path)?;
+        let cookies: Vec<Cookie> = from_json_str(&json)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        let jar = CookieJar::new();
+        cookies.into_iter().for_each(|cookie| jar.insert(cookie));
+        Ok(jar)
+    }
+
+    /// 将未过期的Cookie(包括会话Cookie)保存到Json文件
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> {
+        let json = to_json_pretty(&self.cookies())?;
+        if let Some(dir) = path.as_ref().parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, json)
+    }
+
+    /// 添加一个Cookie,覆盖名称、域名与路径都相同的Cookie;已过期的Cookie只会删除旧值
+    pub fn insert(&self, cookie: Cookie) {
+        let mut cookies = self.cookies.lock().unwrap();
+        let expired = cookie.is_expired();
+        match cookies.iter().position(|old| old.same_key(&cookie)) {
+            Some(index) if expired => { cookies.remove(index); }
+            Some(index) => cookies[index] = cookie,
+            None if expired => {}
+            None => cookies.push(cookie),
+        }
+    }
+
+    /// 保存`url`的响应中的一个`Set-Cookie`值,返回是否被接受
+    pub fn set_cookie(&self, url: &Url, set_cookie: &str) -> bool {
+        match Cookie::parse(url, set_cookie) {
+            Some(cookie) => {
+                self.insert(cookie);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// 保存响应中的全部`Set-Cookie`
+    pub fn store_response(&self, response: &HttpResponse) {
+        self.store_headers(&response.url, &response.headers);
+    }
+
+    // 保存`url`的响应头中的全部`Set-Cookie`
+    fn store_headers(&self, url: &Url, headers: &HeaderMap) {
+        for value in headers.get_all(SET_COOKIE) {
+            if let Ok(value) = value.to_str() {
+                self.set_cookie(url, value);
+            }
+        }
+    }
+
+    /// 获取全部未过期的Cookie
+    pub fn cookies(&self) -> Vec<Cookie> {
+        self.cookies.lock().unwrap().iter().filter(|cookie| !cookie.is_expired()).cloned().collect()
+    }
+
+    /// 获取请求`url`时发送的Cookie,路径更长的排在前面
+    pub fn cookies_for(&self, url: &Url) -> Vec<Cookie> {
+        let mut cookies: Vec<Cookie> = self.cookies.lock().unwrap().iter()
+            .filter(|cookie| cookie.matches(url))
+            .cloned()
+            .collect();
+        cookies.sort_by_key(|cookie| std::cmp::Reverse(cookie.path.len()));
+        cookies
+    }
+
+    /// 获取请求`url`时发送的指定名称的Cookie值
+    pub fn get(&self, url: &Url, name: &str) -> Option<String> {
+        self.cookies_for(url).into_iter().find(|cookie| cookie.name == name).map(|cookie| cookie.value)
+    }
+
+    /// 生成请求`url`时的`Cookie`请求头,没有Cookie时返回None
+    pub fn header_value(&self, url: &Url) -> Option<String> {
+        let cookies = self.cookies_for(url);
+        if cookies.is_empty() {
+            return None;
+        }
+        Some(cookies.iter().map(|cookie| format!("{}={}", cookie.name, cookie.value)).collect::<Vec<_>>().join("; "))
+    }
+
+    /// 删除指定的Cookie,返回是否存在
+    pub fn remove(&self, domain: &str, path: &str, name: &str) -> bool {
+        let mut cookies = self.cookies.lock().unwrap();
+        let len = cookies.len();
+        cookies.retain(|cookie| !(cookie.domain == domain && cookie.path == path && cookie.name == name));
+        cookies.len() != len
+    }
+
+    /// 只保留满足条件的Cookie
+    pub fn retain<F>(&self, f: F) where F: FnMut(&Cookie) -> bool {
+        self.cookies.lock().unwrap().retain(f);
+    }
+
+    /// 删除全部Cookie
+    pub fn clear(&self) {
+        self.cookies.lock().unwrap().clear();
+    }
+
+    // 为请求添加Cookie,保留请求中已有的Cookie请求头
+    fn apply(&self, request: &mut Request) -> HttpResult<()> {
+        let Some(cookies) = self.header_value(&request.url) else {
+            return Ok(());
+        };
+        let value = match request.headers.get(COOKIE).and_then(|value| value.to_str().ok()) {
+            Some(existing) => format!("{}; {}", existing, cookies),
+            None => cookies,
+        };
+        let value = HeaderValue::from_str(&value).map_err(|_| HttpError::Builder(format!("invalid cookie header: {}", value)))?;
+        request.headers.insert(COOKIE, value);
+        Ok(())
+    }
+
+    // 发送请求并跟随重定向,每一跳都添加Cookie并保存响应中的Cookie
+    pub(crate) async fn send_following<R, F, Fut>(&self, mut request: Request, max_redirects: usize, send: F) -> HttpResult<R>
+        where R: RedirectResponse,
+              F: Fn(Request) -> Fut,
+              Fut: Future<Output = HttpResult<R>>
+    {
+        let mut redirects = 0;
+        loop {
+            let mut current = request.clone();
+            self.apply(&mut current)?;
+            let response = send(current).await?;
+            self.store_headers(response.url(), response.headers());
+            let location = match redirect_location(&response) {
+                Some(location) if max_redirects > 0 => location,
+                _ => return Ok(response),
+            };
+            if redirects == max_redirects {
+                return Err(HttpError::TooManyRedirects(location));
+            }
+            redirects += 1;
+            request = redirect_request(request, response.status(), location);
+        }
+    }
+}
+
+impl Middleware for CookieJar {
+    fn on_request(&self, request: &mut Request) -> HttpResult<()> {
+        self.apply(request)
+    }
+
+    fn on_response(&self, response: &mut HttpResponse) -> HttpResult<()> {
+        self.store_response(response);
+        Ok(())
+    }
+}
+
+// Cookie Jar跟随重定向时需要读取的响应信息,完整读取的响应与流式响应都实现了该trait
+pub(crate) trait RedirectResponse {
+    fn status(&self) -> StatusCode;
+    fn url(&self) -> &Url;
+    fn headers(&self) -> &HeaderMap;
+}
+
+impl RedirectResponse for HttpResponse {
+    fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    fn url(&self) -> &Url {
+        &self.url
+    }
+
+    fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+}
+
+impl RedirectResponse for reqwest::Response {
+    fn status(&self) -> StatusCode {
+        reqwest::Response::status(self)
+    }
+
+    fn url(&self) -> &Url {
+        reqwest::Response::url(self)
+    }
+
+    fn headers(&self) -> &HeaderMap {
+        reqwest::Response::headers(self)
+    }
+}
+
+// 获取重定向的目标地址
+fn redirect_location<R: RedirectResponse>(response: &R) -> Option<Url> {
+    if !matches!(response.status().as_u16(), 301 | 302 | 303 | 307 | 308) {
+        return None;
+    }
+    let location = response.headers().get(LOCATION)?.to_str().ok()?;
+    response.url().join(location).ok()
+}
+
+// 生成重定向后的请求: 303以及POST请求的301、302改为不带请求体的GET,跨域时移除凭证
+fn redirect_request(mut request: Request, status: StatusCode, location: Url) -> Request {
+    if status == StatusCode::SEE_OTHER
+        || (matches!(status, StatusCode::MOVED_PERMANENTLY | StatusCode::FOUND) && request.method == Method::POST) {
+        if request.method != Method::HEAD {
+            request.method = Method::GET;
+        }
+        request.body = Body::Empty;
+        request.headers.remove(CONTENT_TYPE);
+        request.headers.remove(CONTENT_LENGTH);
+    }
+    request.follow(location);
+    request
+}
+
+/// 带有Cookie Jar的同步客户端,克隆后共享同一组Cookie与连接池
+///
+/// # Examples
+/// ```no_run
+/// use toys::networks::http::HttpSession;
+/// let session = HttpSession::new();
+/// session.post("https://admin.example.com/login")
+///     .form(&[("username", "admin"), ("password", "secret")])
+///     .send()
+///     .unwrap();
+/// let dashboard = session.get("https://admin.example.com/dashboard").send_text().unwrap();
+/// session.cookies().save("cookies.json").unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct HttpSession {
+    client: HttpClient,
+    jar: CookieJar,
+}
+
+impl Default for HttpSession {
+    fn default() -> Self {
+        HttpSession::with_builder(HttpClient::builder()).expect("failed to build default http session")
+    }
+}
+
+impl HttpSession {
+    /// 使用默认的客户端配置与空的Cookie Jar创建会话
+    pub fn new() -> Self {
+        HttpSession::default()
+    }
+
+    /// 使用指定的客户端配置创建会话
+    pub fn with_builder(builder: HttpClientBuilder) -> HttpResult<Self> {
+        HttpSession::with_jar(builder, CookieJar::new())
+    }
+
+    /// 使用指定的客户端配置与Cookie Jar创建会话,可以传入从文件加载的Cookie Jar
+    pub fn with_jar(builder: HttpClientBuilder, jar: CookieJar) -> HttpResult<Self> {
+        let client = builder.cookie_jar(jar.clone()).build()?;
+        Ok(HttpSession { client, jar })
+    }
+
+    /// 获取会话的Cookie Jar
+    pub fn cookies(&self) -> &CookieJar {
+        &self.jar
+    }
+
+    /// 获取会话使用的同步客户端
+    pub fn client(&self) -> &HttpClient {
+        &self.client
+    }
+
+    /// 获取会话使用的异步客户端,与会话共享Cookie
+    pub fn as_async(&self) -> &AsyncHttpClient {
+        self.client.as_async()
+    }
+
+    /// 创建一个指定请求方法的请求构建器
+    pub fn request(&self, method: Method, url: &str) -> RequestBuilder {
+        self.client.request(method, url)
+    }
+
+    /// 创建GET请求构建器
+    pub fn get(&self, url: &str) -> RequestBuilder {
+        self.client.get(url)
+    }
+
+    /// 创建POST请求构建器
+    pub fn post(&self, url: &str) -> RequestBuilder {
+        self.client.post(url)
+    }
+
+    /// 创建PUT请求构建器
+    pub fn put(&self, url: &str) -> RequestBuilder {
+        self.client.put(url)
+    }
+
+    /// 创建DELETE请求构建器
+    pub fn delete(&self, url: &str) -> RequestBuilder {
+        self.client.delete(url)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+    use reqwest::{Method, Url};
+    use crate::networks::http::HttpClient;
+    use crate::networks::http::auth::BearerAuth;
+    use crate::networks::http::error::HttpError;
+    use crate::networks::http::mock::{MockResponse, MockServer};
+    use crate::networks::http::session::{Cookie, CookieJar, HttpSession};
+
+    /// 测试Set-Cookie解析与域名、路径、过期规则
+    #[test]
+    fn test_cookie_rules() {
+        let url = Url::parse("https://www.example.com/admin/login").unwrap();
+        let cookie = Cookie::parse(&url, "sid=abc; Path=/; Domain=.Example.com; Secure; HttpOnly").unwrap();
+        assert_eq!((cookie.domain.as_str(), cookie.host_only, cookie.secure, cookie.http_only), ("example.com", false, true, true));
+        assert!(cookie.matches(&Url::parse("https://api.example.com/").unwrap()));
+        assert!(!cookie.matches(&Url::parse("http://www.example.com/").unwrap()));
+        assert!(!cookie.matches(&Url::parse("https://badexample.com/").unwrap()));
+
+        let cookie = Cookie::parse(&url, "token=1").unwrap();
+        assert_eq!((cookie.domain.as_str(), cookie.host_only, cookie.path.as_str()), ("www.example.com", true, "/admin"));
+        assert!(cookie.matches(&Url::parse("https://www.example.com/admin/users").unwrap()));
+        assert!(!cookie.matches(&Url::parse("https://www.example.com/administrator").unwrap()));
+        assert!(!cookie.matches(&Url::parse("https://api.example.com/admin").unwrap()));
+
+        assert!(Cookie::parse(&url, "a=1; Domain=other.com").is_none());
+        assert!(Cookie::parse(&url, "a=1; Domain=com").is_none());
+        assert!(Cookie::parse(&url, "a=1; Domain=.com").is_none());
+        let cookie = Cookie::parse(&Url::parse("http://localhost/").unwrap(), "a=1; Domain=localhost").unwrap();
+        assert_eq!((cookie.domain.as_str(), cookie.host_only), ("localhost", true));
+        assert!(Cookie::parse(&url, "novalue").is_none());
+        let cookie = Cookie::parse(&url, "a=1; Expires=Wed, 21-Oct-2015 07:28:00 GMT").unwrap();
+        assert_eq!(cookie.expires, Some(Utc.with_ymd_and_hms(2015, 10, 21, 7, 28, 0).unwrap()));
+        assert!(cookie.is_expired());
+        let cookie = Cookie::parse(&url, "a=1; Expires=Wed, 21 Oct 2015 07:28:00 GMT; Max-Age=60").unwrap();
+        assert!(!cookie.is_expired());
+    }
+
+    /// 测试覆盖、删除以及保存与加载
+    #[test]
+    fn test_jar_persistence() {
+        let url = Url::parse("http://example.com/").unwrap();
+        let jar = CookieJar::new();
+        assert!(jar.set_cookie(&url, "a=1; Path=/"));
+        assert!(jar.set_cookie(&url, "b=2; Path=/; Max-Age=3600"));
+        assert!(jar.set_cookie(&url, "a=3; Path=/"));
+        jar.insert(Cookie::new("c", "4", "example.com").path("/docs"));
+        assert_eq!(jar.header_value(&url), Some("a=3; b=2".to_string()));
+        assert_eq!(jar.header_value(&Url::parse("http://example.com/docs/x").unwrap()), Some("c=4; a=3; b=2".to_string()));
+
+        let path = std::env::temp_dir().join("toys_cookie_jar_test.json");
+        jar.save(&path).unwrap();
+        let loaded = CookieJar::load(&path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(loaded.cookies(), jar.cookies());
+
+        // Max-Age=0删除Cookie
+        assert!(loaded.set_cookie(&url, "a=gone; Path=/; Max-Age=0"));
+        assert_eq!(loaded.get(&url, "a"), None);
+        assert!(loaded.remove("example.com", "/docs", "c"));
+        loaded.retain(|cookie| cookie.name != "b");
+        assert!(loaded.cookies().is_empty());
+    }
+
+    /// 测试会话保存重定向响应中的Cookie并在后续请求中发送
+    #[test]
+    fn test_session_login() {
+        let server = MockServer::start();
+        server.mock(Method::POST, "/login").respond(MockResponse::new(302)
+            .header("location", "/home")
+            .header("set-cookie", "sid=s3cr3t; Path=/; HttpOnly")
+            .header("set-cookie", "flash=welcome"));
+        server.mock(Method::GET, "/home").respond_with(|request| {
+            MockResponse::text(request.header("cookie").unwrap_or("").to_string())
+        });
+        server.mock(Method::GET, "/loop").respond(MockResponse::new(302).header("location", "/loop"));
+
+        let session = HttpSession::new();
+        let home = session.post(&server.url("/login")).body("user=admin").send_text().unwrap().body;
+        assert_eq!(home, "sid=s3cr3t; flash=welcome");
+        assert_eq!(server.requests()[1].method, Method::GET);
+        assert_eq!(session.cookies().cookies().len(), 2);
+        assert_eq!(session.get(&server.url("/home")).send_text().unwrap().body, "sid=s3cr3t; flash=welcome");
+
+        // 没有Cookie Jar的客户端不会携带Cookie
+        assert_eq!(HttpClient::new().get(&server.url("/home")).send_text().unwrap().body, "");
+        let session = HttpSession::with_builder(HttpClient::builder().max_redirects(3)).unwrap();
+        match session.get(&server.url("/loop")).send() {
+            Err(HttpError::TooManyRedirects(url)) => assert_eq!(url.path(), "/loop"),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert_eq!(server.received(Method::GET, "/loop"), 4);
+    }
+
+    /// 测试Cookie Jar总是位于中间件之后,跨域重定向时不会泄露认证请求头
+    #[test]
+    fn test_cross_origin_redirect() {
+        let other = MockServer::start();
+        other.mock(Method::GET, "/landing").respond_with(|request| {
+            MockResponse::text(request.header("authorization").unwrap_or("").to_string())
+        });
+        let server = MockServer::start();
+        server.mock(Method::GET, "/away").respond(MockResponse::new(302).header("location", &other.url("/landing")));
+        server.mock(Method::GET, "/local").respond(MockResponse::new(302).header("location", "/landing"));
+        server.mock(Method::GET, "/landing").respond_with(|request| {
+            MockResponse::text(request.header("authorization").unwrap_or("").to_string())
+        });
+
+        // 先添加Cookie Jar再添加认证中间件,重定向仍然不会重新执行认证中间件
+        let client = HttpClient::builder().cookie_jar(CookieJar::new()).auth(BearerAuth::new("t")).build().unwrap();
+        assert_eq!(client.get(&server.url("/away")).send_text().unwrap().body, "");
+        assert_eq!(other.received(Method::GET, "/landing"), 1);
+        assert_eq!(client.get(&server.url("/local")).send_text().unwrap().body, "Bearer t");
+    }
+}
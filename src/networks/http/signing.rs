@@ -0,0 +1,491 @@
+//! # 请求签名
+//!
+//! 提供通用的HMAC-SHA256请求头签名与AWS Signature V4签名中间件.
+//! 签名依赖最终的请求头,签名中间件应当最后添加.
+
+use std::fmt::{Debug, Formatter};
+use std::sync::Arc;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use reqwest::header::{HeaderName, HeaderValue, AUTHORIZATION, HOST};
+use reqwest::Url;
+use sha2::{Digest, Sha256};
+use crate::networks::http::error::{HttpError, HttpResult};
+use crate::networks::http::middleware::Middleware;
+use crate::networks::http::percent;
+use crate::networks::http::request::{Body, Request};
+
+type HmacSha256 = Hmac<Sha256>;
+
+// 计算HMAC-SHA256
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = HmacSha256::new_from_slice(key).expect("hmac accepts keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+// 请求体的SHA256摘要,流式请求体无法预先计算时返回None
+fn body_sha256(body: &Body) -> Option<String> {
+    match body {
+        Body::Empty => Some(hex::encode(Sha256::digest(b""))),
+        Body::Bytes(bytes) => Some(hex::encode(Sha256::digest(bytes))),
+        Body::Multipart(form) => form.to_bytes().map(|bytes| hex::encode(Sha256::digest(&bytes))),
+    }
+}
+
+fn header_value(value: &str) -> HttpResult<HeaderValue> {
+    HeaderValue::from_str(value).map_err(|_| HttpError::Builder(format!("invalid signature header value: {}", value)))
+}
+
+/// HMAC签名原文的组成部分,各部分之间以换行符连接
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CanonicalPart {
+    /// 请求方法
+    Method,
+    /// 请求路径
+    Path,
+    /// 按参数名排序后的Query参数
+    Query,
+    /// 请求头的值,不存在时为空字符串
+    Header(String),
+    /// 签名时间戳,Unix秒
+    Timestamp,
+    /// 请求体SHA256摘要的十六进制
+    BodySha256,
+    /// 固定文本
+    Literal(String),
+}
+
+// 自定义签名原文: 请求、时间戳
+type CanonicalFn = Arc<dyn Fn(&Request, i64) -> String + Send + Sync>;
+
+/// 通用HMAC-SHA256签名中间件,签名与时间戳写入请求头
+///
+/// # Examples
+/// ```
+/// use toys::networks::http::HttpClient;
+/// use toys::networks::http::signing::{CanonicalPart, HmacSigner};
+/// let signer = HmacSigner::new(b"secret")
+///     .parts(vec![CanonicalPart::Method, CanonicalPart::Path, CanonicalPart::Timestamp, CanonicalPart::BodySha256])
+///     .signature_header("X-Partner-Signature")
+///     .key_id("X-Partner-Key", "partner-1");
+/// let client = HttpClient::builder().middleware(signer).build().unwrap();
+/// ```
+#[derive(Clone)]
+pub struct HmacSigner {
+    secret: Vec<u8>,
+    parts: Vec<CanonicalPart>,
+    canonical: Option<CanonicalFn>,
+    signature_header: HeaderName,
+    timestamp_header: Option<HeaderName>,
+    key_id: Option<(HeaderName, HeaderValue)>,
+    // 签名使用Base64编码,默认为十六进制
+    base64: bool,
+    // 配置过程中产生的第一个错误,在签名时返回
+    error: Option<String>,
+}
+
+impl Debug for HmacSigner {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HmacSigner")
+            .field("parts", &self.parts)
+            .field("signature_header", &self.signature_header)
+            .field("timestamp_header", &self.timestamp_header)
+            .finish_non_exhaustive()
+    }
+}
+
+impl HmacSigner {
+    /// 默认签名原文为请求方法、路径、Query参数、时间戳与请求体摘要,
+    /// 签名写入`X-Signature`,时间戳写入`X-Timestamp`
+    pub fn new(secret: &[u8]) -> Self {
+        HmacSigner {
+            secret: secret.to_vec(),
+            parts: vec![CanonicalPart::Method, CanonicalPart::Path, CanonicalPart::Query,
+                        CanonicalPart::Timestamp, CanonicalPart::BodySha256],
+            canonical: None,
+            signature_header: HeaderName::from_static("x-signature"),
+            timestamp_header: Some(HeaderName::from_static("x-timestamp")),
+            key_id: None,
+            base64: false,
+            error: None,
+        }
+    }
+
+    /// 设置签名原文的组成部分
+    pub fn parts(mut self, parts: Vec<CanonicalPart>) -> Self {
+        self.parts = parts;
+        self
+    }
+
+    /// 使用闭包生成签名原文,优先于`parts`
+    pub fn canonical_with<F>(mut self, canonical: F) -> Self where F: Fn(&Request, i64) -> String + Send + Sync + 'static {
+        self.canonical = Some(Arc::new(canonical));
+        self
+    }
+
+    /// 设置签名请求头,名称不合法时错误会在签名时返回
+    pub fn signature_header(mut self, name: &str) -> Self {
+        match HeaderName::from_bytes(name.as_bytes()) {
+            Ok(name) => self.signature_header = name,
+            Err(_) => self.set_error(format!("invalid signature header name: {}", name)),
+        }
+        self
+    }
+
+    /// 设置时间戳请求头,为None时不发送时间戳,名称不合法时错误会在签名时返回
+    pub fn timestamp_header(mut self, name: Option<&str>) -> Self {
+        match name.map(|name| HeaderName::from_bytes(name.as_bytes()).map_err(|_| name)).transpose() {
+            Ok(name) => self.timestamp_header = name,
+            Err(name) => self.set_error(format!("invalid timestamp header name: {}", name)),
+        }
+        self
+    }
+
+    /// 通过请求头发送密钥ID,名称或值不合法时错误会在签名时返回
+    pub fn key_id(mut self, header: &str, id: &str) -> Self {
+        match (HeaderName::from_bytes(header.as_bytes()), HeaderValue::from_str(id)) {
+            (Ok(name), Ok(value)) => self.key_id = Some((name, value)),
+            _ => self.set_error(format!("invalid key id header: {}: {}", header, id)),
+        }
+        self
+    }
+
+    /// 设置签名是否使用Base64编码
+    pub fn base64(mut self, enable: bool) -> Self {
+        self.base64 = enable;
+        self
+    }
+
+    /// 生成签名原文
+    pub fn canonical_string(&self, request: &Request, timestamp: i64) -> String {
+        if let Some(canonical) = &self.canonical {
+            return canonical(request, timestamp);
+        }
+        self.parts.iter()
+            .map(|part| match part {
+                CanonicalPart::Method => request.method.to_string(),
+                CanonicalPart::Path => request.url.path().to_string(),
+                CanonicalPart::Query => sorted_query(&request.url, |value| value.to_string()),
+                CanonicalPart::Header(name) => request.headers.get(name.as_str())
+                    .and_then(|value| value.to_str().ok())
+                    .unwrap_or("")
+                    .trim()
+                    .to_string(),
+                CanonicalPart::Timestamp => timestamp.to_string(),
+                CanonicalPart::BodySha256 => body_sha256(&request.body).unwrap_or_default(),
+                CanonicalPart::Literal(text) => text.clone(),
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// 使用指定的时间戳签名请求
+    pub fn sign_at(&self, request: &mut Request, timestamp: i64) -> HttpResult<()> {
+        if let Some(error) = &self.error {
+            return Err(HttpError::Builder(error.clone()));
+        }
+        if let Some((name, value)) = &self.key_id {
+            request.headers.insert(name.clone(), value.clone());
+        }
+        if let Some(name) = &self.timestamp_header {
+            request.headers.insert(name.clone(), header_value(&timestamp.to_string())?);
+        }
+        let mac = hmac_sha256(&self.secret, self.canonical_string(request, timestamp).as_bytes());
+        let signature = if self.base64 { STANDARD.encode(mac) } else { hex::encode(mac) };
+        request.headers.insert(self.signature_header.clone(), header_value(&signature)?);
+        Ok(())
+    }
+
+    // 只保留第一个错误
+    fn set_error(&mut self, error: String) {
+        self.error.get_or_insert(error);
+    }
+}
+
+impl Middleware for HmacSigner {
+    fn on_request(&self, request: &mut Request) -> HttpResult<()> {
+        self.sign_at(request, Utc::now().timestamp())
+    }
+}
+
+/// AWS Signature Version 4签名中间件
+///
+/// # Examples
+/// ```
+/// use toys::networks::http::HttpClient;
+/// use toys::networks::http::signing::SigV4;
+/// let signer = SigV4::s3("AKIDEXAMPLE", "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY", "us-east-1");
+/// let client = HttpClient::builder().middleware(signer).build().unwrap();
+/// ```
+#[derive(Clone)]
+pub struct SigV4 {
+    access_key: String,
+    secret_key: String,
+    session_token: Option<String>,
+    region: String,
+    service: String,
+    // 是否发送x-amz-content-sha256请求头,S3要求发送
+    content_sha256: bool,
+    // 规范请求中是否对路径进行两次编码,除S3外的服务都要求两次编码
+    double_encode_path: bool,
+}
+
+impl Debug for SigV4 {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SigV4")
+            .field("access_key", &self.access_key)
+            .field("region", &self.region)
+            .field("service", &self.service)
+            .finish_non_exhaustive()
+    }
+}
+
+impl SigV4 {
+    pub fn new(access_key: &str, secret_key: &str, region: &str, service: &str) -> Self {
+        SigV4 {
+            access_key: access_key.to_string(),
+            secret_key: secret_key.to_string(),
+            session_token: None,
+            region: region.to_string(),
+            service: service.to_string(),
+            content_sha256: false,
+            double_encode_path: true,
+        }
+    }
+
+    /// S3及兼容S3的对象存储,路径只编码一次
+    pub fn s3(access_key: &str, secret_key: &str, region: &str) -> Self {
+        SigV4 { content_sha256: true, double_encode_path: false, ..SigV4::new(access_key, secret_key, region, "s3") }
+    }
+
+    /// 设置临时凭证的会话令牌
+    pub fn session_token(mut self, token: &str) -> Self {
+        self.session_token = Some(token.to_string());
+        self
+    }
+
+    /// 设置是否发送x-amz-content-sha256请求头
+    pub fn content_sha256(mut self, enable: bool) -> Self {
+        self.content_sha256 = enable;
+        self
+    }
+
+    /// 设置规范请求中是否对路径进行两次编码,默认开启,S3需要关闭
+    pub fn double_encode_path(mut self, enable: bool) -> Self {
+        self.double_encode_path = enable;
+        self
+    }
+
+    /// 使用指定的时间签名请求
+    pub fn sign_at(&self, request: &mut Request, time: DateTime<Utc>) -> HttpResult<()> {
+        let amz_date = time.format("%Y%m%dT%H%M%SZ").to_string();
+        let date = &amz_date[..8];
+        let host = match (request.url.host_str(), request.url.port()) {
+            (Some(host), Some(port)) => format!("{}:{}", host, port),
+            (Some(host), None) => host.to_string(),
+            _ => return Err(HttpError::InvalidUrl(request.url.to_string())),
+        };
+        request.headers.insert(HOST, header_value(&host)?);
+        request.headers.insert("x-amz-date", header_value(&amz_date)?);
+        if let Some(token) = &self.session_token {
+            request.headers.insert("x-amz-security-token", header_value(token)?);
+        }
+        let payload_hash = body_sha256(&request.body).unwrap_or_else(|| "UNSIGNED-PAYLOAD".to_string());
+        if self.content_sha256 {
+            request.headers.insert("x-amz-content-sha256", header_value(&payload_hash)?);
+        }
+
+        let (canonical_request, signed_headers) = self.canonical_request(request, &payload_hash);
+        let scope = format!("{}/{}/{}/aws4_request", date, self.region, self.service);
+        let string_to_sign = format!("AWS4-HMAC-SHA256\n{}\n{}\n{}",
+                                     amz_date, scope, hex::encode(Sha256::digest(canonical_request.as_bytes())));
+        let signature = hex::encode(hmac_sha256(&self.signing_key(date), string_to_sign.as_bytes()));
+        let authorization = format!("AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+                                    self.access_key, scope, signed_headers, signature);
+        let mut value = header_value(&authorization)?;
+        value.set_sensitive(true);
+        request.headers.insert(AUTHORIZATION, value);
+        Ok(())
+    }
+
+    /// 生成规范请求,返回规范请求与参与签名的请求头列表
+    pub fn canonical_request(&self, request: &Request, payload_hash: &str) -> (String, String) {
+        let path = request.url.path().split('/')
+            .map(|segment| {
+                let encoded = percent::encode(&percent::decode(segment));
+                if self.double_encode_path { percent::encode(&encoded) } else { encoded }
+            })
+            .collect::<Vec<_>>()
+            .join("/");
+        let query = sorted_query(&request.url, percent::encode);
+
+        // 请求头名称转为小写并排序,同名请求头的值以逗号连接
+        let mut headers: Vec<(String, String)> = Vec::new();
+        for name in request.headers.keys() {
+            let values: Vec<String> = request.headers.get_all(name).iter()
+                .map(|value| value.to_str().unwrap_or("").split_whitespace().collect::<Vec<_>>().join(" "))
+                .collect();
+            headers.push((name.as_str().to_string(), values.join(",")));
+        }
+        headers.sort();
+        let canonical_headers: String = headers.iter().map(|(name, value)| format!("{}:{}\n", name, value)).collect();
+        let signed_headers = headers.iter().map(|(name, _)| name.as_str()).collect::<Vec<_>>().join(";");
+
+        let canonical = format!("{}\n{}\n{}\n{}\n{}\n{}",
+                                request.method, path, query, canonical_headers, signed_headers, payload_hash);
+        (canonical, signed_headers)
+    }
+
+    // 派生签名密钥
+    fn signing_key(&self, date: &str) -> Vec<u8> {
+        let k_date = hmac_sha256(format!("AWS4{}", self.secret_key).as_bytes(), date.as_bytes());
+        let k_region = hmac_sha256(&k_date, self.region.as_bytes());
+        let k_service = hmac_sha256(&k_region, self.service.as_bytes());
+        hmac_sha256(&k_service, b"aws4_request")
+    }
+}
+
+impl Middleware for SigV4 {
+    fn on_request(&self, request: &mut Request) -> HttpResult<()> {
+        self.sign_at(request, Utc::now())
+    }
+}
+
+// 按编码后的参数名、参数值排序Query参数
+fn sorted_query<F>(url: &Url, encode: F) -> String where F: Fn(&str) -> String {
+    let mut pairs: Vec<(String, String)> = url.query_pairs()
+        .map(|(key, value)| (encode(&key), encode(&value)))
+        .collect();
+    pairs.sort();
+    pairs.iter().map(|(key, value)| format!("{}={}", key, value)).collect::<Vec<_>>().join("&")
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+    use reqwest::{Method, Url};
+    use crate::networks::http::{HttpClient, HttpError};
+    use crate::networks::http::mock::MockServer;
+    use crate::networks::http::request::{Body, Request};
+    use crate::networks::http::signing::{CanonicalPart, HmacSigner, SigV4};
+
+    // AWS SigV4测试套件使用的凭证
+    fn suite_signer() -> SigV4 {
+        SigV4::new("AKIDEXAMPLE", "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY", "us-east-1", "service")
+    }
+
+    fn sign(method: Method, url: &str, headers: &[(&'static str, &str)], body: &'static str) -> Request {
+        let mut request = Request::new(method, Url::parse(url).unwrap());
+        for (name, value) in headers {
+            request.headers.insert(*name, value.parse().unwrap());
+        }
+        if !body.is_empty() {
+            request.body = Body::Bytes(body.into());
+        }
+        suite_signer().sign_at(&mut request, Utc.with_ymd_and_hms(2015, 8, 30, 12, 36, 0).unwrap()).unwrap();
+        request
+    }
+
+    fn signature(request: &Request) -> String {
+        let authorization = request.headers["authorization"].to_str().unwrap();
+        authorization.rsplit("Signature=").next().unwrap().to_string()
+    }
+
+    /// 使用AWS SigV4测试套件(aws-sig-v4-test-suite)的向量验证签名
+    #[test]
+    fn test_sigv4_test_suite() {
+        let request = sign(Method::GET, "https://example.amazonaws.com/", &[], "");
+        assert_eq!(request.headers["authorization"], "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/service/aws4_request, \
+            SignedHeaders=host;x-amz-date, Signature=5fa00fa31553b73ebf1942676e86291e8372ff2a2260956d9b8aae1d763fbf31");
+        // get-vanilla-query-order-key-case
+        let request = sign(Method::GET, "https://example.amazonaws.com/?Param2=value2&Param1=value1", &[], "");
+        assert_eq!(signature(&request), "b97d918cfa904a5beff61c982a1b6f458b799221646efd99d3219ec94cdf2500");
+        // post-vanilla
+        let request = sign(Method::POST, "https://example.amazonaws.com/", &[], "");
+        assert_eq!(signature(&request), "5da7c1a2acd57cee7505fc6676e4e544621c30862966e37dddb68e92efbe5d6b");
+
+        // get-space与get-utf8: 套件中的原始路径未经编码,规范请求中只编码一次;
+        // 解析后的请求地址已经编码过一次,因此关闭两次编码时与套件的结果一致
+        let time = Utc.with_ymd_and_hms(2015, 8, 30, 12, 36, 0).unwrap();
+        for (path, double_encoded, expected) in [
+            ("/example space/", "/example%2520space/", "652487583200325589f1fba4c7e578f72c47cb61beeca81406b39ddec1366741"),
+            ("/ሴ", "/%25E1%2588%25B4", "8318018e0b0f223aa2bbf98705b62bb787dc9c0e678f255a891fd03141be5d85"),
+        ] {
+            let url = Url::parse(&format!("https://example.amazonaws.com{}", path)).unwrap();
+            let mut request = Request::new(Method::GET, url);
+            suite_signer().double_encode_path(false).sign_at(&mut request, time).unwrap();
+            assert_eq!(signature(&request), expected);
+            // 默认对已编码的路径再编码一次
+            let (canonical, _) = suite_signer().canonical_request(&request, "");
+            assert_eq!(canonical.lines().nth(1), Some(double_encoded));
+        }
+    }
+
+    /// 使用AWS文档中IAM ListUsers的示例验证规范请求与签名
+    #[test]
+    fn test_sigv4_iam_example() {
+        let signer = SigV4::new("AKIDEXAMPLE", "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY", "us-east-1", "iam");
+        assert_eq!(hex::encode(signer.signing_key("20150830")), "c4afb1cc5771d871763a393e44b703571b55cc28424d1a5e86da6ed3c154a4b9");
+        let mut request = Request::new(Method::GET, Url::parse("https://iam.amazonaws.com/?Action=ListUsers&Version=2010-05-08").unwrap());
+        request.headers.insert("content-type", "application/x-www-form-urlencoded; charset=utf-8".parse().unwrap());
+        signer.sign_at(&mut request, Utc.with_ymd_and_hms(2015, 8, 30, 12, 36, 0).unwrap()).unwrap();
+        assert_eq!(signature(&request), "5d672d79c15b13162d9279b0855cfba6789a8edb4c82c400e06b5924a6f2b5d7");
+    }
+
+    /// 测试HMAC签名原文与签名
+    #[test]
+    fn test_hmac_signer() {
+        let mut request = Request::new(Method::POST, Url::parse("https://api.example.com/orders?b=2&a=1").unwrap());
+        request.headers.insert("content-type", "application/json".parse().unwrap());
+        request.body = Body::Bytes("{}".into());
+        let signer = HmacSigner::new(b"secret")
+            .parts(vec![CanonicalPart::Method, CanonicalPart::Path, CanonicalPart::Query,
+                        CanonicalPart::Header("Content-Type".into()), CanonicalPart::Timestamp, CanonicalPart::BodySha256])
+            .key_id("X-Key-Id", "k1");
+        assert_eq!(signer.canonical_string(&request, 1700000000),
+                   "POST\n/orders\na=1&b=2\napplication/json\n1700000000\n44136fa355b3678a1146ad16f7e8649e94fb4fc21fe77e8310c060f61caaff8a");
+        signer.sign_at(&mut request, 1700000000).unwrap();
+        assert_eq!(request.headers["x-timestamp"], "1700000000");
+        assert_eq!(request.headers["x-key-id"], "k1");
+        assert_eq!(request.headers["x-signature"].len(), 64);
+
+        // RFC 4231 测试用例2
+        let signer = HmacSigner::new(b"Jefe").canonical_with(|_, _| "what do ya want for nothing?".into()).timestamp_header(None);
+        signer.sign_at(&mut request, 0).unwrap();
+        assert_eq!(request.headers["x-signature"], "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843");
+        let signer = signer.base64(true);
+        signer.sign_at(&mut request, 0).unwrap();
+        assert_eq!(request.headers["x-signature"], "W9zBRr9gdU5qBCQmCJV1x1oAPwidJzmDnexYuWTsOEM=");
+
+        // 非法的请求头配置在签名时返回错误
+        assert!(matches!(HmacSigner::new(b"k").signature_header("bad header").sign_at(&mut request, 0), Err(HttpError::Builder(_))));
+        assert!(matches!(HmacSigner::new(b"k").key_id("X-Key-Id", "bad\nid").sign_at(&mut request, 0), Err(HttpError::Builder(_))));
+    }
+
+    /// 测试作为中间件签名实际发送的请求
+    #[test]
+    fn test_signing_middleware() {
+        let server = MockServer::start();
+        let signer = HmacSigner::new(b"secret");
+        let client = HttpClient::builder().middleware(signer.clone()).build().unwrap();
+        client.post(&server.url("/orders?b=2&a=1")).body("{}").send().unwrap();
+        let client = HttpClient::builder().middleware(SigV4::s3("AKIDEXAMPLE", "secret", "us-east-1")).build().unwrap();
+        client.get(&server.url("/bucket/key")).send().unwrap();
+
+        let requests = server.requests();
+        // 服务端使用相同的密钥与时间戳重新计算签名
+        let timestamp: i64 = requests[0].header("x-timestamp").unwrap().parse().unwrap();
+        let mut expected = Request::new(Method::POST, Url::parse(&server.url("/orders?b=2&a=1")).unwrap());
+        expected.body = Body::Bytes(requests[0].body.clone());
+        signer.sign_at(&mut expected, timestamp).unwrap();
+        assert_eq!(requests[0].header("x-signature"), expected.headers["x-signature"].to_str().ok());
+
+        let authorization = requests[1].header("authorization").unwrap();
+        assert!(authorization.starts_with("AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/"));
+        assert!(authorization.contains("/us-east-1/s3/aws4_request, SignedHeaders=host;x-amz-content-sha256;x-amz-date, "));
+        assert_eq!(requests[1].header("x-amz-content-sha256"), Some("e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"));
+    }
+}
@@ -0,0 +1,453 @@
+//! # Server-Sent Events
+//!
+//! 按照HTML规范解析`text/event-stream`响应,以异步`Stream`返回事件.
+//! 连接断开后会等待重连间隔并携带`Last-Event-ID`自动重连,服务端返回204时停止.
+//! 每次连接前依次调用中间件的`prepare`(认证、签名、限流等),响应不经过中间件;
+//! 事件流不受总超时限制,读超时作用于两块数据之间的间隔.
+
+use std::time::Duration;
+use bytes::Bytes;
+use futures_util::stream::{self, BoxStream};
+use futures_util::StreamExt;
+use reqwest::header::{ACCEPT, CACHE_CONTROL, CONTENT_TYPE, HeaderValue};
+use reqwest::StatusCode;
+use serde::de::DeserializeOwned;
+use crate::networks::http::client::{spawn, with_read_timeout, AsyncHttpClient};
+use crate::networks::http::error::{HttpError, HttpResult};
+use crate::networks::http::request::{AsyncRequestBuilder, Request};
+
+/// 默认的重连间隔
+pub const DEFAULT_RETRY: Duration = Duration::from_secs(3);
+
+/// 一个事件,`T` 表示数据类型,默认为文本
+#[derive(Debug, Clone, PartialEq)]
+pub struct Event<T = String> {
+    // 事件ID,即收到该事件时的Last-Event-ID
+    pub id: Option<String>,
+    // 事件类型,默认为message
+    pub event: String,
+    // 事件数据,多行data以换行符连接
+    pub data: T,
+    // 事件中携带的重连间隔
+    pub retry: Option<Duration>,
+}
+
+impl Event {
+    /// 将Json数据反序列化为`T`,失败时返回的`HttpError::Decode`中保留了原始数据
+    pub fn json<T: DeserializeOwned>(self) -> HttpResult<Event<T>> {
+        let data = serde_json::from_str(&self.data).map_err(|source| HttpError::Decode {
+            source,
+            status: StatusCode::OK,
+            body: Bytes::from(self.data.clone()),
+        })?;
+        Ok(Event { id: self.id, event: self.event, data, retry: self.retry })
+    }
+}
+
+// 增量解析事件流
+#[derive(Debug, Default)]
+struct Parser {
+    // 尚未组成完整行的数据
+    buffer: Vec<u8>,
+    // 上一块数据以\r结尾,需要跳过下一块开头的\n
+    skip_lf: bool,
+    // 是否已经处理过流开头的BOM
+    started: bool,
+    event: String,
+    data: String,
+    retry: Option<Duration>,
+    // 服务端最后一次设置的重连间隔
+    reconnect_time: Option<Duration>,
+    last_event_id: Option<String>,
+}
+
+impl Parser {
+    // 解析一块数据,返回其中完整的事件
+    fn feed(&mut self, chunk: &[u8]) -> Vec<Event> {
+        let mut events = Vec::new();
+        let mut chunk = chunk;
+        if self.skip_lf && chunk.first() == Some(&b'\n') {
+            chunk = &chunk[1..];
+        }
+        self.skip_lf = false;
+        let mut start = 0;
+        let mut i = 0;
+        while i < chunk.len() {
+            if chunk[i] == b'\n' || chunk[i] == b'\r' {
+                self.buffer.extend_from_slice(&chunk[start..i]);
+                let line = std::mem::take(&mut self.buffer);
+                if let Some(event) = self.line(&line) {
+                    events.push(event);
+                }
+                if chunk[i] == b'\r' {
+                    if i + 1 == chunk.len() {
+                        self.skip_lf = true;
+                    } else if chunk[i + 1] == b'\n' {
+                        i += 1;
+                    }
+                }
+                start = i + 1;
+            }
+            i += 1;
+        }
+        self.buffer.extend_from_slice(&chunk[start..]);
+        events
+    }
+
+    // 处理一行
+    fn line(&mut self, line: &[u8]) -> Option<Event> {
+        let mut line = String::from_utf8_lossy(line).into_owned();
+        if !self.started {
+            self.started = true;
+            if let Some(stripped) = line.strip_prefix('\u{feff}') {
+                line = stripped.to_string();
+            }
+        }
+        if line.is_empty() {
+            return self.dispatch();
+        }
+        if line.starts_with(':') {
+            return None;
+        }
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line.as_str(), ""),
+        };
+        match field {
+            "event" => self.event = value.to_string(),
+            "data" => {
+                self.data.push_str(value);
+                self.data.push('\n');
+            }
+            "id" if !value.contains('\0') => self.last_event_id = Some(value.to_string()),
+            "retry" if !value.is_empty() && value.bytes().all(|b| b.is_ascii_digit()) => {
+                self.retry = value.parse().ok().map(Duration::from_millis);
+                self.reconnect_time = self.retry.or(self.reconnect_time);
+            }
+            _ => {}
+        }
+        None
+    }
+
+    // 空行表示一个事件结束,没有data时不产生事件
+    fn dispatch(&mut self) -> Option<Event> {
+        let event = std::mem::take(&mut self.event);
+        let retry = self.retry.take();
+        if self.data.is_empty() {
+            return None;
+        }
+        let mut data = std::mem::take(&mut self.data);
+        data.pop();
+        Some(Event {
+            id: self.last_event_id.clone(),
+            event: if event.is_empty() { "message".to_string() } else { event },
+            data,
+            retry,
+        })
+    }
+
+    // 连接断开时丢弃不完整的事件,保留Last-Event-ID
+    fn reset(&mut self) {
+        self.buffer.clear();
+        self.skip_lf = false;
+        self.started = false;
+        self.event.clear();
+        self.data.clear();
+        self.retry = None;
+    }
+}
+
+/// 事件流构建器,由请求构建器的`sse`方法创建
+///
+/// # Examples
+/// ```no_run
+/// use futures_util::StreamExt;
+/// use serde::Deserialize;
+/// use toys::networks::http::AsyncHttpClient;
+///
+/// #[derive(Deserialize)]
+/// struct Chunk { content: String }
+///
+/// # async fn run() -> toys::networks::http::HttpResult<()> {
+/// let mut events = AsyncHttpClient::new()
+///     .post("https://llm.example.com/v1/chat")
+///     .json(&serde_json::json!({"prompt": "你好", "stream": true}))
+///     .sse()
+///     .max_reconnects(3)
+///     .json::<Chunk>();
+/// while let Some(event) = events.next().await {
+///     print!("{}", event?.data.content);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct EventSource {
+    client: AsyncHttpClient,
+    request: HttpResult<Request>,
+    // 初始的重连间隔,可以被服务端的retry字段修改
+    retry: Duration,
+    // 连续重连失败的最大次数,为None时一直重连
+    max_reconnects: Option<usize>,
+    last_event_id: Option<String>,
+}
+
+impl EventSource {
+    pub(crate) fn new(request: AsyncRequestBuilder) -> Self {
+        let (client, request) = request.into_parts();
+        EventSource { client, request, retry: DEFAULT_RETRY, max_reconnects: None, last_event_id: None }
+    }
+
+    /// 设置初始的重连间隔
+    pub fn retry(mut self, retry: Duration) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// 设置连续重连失败的最大次数,超过后返回最后一次的错误并结束
+    pub fn max_reconnects(mut self, max: usize) -> Self {
+        self.max_reconnects = Some(max);
+        self
+    }
+
+    /// 连接断开后不再重连
+    pub fn no_reconnect(self) -> Self {
+        self.max_reconnects(0)
+    }
+
+    /// 设置第一次连接时发送的Last-Event-ID,用于从上次中断的位置继续
+    pub fn last_event_id(mut self, id: &str) -> Self {
+        self.last_event_id = Some(id.to_string());
+        self
+    }
+
+    /// 获取事件流.状态码不是200、Content-Type不是`text/event-stream`时返回错误并结束,不会重连
+    pub fn stream(self) -> BoxStream<'static, HttpResult<Event>> {
+        let state = State {
+            client: self.client,
+            request: Some(self.request),
+            retry: self.retry,
+            max_reconnects: self.max_reconnects,
+            failures: 0,
+            connected: false,
+            response: None,
+            parser: Parser { last_event_id: self.last_event_id, ..Parser::default() },
+            pending: Vec::new().into_iter(),
+            done: false,
+        };
+        stream::unfold(state, |mut state| async move {
+            let item = state.next().await?;
+            Some((item, state))
+        }).boxed()
+    }
+
+    /// 获取将数据反序列化为`T`的事件流
+    pub fn json<T: DeserializeOwned + Send + 'static>(self) -> BoxStream<'static, HttpResult<Event<T>>> {
+        self.stream().map(|event| event.and_then(Event::json)).boxed()
+    }
+
+    /// 在后台运行时上接收事件,返回一个阻塞的迭代器,供同步代码使用
+    pub fn iter(self) -> impl Iterator<Item = HttpResult<Event>> {
+        let mut events = self.stream();
+        let (tx, rx) = std::sync::mpsc::channel();
+        spawn(async move {
+            while let Some(event) = events.next().await {
+                if tx.send(event).is_err() {
+                    break;
+                }
+            }
+        });
+        rx.into_iter()
+    }
+}
+
+// 事件流的状态
+struct State {
+    client: AsyncHttpClient,
+    request: Option<HttpResult<Request>>,
+    retry: Duration,
+    max_reconnects: Option<usize>,
+    // 连续失败的次数
+    failures: usize,
+    // 是否已经连接过,重连前需要等待
+    connected: bool,
+    response: Option<reqwest::Response>,
+    parser: Parser,
+    pending: std::vec::IntoIter<Event>,
+    done: bool,
+}
+
+impl State {
+    async fn next(&mut self) -> Option<HttpResult<Event>> {
+        loop {
+            if let Some(event) = self.pending.next() {
+                return Some(Ok(event));
+            }
+            if self.done {
+                return None;
+            }
+            let read_timeout = self.client.config().read_timeout;
+            let result = match self.response.as_mut() {
+                Some(response) => match with_read_timeout(read_timeout, response.chunk()).await {
+                    Ok(Ok(Some(chunk))) => {
+                        self.pending = self.parser.feed(&chunk).into_iter();
+                        Ok(())
+                    }
+                    Ok(Ok(None)) => {
+                        self.disconnect();
+                        Ok(())
+                    }
+                    Ok(Err(e)) => Err(e.into()),
+                    Err(e) => Err(e),
+                },
+                None => self.connect().await,
+            };
+            if let Err(e) = result {
+                if let Some(e) = self.fail(e) {
+                    return Some(Err(e));
+                }
+            }
+        }
+    }
+
+    // 建立连接,不可恢复的错误会结束事件流
+    async fn connect(&mut self) -> HttpResult<()> {
+        let mut request = match self.request.as_ref() {
+            Some(Ok(request)) => request.clone(),
+            _ => {
+                self.done = true;
+                return Err(self.request.take().and_then(Result::err)
+                    .unwrap_or_else(|| HttpError::Builder("invalid event source request".to_string())));
+            }
+        };
+        if self.connected {
+            tokio::time::sleep(self.parser.reconnect_time.unwrap_or(self.retry)).await;
+        }
+        self.connected = true;
+        request.headers.insert(ACCEPT, HeaderValue::from_static("text/event-stream"));
+        request.headers.insert(CACHE_CONTROL, HeaderValue::from_static("no-cache"));
+        if let Some(id) = &self.parser.last_event_id {
+            if let Ok(value) = HeaderValue::from_str(id) {
+                request.headers.insert("last-event-id", value);
+            }
+        }
+        let response = self.client.send_streaming(request).await?;
+        let is_event_stream = response.headers().get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.trim_start().starts_with("text/event-stream"));
+        match response.status() {
+            StatusCode::NO_CONTENT => self.done = true,
+            StatusCode::OK if is_event_stream => {
+                self.failures = 0;
+                self.response = Some(response);
+            }
+            status => {
+                self.done = true;
+                let url = response.url().clone();
+                let body = response.bytes().await.unwrap_or_default();
+                return Err(HttpError::Status { status, url, body });
+            }
+        }
+        Ok(())
+    }
+
+    // 连接正常结束,准备重连
+    fn disconnect(&mut self) {
+        self.response = None;
+        self.parser.reset();
+        if self.max_reconnects == Some(0) {
+            self.done = true;
+        }
+    }
+
+    // 连接出错,未超过重连次数时继续重连,否则返回错误
+    fn fail(&mut self, error: HttpError) -> Option<HttpError> {
+        self.response = None;
+        self.parser.reset();
+        self.failures += 1;
+        if self.done || self.max_reconnects.is_some_and(|max| self.failures > max) {
+            self.done = true;
+            return Some(error);
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use futures_util::StreamExt;
+    use reqwest::Method;
+    use serde::Deserialize;
+    use crate::networks::http::{AsyncHttpClient, HttpClient, HttpError};
+    use crate::networks::http::mock::{MockResponse, MockServer};
+    use crate::networks::http::sse::{Event, Parser};
+
+    fn event(id: Option<&str>, event: &str, data: &str) -> Event {
+        Event { id: id.map(String::from), event: event.to_string(), data: data.to_string(), retry: None }
+    }
+
+    /// 测试按照规范解析事件,数据可以在任意位置分块
+    #[test]
+    fn test_parser() {
+        let stream = "\u{feff}: comment\r\ndata: first\r\ndata:  second\r\n\r\nid: 7\nevent: update\ndata\nretry: 1500\n\n\
+            event: empty\n\nretry: x\ndata: {\"n\":1}\r\rdata: incomplete";
+        for size in [1, 3, stream.len()] {
+            let mut parser = Parser::default();
+            let events: Vec<Event> = stream.as_bytes().chunks(size).flat_map(|chunk| parser.feed(chunk)).collect();
+            assert_eq!(events.len(), 3);
+            assert_eq!(events[0], event(None, "message", "first\n second"));
+            assert_eq!(events[1], Event { retry: Some(Duration::from_millis(1500)), ..event(Some("7"), "update", "") });
+            assert_eq!(events[2], event(Some("7"), "message", "{\"n\":1}"));
+        }
+    }
+
+    /// 测试断线后携带Last-Event-ID重连,服务端返回204时结束
+    #[tokio::test]
+    async fn test_reconnect() {
+        let server = MockServer::start();
+        server.mock(Method::GET, "/events").respond_with(|request| {
+            let body = match request.header("last-event-id") {
+                None => "retry: 10\nid: 1\ndata: a\n\nid: 2\ndata: b\n\ndata: lost",
+                Some("2") => "id: 3\nevent: done\ndata: c\n\n",
+                Some(_) => return MockResponse::new(204),
+            };
+            MockResponse::ok().header("content-type", "text/event-stream").body(body)
+        });
+        let events: Vec<Event> = AsyncHttpClient::new().get(&server.url("/events")).sse().stream()
+            .map(Result::unwrap)
+            .collect()
+            .await;
+        let data: Vec<&str> = events.iter().map(|event| event.data.as_str()).collect();
+        assert_eq!(data, ["a", "b", "c"]);
+        assert_eq!(events[2].event, "done");
+        assert_eq!(server.received(Method::GET, "/events"), 3);
+        assert_eq!(server.requests()[0].header("accept"), Some("text/event-stream"));
+    }
+
+    /// 测试Json事件、同步迭代以及错误状态码
+    #[test]
+    fn test_json_and_errors() {
+        #[derive(Debug, Deserialize)]
+        struct Tick {
+            n: u32,
+        }
+        let server = MockServer::start();
+        server.mock(Method::GET, "/ticks").respond(MockResponse::ok()
+            .header("content-type", "text/event-stream; charset=utf-8")
+            .body("data: {\"n\":1}\n\ndata: {\"n\":2}\n\ndata: oops\n\n"));
+        server.mock(Method::GET, "/missing").respond(MockResponse::new(404));
+
+        let client = HttpClient::new();
+        let events: Vec<_> = client.get(&server.url("/ticks")).sse().no_reconnect().iter()
+            .map(|event| event.unwrap().json::<Tick>())
+            .collect();
+        assert_eq!(events[0].as_ref().unwrap().data.n, 1);
+        assert_eq!(events[1].as_ref().unwrap().data.n, 2);
+        assert_eq!(events[2].as_ref().unwrap_err().body().unwrap(), "oops");
+
+        let mut errors = client.get(&server.url("/missing")).sse().iter();
+        assert!(matches!(errors.next(), Some(Err(HttpError::Status { status, .. })) if status == 404));
+        assert!(errors.next().is_none());
+    }
+}
@@ -1,7 +1,15 @@
 use std::net::UdpSocket;
 use std::string::ToString;
 use serde::Deserialize;
+#[cfg(feature = "http")]
 use crate::data::json::from_json_str;
+#[cfg(feature = "http")]
+use crate::networks::http::{AsyncHttpClient, HttpClient};
+
+// 查询本机公网IP信息的接口
+const IP_INFO_URL: &str = "http://ip-api.com/json/";
+// 查询IP所属地区的接口
+const IP_ADDRESS_URL: &str = "https://whois.pconline.com.cn/ipJson.jsp";
 
 // IP相关信息实体
 #[derive(Deserialize,Debug)]
@@ -59,7 +67,7 @@ impl IPAddress {
 /// 获取本机局域网IP
 ///
 /// # Examples
-/// ```
+/// ```no_run
 /// use toys::networks::ip::get_internal_ip;
 /// assert_eq!(get_internal_ip().unwrap(),"192.168.0.100".to_string())
 /// ```
@@ -76,7 +84,7 @@ pub fn get_internal_ip() -> Option<String>{
 /// 获取本机公网IP
 ///
 /// # Examples
-/// ```
+/// ```no_run
 /// use toys::networks::ip::get_public_ip;
 /// assert_eq!(get_public_ip().unwrap(),"168.138.213.6".to_string());
 /// ```
@@ -87,7 +95,7 @@ pub fn get_public_ip() -> Option<String>{
 /// 获取IP的经度纬度
 ///
 /// # Examples
-/// ```
+/// ```no_run
 /// use toys::networks::ip::get_ip_lat_lon;
 /// assert_eq!(get_ip_lat_lon().unwrap(),(35.798, 140.1803))
 /// ```
@@ -99,20 +107,24 @@ pub fn get_ip_lat_lon()-> Option<(f64,f64)>{
 
 /// 获取本机IP相关信息
 /// # Examples
-/// ```rust
+/// ```no_run
 /// use toys::networks::ip::get_ip_info;
 /// assert_eq!(get_ip_info().unwrap().query,"103.149.249.231".to_string());
 /// ```
 pub fn get_ip_info() -> Result<IPInfo,Box<dyn std::error::Error>>{
-    // 请求获取公网信息
-    let response = reqwest::blocking::get("http://ip-api.com/json/")?;
-    // 映射为IPInfo结构体
-    Ok(response.json::<IPInfo>()?)
+    backend::ip_info()
+}
+
+/// 使用指定的客户端获取本机IP相关信息,便于添加缓存、录制回放等中间件
+#[cfg(feature = "http")]
+pub fn get_ip_info_with(client: &HttpClient) -> Result<IPInfo,Box<dyn std::error::Error>>{
+    // 请求获取公网信息,映射为IPInfo结构体
+    Ok(client.get(IP_INFO_URL).send_json::<IPInfo>()?.body)
 }
 
 /// 获取本机IP相关信息(异步)
 /// # Examples
-/// ```
+/// ```no_run
 ///use toys::networks::ip::get_ip_info_async;
 ///#[tokio::test]
 ///async fn test_get_ip_info_async() -> Result<(),Box<dyn std::error::Error>>{
@@ -121,31 +133,42 @@ pub fn get_ip_info() -> Result<IPInfo,Box<dyn std::error::Error>>{
 /// }
 /// ```
 pub async fn get_ip_info_async() -> Result<IPInfo,Box<dyn std::error::Error>>{
-    Ok(reqwest::get("http://ip-api.com/json/").await?
-        .json::<IPInfo>().await?)
+    backend::ip_info_async().await
+}
+
+/// 使用指定的客户端获取本机IP相关信息(异步)
+#[cfg(feature = "http")]
+pub async fn get_ip_info_async_with(client: &AsyncHttpClient) -> Result<IPInfo,Box<dyn std::error::Error>>{
+    Ok(client.get(IP_INFO_URL).send_json::<IPInfo>().await?.body)
 }
 
 /// 获取IP地区相关信息
 ///
 /// # Examples
-/// ```
+/// ```no_run
 /// use toys::networks::ip::{get_ip_address_info, IPAddress};
 /// let address: IPAddress = get_ip_address_info("103.149.249.231").unwrap();
 /// assert_eq!(address.pro,"香港".to_string());
 /// ```
 pub fn get_ip_address_info(ip: &str) -> Result<IPAddress,Box<dyn std::error::Error>>{
-    // 拼接查询url,根据ip查询
-    let url = format!("https://whois.pconline.com.cn/ipJson.jsp?ip={}&json=true",ip);
-    // 获取响应
-    let response = reqwest::blocking::get(url)?;
+    backend::address_info(ip)
+}
+
+/// 使用指定的客户端获取IP地区相关信息
+#[cfg(feature = "http")]
+pub fn get_ip_address_info_with(client: &HttpClient, ip: &str) -> Result<IPAddress,Box<dyn std::error::Error>>{
+    // 根据ip查询,响应为GBK编码的Json
+    let response = client.get(IP_ADDRESS_URL)
+        .query(&[("ip", ip), ("json", "true")])
+        .send_text()?;
     // 将响应映射到实体
-    Ok(from_json_str(&(response.text()?))?)
+    Ok(from_json_str(&response.body)?)
 }
 
 /// 获取IP地区相关信息(异步)
 /// # Examples
 ///
-/// ```rust
+/// ```no_run
 /// use toys::networks::ip::get_ip_address_info_async;
 /// #[tokio::test]
 /// async fn test_get_ip_address_info_async() -> Result<(),Box<dyn std::error::Error>>{
@@ -154,9 +177,68 @@ pub fn get_ip_address_info(ip: &str) -> Result<IPAddress,Box<dyn std::error::Err
 /// }
 /// ```
 pub async fn get_ip_address_info_async(ip: &str)-> Result<IPAddress,Box<dyn std::error::Error>>{
-    let url =  format!("https://whois.pconline.com.cn/ipJson.jsp?ip={}&json=true",ip);
-    Ok(from_json_str(&reqwest::get(url).await?
-                                    .text().await?)?)
+    backend::address_info_async(ip).await
+}
+
+/// 使用指定的客户端获取IP地区相关信息(异步)
+#[cfg(feature = "http")]
+pub async fn get_ip_address_info_async_with(client: &AsyncHttpClient, ip: &str)-> Result<IPAddress,Box<dyn std::error::Error>>{
+    let response = client.get(IP_ADDRESS_URL)
+        .query(&[("ip", ip), ("json", "true")])
+        .send_text().await?;
+    Ok(from_json_str(&response.body)?)
+}
+
+// 开启`http`特性时通过默认客户端发送请求,可以使用其中间件与配置
+#[cfg(feature = "http")]
+mod backend {
+    use crate::networks::http::{default_async_client, default_client};
+    use crate::networks::ip::{IPAddress, IPInfo};
+
+    pub fn ip_info() -> Result<IPInfo,Box<dyn std::error::Error>>{
+        super::get_ip_info_with(default_client())
+    }
+
+    pub async fn ip_info_async() -> Result<IPInfo,Box<dyn std::error::Error>>{
+        super::get_ip_info_async_with(default_async_client()).await
+    }
+
+    pub fn address_info(ip: &str) -> Result<IPAddress,Box<dyn std::error::Error>>{
+        super::get_ip_address_info_with(default_client(), ip)
+    }
+
+    pub async fn address_info_async(ip: &str) -> Result<IPAddress,Box<dyn std::error::Error>>{
+        super::get_ip_address_info_async_with(default_async_client(), ip).await
+    }
+}
+
+// 未开启`http`特性时直接使用reqwest发送请求
+#[cfg(not(feature = "http"))]
+mod backend {
+    use crate::data::json::from_json_str;
+    use crate::networks::ip::{IP_ADDRESS_URL, IP_INFO_URL, IPAddress, IPInfo};
+
+    pub fn ip_info() -> Result<IPInfo,Box<dyn std::error::Error>>{
+        Ok(reqwest::blocking::get(IP_INFO_URL)?.json::<IPInfo>()?)
+    }
+
+    pub async fn ip_info_async() -> Result<IPInfo,Box<dyn std::error::Error>>{
+        Ok(reqwest::get(IP_INFO_URL).await?.json::<IPInfo>().await?)
+    }
+
+    pub fn address_info(ip: &str) -> Result<IPAddress,Box<dyn std::error::Error>>{
+        let response = reqwest::blocking::Client::new().get(IP_ADDRESS_URL)
+            .query(&[("ip", ip), ("json", "true")])
+            .send()?;
+        Ok(from_json_str(&response.text()?)?)
+    }
+
+    pub async fn address_info_async(ip: &str) -> Result<IPAddress,Box<dyn std::error::Error>>{
+        let response = reqwest::Client::new().get(IP_ADDRESS_URL)
+            .query(&[("ip", ip), ("json", "true")])
+            .send().await?;
+        Ok(from_json_str(&response.text().await?)?)
+    }
 }
 
 /// 请求天气信息响应体
@@ -220,30 +302,53 @@ pub async fn get_weather(lat: f64, lon: f64) -> Result<WeatherInfo,Box<dyn std::
 
 #[cfg(test)]
 mod tests{
-    use reqwest::Error;
-    use crate::networks::ip::{get_internal_ip, get_ip_address_info, get_ip_info_async, get_public_ip};
+    use std::net::IpAddr;
+    #[cfg(feature = "http")]
+    use crate::networks::http::{AsyncHttpClient, HttpClient};
+    #[cfg(feature = "http")]
+    use crate::networks::http::cassette::Cassette;
+    use crate::networks::ip::get_internal_ip;
+    #[cfg(feature = "http")]
+    use crate::networks::ip::{get_ip_address_info_with, get_ip_info_async_with, get_ip_info_with};
+
+    // 录制文件,设置环境变量TOYS_CASSETTE=record后重新录制
+    #[cfg(feature = "http")]
+    const CASSETTE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/cassettes/ip.json");
+
+    #[cfg(feature = "http")]
+    fn client() -> HttpClient {
+        HttpClient::builder().middleware(Cassette::from_env(CASSETTE).unwrap()).build().unwrap()
+    }
 
     #[test]
     pub fn test_get_internal_ip(){
-        let ip = get_internal_ip().unwrap();
-        assert_eq!(ip,"192.168.0.100".to_string());
+        // 没有网络时无法获取局域网IP
+        if let Some(ip) = get_internal_ip() {
+            assert!(ip.parse::<IpAddr>().is_ok());
+        }
     }
 
+    #[cfg(feature = "http")]
     #[test]
-    pub fn test_get_public_ip(){
-        // let ip = get_public_ip().unwrap();
-        // assert_eq!(ip,"45.62.169.92".to_string());
+    pub fn test_get_ip_info(){
+        let info = get_ip_info_with(&client()).unwrap();
+        assert_eq!(info.query,"103.149.249.231".to_string());
+        assert_eq!((info.lat,info.lon),(22.2842,114.1759));
     }
 
+    #[cfg(feature = "http")]
     #[test]
     pub fn test_get_ip_address_name(){
-        let address = get_ip_address_info("103.149.249.231").unwrap();
-        println!("{:?}",address);
+        let address = get_ip_address_info_with(&client(),"103.149.249.231").unwrap();
+        assert_eq!(address.pro,"香港");
+        assert_eq!(address.get_name(),"香港");
     }
 
+    #[cfg(feature = "http")]
     #[tokio::test]
     pub async fn test_get_ip_info_async()-> Result<(),std::io::Error>{
-        assert_eq!(get_ip_info_async().await.unwrap().query,"103.149.249.231".to_string());
+        let client = AsyncHttpClient::builder().middleware(Cassette::from_env(CASSETTE).unwrap()).build_async().unwrap();
+        assert_eq!(get_ip_info_async_with(&client).await.unwrap().query,"103.149.249.231".to_string());
         Ok(())
     }
-}
\ No newline at end of file
+}
@@ -3,4 +3,8 @@
 
 #[cfg(feature = "http")]
 pub mod http;
-pub mod ip;
\ No newline at end of file
+pub mod ip;
+#[cfg(feature = "websocket")]
+pub mod websocket;
+#[cfg(feature = "server")]
+pub mod server;
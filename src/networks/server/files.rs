@@ -0,0 +1,119 @@
+//! # 静态文件
+
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, UNIX_EPOCH};
+use reqwest::header::{IF_MODIFIED_SINCE, LAST_MODIFIED};
+use reqwest::StatusCode;
+use crate::networks::http::middleware::BoxFuture;
+use crate::networks::server::request::Request;
+use crate::networks::server::response::{HandlerResult, Response, ServerError};
+use crate::networks::server::router::Handler;
+
+/// 静态文件处理函数,文件路径取自`path`路径参数,通常通过[`Router::static_files`](crate::networks::server::Router::static_files)添加.
+/// 请求目录时返回目录下的`index.html`,支持`If-Modified-Since`,拒绝包含`..`的路径
+#[derive(Debug, Clone)]
+pub struct StaticFiles {
+    dir: Arc<PathBuf>,
+    index: Option<String>,
+}
+
+impl StaticFiles {
+    /// 创建目录`dir`的静态文件处理函数
+    pub fn new<P: Into<PathBuf>>(dir: P) -> Self {
+        StaticFiles { dir: Arc::new(dir.into()), index: Some("index.html".to_string()) }
+    }
+
+    /// 设置请求目录时返回的文件名,为None时请求目录返回404
+    pub fn index(mut self, index: Option<&str>) -> Self {
+        self.index = index.map(str::to_string);
+        self
+    }
+}
+
+impl Handler for StaticFiles {
+    fn call(&self, request: Request) -> BoxFuture<'static, HandlerResult> {
+        let files = self.clone();
+        Box::pin(async move { files.serve(request).await })
+    }
+}
+
+impl StaticFiles {
+    async fn serve(&self, request: Request) -> HandlerResult {
+        let relative = Path::new(request.param("path").unwrap_or(""));
+        if !relative.components().all(|component| matches!(component, Component::Normal(_))) {
+            return Err(ServerError::not_found("file not found"));
+        }
+        let mut path = self.dir.join(relative);
+        let mut metadata = tokio::fs::metadata(&path).await.map_err(|_| ServerError::not_found("file not found"))?;
+        if metadata.is_dir() {
+            let index = self.index.as_deref().ok_or_else(|| ServerError::not_found("file not found"))?;
+            path.push(index);
+            metadata = tokio::fs::metadata(&path).await.map_err(|_| ServerError::not_found("file not found"))?;
+        }
+        if !metadata.is_file() {
+            return Err(ServerError::not_found("file not found"));
+        }
+
+        // HTTP日期精确到秒
+        let modified = metadata.modified().ok()
+            .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+            .map(|since| UNIX_EPOCH + Duration::from_secs(since.as_secs()));
+        let since = request.header(IF_MODIFIED_SINCE.as_str()).and_then(|value| httpdate::parse_http_date(value).ok());
+        if let (Some(modified), Some(since)) = (modified, since) {
+            if modified <= since {
+                return Ok(Response::new(StatusCode::NOT_MODIFIED));
+            }
+        }
+
+        let content_type = mime_guess::from_path(&path).first_or_octet_stream();
+        let mut response = Response::ok()
+            .header("Content-Type", content_type.essence_str())
+            .body(tokio::fs::read(&path).await?);
+        if let Some(modified) = modified {
+            response = response.header(LAST_MODIFIED.as_str(), &httpdate::fmt_http_date(modified));
+        }
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::StatusCode;
+    use crate::networks::http::AsyncHttpClient;
+    use crate::networks::server::{Router, Server};
+    use crate::networks::server::files::StaticFiles;
+
+    /// 测试静态文件、目录首页、缓存协商与路径穿越
+    #[tokio::test]
+    async fn test_static_files() {
+        let dir = std::env::temp_dir().join(format!("toys-static-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("docs")).unwrap();
+        std::fs::write(dir.join("index.html"), "<h1>home</h1>").unwrap();
+        std::fs::write(dir.join("docs/app.js"), "console.log(1)").unwrap();
+        std::fs::write(std::env::temp_dir().join("toys-secret.txt"), "secret").unwrap();
+
+        let router = Router::new()
+            .static_files("/assets/", &dir)
+            .get("/raw/{*path}", StaticFiles::new(&dir).index(None));
+        let server = Server::new(router).bind("127.0.0.1:0").await.unwrap();
+        let client = AsyncHttpClient::new();
+
+        let response = client.get(&server.url("/assets/docs/app.js")).send().await.unwrap();
+        assert_eq!(response.header("content-type"), Some("text/javascript"));
+        assert_eq!(response.text(), "console.log(1)");
+        let last_modified = response.header("last-modified").unwrap().to_string();
+        let cached = client.get(&server.url("/assets/docs/app.js")).header("If-Modified-Since", &last_modified).send().await.unwrap();
+        assert_eq!(cached.status, StatusCode::NOT_MODIFIED);
+
+        assert_eq!(client.get(&server.url("/assets")).send().await.unwrap().text(), "<h1>home</h1>");
+        assert_eq!(client.get(&server.url("/raw/")).send().await.unwrap().status, StatusCode::NOT_FOUND);
+        assert_eq!(client.get(&server.url("/assets/missing.txt")).send().await.unwrap().status, StatusCode::NOT_FOUND);
+        let escaped = client.get(&server.url("/assets/..%2Ftoys-secret.txt")).send().await.unwrap();
+        assert_eq!(escaped.status, StatusCode::NOT_FOUND);
+
+        server.shutdown().await.unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        std::fs::remove_file(std::env::temp_dir().join("toys-secret.txt")).unwrap();
+    }
+}
@@ -0,0 +1,323 @@
+//! # 嵌入式HTTP服务
+//!
+//! 开启`server`特性后可用,适合为工具提供管理、健康检查等小型接口:
+//! 支持路径参数路由、基于[`data::json`](crate::data::json)的Json请求与响应、中间件、静态文件以及优雅停机,
+//! 并提供校验签名的[`webhook`]接收处理函数.
+//!
+//! # Examples
+//! ```no_run
+//! use serde::Deserialize;
+//! use toys::networks::server::{AccessLog, Request, Response, Router, Server};
+//!
+//! #[derive(Deserialize)]
+//! struct Echo { message: String }
+//!
+//! # async fn run() -> std::io::Result<()> {
+//! let router = Router::new()
+//!     .get("/health", |_| async { Ok(Response::text("ok")) })
+//!     .post("/echo", |request: Request| async move {
+//!         let echo: Echo = request.json()?;
+//!         Ok(Response::json(&serde_json::json!({"message": echo.message})))
+//!     })
+//!     .middleware(AccessLog::new());
+//! // 收到Ctrl+C后停止接收新连接,等待处理中的请求完成
+//! Server::new(router).run("0.0.0.0:8080", async { let _ = tokio::signal::ctrl_c().await; }).await
+//! # }
+//! ```
+
+use std::convert::Infallible;
+use std::future::Future;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use bytes::{Bytes, BytesMut};
+use hyper::body::HttpBody;
+use hyper::server::conn::AddrStream;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::Body;
+use reqwest::header::CONTENT_LENGTH;
+use reqwest::StatusCode;
+use tokio::sync::oneshot;
+use crate::networks::http::client::block_on;
+
+pub mod files;
+pub mod request;
+pub mod response;
+pub mod router;
+pub mod webhook;
+
+pub use files::StaticFiles;
+pub use request::Request;
+pub use response::{HandlerResult, Response, ServerError};
+pub use router::{AccessLog, Handler, Middleware, Next, Router};
+
+/// 默认允许的最大请求体大小
+pub const DEFAULT_MAX_BODY_SIZE: usize = 2 * 1024 * 1024;
+
+/// HTTP服务
+pub struct Server {
+    router: Arc<Router>,
+    max_body_size: usize,
+}
+
+impl Server {
+    /// 使用路由表创建服务
+    pub fn new(router: Router) -> Self {
+        Server { router: Arc::new(router), max_body_size: DEFAULT_MAX_BODY_SIZE }
+    }
+
+    /// 设置允许的最大请求体大小,超过时返回413
+    pub fn max_body_size(mut self, bytes: usize) -> Self {
+        self.max_body_size = bytes;
+        self
+    }
+
+    /// 在当前的tokio运行时中监听`addr`(端口为0时随机分配)并在后台处理请求
+    pub async fn bind(self, addr: &str) -> io::Result<ServerHandle> {
+        let (addr, shutdown, serving) = self.serve(addr)?;
+        let (done_tx, done_rx) = oneshot::channel();
+        tokio::spawn(async move {
+            let _ = done_tx.send(serving.await);
+        });
+        Ok(ServerHandle { addr, shutdown, done: done_rx })
+    }
+
+    /// 在独立的线程与运行时中启动服务,供同步代码使用.
+    /// 服务不占用HTTP客户端的共享运行时,处理函数中可以放心使用同步客户端
+    pub fn start(self, addr: &str) -> io::Result<ServerHandle> {
+        let addr = addr.to_string();
+        let runtime = tokio::runtime::Builder::new_multi_thread().enable_all().thread_name("toys-server").build()?;
+        let (ready_tx, ready_rx) = std::sync::mpsc::channel();
+        std::thread::Builder::new().name("toys-server".to_string()).spawn(move || {
+            runtime.block_on(async move {
+                let (addr, shutdown, serving) = match self.serve(&addr) {
+                    Ok(parts) => parts,
+                    Err(e) => {
+                        let _ = ready_tx.send(Err(e));
+                        return;
+                    }
+                };
+                let (done_tx, done_rx) = oneshot::channel();
+                let _ = ready_tx.send(Ok(ServerHandle { addr, shutdown, done: done_rx }));
+                let _ = done_tx.send(serving.await);
+            });
+        })?;
+        ready_rx.recv().map_err(|_| io::Error::other("server thread exited before binding"))?
+    }
+
+    // 监听`addr`,返回实际监听的地址、停机信号的发送端以及运行服务直到停机的Future.需要在tokio运行时中调用
+    fn serve(self, addr: &str) -> io::Result<(SocketAddr, oneshot::Sender<()>, impl Future<Output = io::Result<()>>)> {
+        let addr: SocketAddr = addr.parse().map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, format!("invalid address {}: {}", addr, e)))?;
+        let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+        let server = hyper::Server::try_bind(&addr).map_err(io::Error::other)?;
+        let (router, max_body_size) = (self.router, self.max_body_size);
+        let make_service = make_service_fn(move |connection: &AddrStream| {
+            let (router, remote_addr) = (router.clone(), connection.remote_addr());
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request| handle(router.clone(), remote_addr, max_body_size, request)))
+            }
+        });
+        let server = server.serve(make_service);
+        let local_addr = server.local_addr();
+        let serving = async move {
+            // 句柄被丢弃时发送端关闭,此时服务继续运行
+            let signal = async {
+                if shutdown_rx.await.is_err() {
+                    std::future::pending::<()>().await;
+                }
+            };
+            server.with_graceful_shutdown(signal).await.map_err(io::Error::other)
+        };
+        Ok((local_addr, shutdown_tx, serving))
+    }
+
+    /// 运行服务直到`signal`完成,之后停止接收新连接并等待处理中的请求完成
+    pub async fn run<F>(self, addr: &str, signal: F) -> io::Result<()> where F: Future<Output = ()> {
+        let handle = self.bind(addr).await?;
+        signal.await;
+        handle.shutdown().await
+    }
+}
+
+/// 运行中的服务,丢弃句柄不会停止服务
+pub struct ServerHandle {
+    addr: SocketAddr,
+    shutdown: oneshot::Sender<()>,
+    done: oneshot::Receiver<io::Result<()>>,
+}
+
+impl ServerHandle {
+    /// 服务监听的地址
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// 拼接完整的请求地址
+    pub fn url(&self, path: &str) -> String {
+        format!("http://{}{}", self.addr, path)
+    }
+
+    /// 优雅停机:停止接收新连接,等待处理中的请求完成
+    pub async fn shutdown(self) -> io::Result<()> {
+        let _ = self.shutdown.send(());
+        self.done.await.unwrap_or(Ok(()))
+    }
+
+    /// 同步优雅停机
+    pub fn shutdown_blocking(self) -> io::Result<()> {
+        block_on(self.shutdown())
+    }
+}
+
+// 转换请求并交给中间件与路由处理
+async fn handle(router: Arc<Router>, remote_addr: SocketAddr, max_body_size: usize, request: hyper::Request<Body>)
+    -> Result<hyper::Response<Body>, Infallible>
+{
+    let (parts, body) = request.into_parts();
+    let response = match read_body(body, &parts.headers, max_body_size).await {
+        Ok(body) => {
+            let request = Request::new(parts.method, parts.uri.path(), parts.uri.query().unwrap_or(""), parts.headers, body, remote_addr);
+            router::Next::new(&router).run(request).await.or_internal_error()
+        }
+        Err(error) => error.into(),
+    };
+    let mut builder = hyper::Response::builder().status(response.status);
+    if let Some(headers) = builder.headers_mut() {
+        headers.extend(response.headers);
+    }
+    Ok(builder.body(Body::from(response.body)).unwrap_or_else(|_| {
+        let mut response = hyper::Response::new(Body::empty());
+        *response.status_mut() = StatusCode::INTERNAL_SERVER_ERROR;
+        response
+    }))
+}
+
+// 读取请求体,超过大小限制时返回413
+async fn read_body(mut body: Body, headers: &reqwest::header::HeaderMap, max_body_size: usize) -> Result<Bytes, ServerError> {
+    let too_large = || ServerError::new(StatusCode::PAYLOAD_TOO_LARGE, "request body too large");
+    let declared = headers.get(CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<usize>().ok());
+    if declared.is_some_and(|length| length > max_body_size) {
+        return Err(too_large());
+    }
+    let mut bytes = BytesMut::new();
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk.map_err(|e| ServerError::bad_request(format!("failed to read request body: {}", e)))?;
+        if bytes.len() + chunk.len() > max_body_size {
+            return Err(too_large());
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    Ok(bytes.freeze())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    use reqwest::StatusCode;
+    use serde::{Deserialize, Serialize};
+    use crate::networks::http::middleware::BoxFuture;
+    use crate::networks::http::{AsyncHttpClient, HttpClient};
+    use crate::networks::server::{AccessLog, Middleware, Next, Request, Response, Router, Server, ServerError};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct User {
+        id: u64,
+        name: String,
+    }
+
+    // 校验请求头中的Token
+    struct TokenAuth;
+
+    impl Middleware for TokenAuth {
+        fn handle<'a>(&'a self, request: Request, next: Next<'a>) -> BoxFuture<'a, Response> {
+            Box::pin(async move {
+                if request.path.starts_with("/admin") && request.header("x-token") != Some("secret") {
+                    return ServerError::unauthorized("invalid token").into();
+                }
+                next.run(request).await.header("X-Served-By", "toys")
+            })
+        }
+    }
+
+    fn router(log: Arc<Mutex<Vec<String>>>) -> Router {
+        Router::new()
+            .get("/users/{id}", |request: Request| async move {
+                let id: u64 = request.param_as("id")?;
+                let verbose = request.query_param("verbose") == Some("true");
+                Ok(Response::json(&User { id, name: if verbose { "user (verbose)" } else { "user" }.to_string() }))
+            })
+            .post("/users", |request: Request| async move {
+                let user: User = request.json()?;
+                Ok(Response::json(&user).status(StatusCode::CREATED))
+            })
+            .get("/admin/stats", |_| async { Ok(Response::text("stats")) })
+            .get("/thread", |_| async { Ok(Response::text(std::thread::current().name().unwrap_or(""))) })
+            .get("/bad-header", |_| async { Ok(Response::text("x").header("X-Bad", "a\nb")) })
+            .get("/slow", |_| async {
+                tokio::time::sleep(Duration::from_millis(300)).await;
+                Ok(Response::text("done"))
+            })
+            .middleware(AccessLog::with_logger(move |line| log.lock().unwrap().push(line.to_string())))
+            .middleware(TokenAuth)
+    }
+
+    /// 测试路由、Json请求与响应、错误响应、中间件以及请求体大小限制
+    #[test]
+    fn test_routes_and_middleware() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let server = Server::new(router(log.clone())).max_body_size(64).start("127.0.0.1:0").unwrap();
+        let client = HttpClient::new();
+
+        let user: User = client.get(&server.url("/users/7?verbose=true")).send_json().unwrap().body;
+        assert_eq!(user, User { id: 7, name: "user (verbose)".to_string() });
+        let created = client.post(&server.url("/users")).json(&User { id: 1, name: "a".to_string() }).send().unwrap();
+        assert_eq!((created.status, created.header("x-served-by")), (StatusCode::CREATED, Some("toys")));
+
+        let invalid = client.post(&server.url("/users")).body("{").send().unwrap();
+        assert_eq!(invalid.status, StatusCode::BAD_REQUEST);
+        assert!(invalid.text().starts_with(r#"{"error":"invalid json body"#));
+        assert_eq!(client.get(&server.url("/users/abc")).send().unwrap().status, StatusCode::BAD_REQUEST);
+        assert_eq!(client.get(&server.url("/missing")).send().unwrap().status, StatusCode::NOT_FOUND);
+        let not_allowed = client.delete(&server.url("/users/7")).send().unwrap();
+        assert_eq!((not_allowed.status, not_allowed.header("allow")), (StatusCode::METHOD_NOT_ALLOWED, Some("GET")));
+        assert_eq!(client.head(&server.url("/users/7")).send().unwrap().status, StatusCode::OK);
+        let large = client.post(&server.url("/users")).body(vec![b'x'; 65]).send().unwrap();
+        assert_eq!(large.status, StatusCode::PAYLOAD_TOO_LARGE);
+
+        assert_eq!(client.get(&server.url("/admin/stats")).send().unwrap().status, StatusCode::UNAUTHORIZED);
+        let stats = client.get(&server.url("/admin/stats")).header("X-Token", "secret").send_text().unwrap();
+        assert_eq!(stats.body, "stats");
+        let log = log.lock().unwrap();
+        assert!(log[0].contains("GET /users/7 200"));
+        assert!(log.iter().any(|line| line.contains("GET /admin/stats 401")));
+        drop(log);
+
+        // 同步启动的服务运行在独立的运行时中
+        assert_eq!(client.get(&server.url("/thread")).send_text().unwrap().body, "toys-server");
+        assert!(Server::new(Router::new()).start("not an address").is_err());
+        let bad = client.get(&server.url("/bad-header")).send().unwrap();
+        assert_eq!((bad.status, bad.text()), (StatusCode::INTERNAL_SERVER_ERROR, r#"{"error":"invalid response header: X-Bad"}"#.to_string()));
+        assert_eq!(Response::ok().header("bad name", "v").header("X-Bad", "\n").error(), Some("invalid response header: bad name"));
+        server.shutdown_blocking().unwrap();
+    }
+
+    /// 测试优雅停机时等待处理中的请求完成并拒绝新连接
+    #[tokio::test]
+    async fn test_graceful_shutdown() {
+        let server = Server::new(router(Arc::new(Mutex::new(Vec::new())))).bind("127.0.0.1:0").await.unwrap();
+        let url = server.url("/slow");
+        let client = AsyncHttpClient::new();
+        let pending = tokio::spawn({
+            let (client, url) = (client.clone(), url.clone());
+            async move { client.get(&url).send_text().await.unwrap().body }
+        });
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        server.shutdown().await.unwrap();
+        assert_eq!(pending.await.unwrap(), "done");
+        assert!(client.get(&url).send().await.is_err());
+        assert!(Server::new(Router::new()).bind("not an address").await.is_err());
+    }
+}
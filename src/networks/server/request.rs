@@ -0,0 +1,89 @@
+//! # 服务端收到的请求
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::str::FromStr;
+use bytes::Bytes;
+use reqwest::header::HeaderMap;
+use reqwest::Method;
+use serde::de::DeserializeOwned;
+use crate::data::json::from_json_bytes;
+use crate::networks::server::response::ServerError;
+
+/// 服务端收到的请求
+#[derive(Debug, Clone)]
+pub struct Request {
+    // 请求方法
+    pub method: Method,
+    // 请求路径,不包含Query参数
+    pub path: String,
+    // 解码后的Query参数
+    pub query: Vec<(String, String)>,
+    // 请求头
+    pub headers: HeaderMap,
+    // 请求体
+    pub body: Bytes,
+    // 客户端地址
+    pub remote_addr: SocketAddr,
+    // 原始的Query字符串
+    raw_query: String,
+    // 路由匹配得到的路径参数
+    params: HashMap<String, String>,
+}
+
+impl Request {
+    pub(crate) fn new(method: Method, path: &str, raw_query: &str, headers: HeaderMap, body: Bytes, remote_addr: SocketAddr) -> Self {
+        Request {
+            method,
+            path: path.to_string(),
+            query: serde_urlencoded::from_str(raw_query).unwrap_or_default(),
+            headers,
+            body,
+            remote_addr,
+            raw_query: raw_query.to_string(),
+            params: HashMap::new(),
+        }
+    }
+
+    pub(crate) fn set_params(&mut self, params: HashMap<String, String>) {
+        self.params = params;
+    }
+
+    /// 获取一个路径参数
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params.get(name).map(String::as_str)
+    }
+
+    /// 将路径参数解析为`T`,不存在或格式不符时返回400错误
+    pub fn param_as<T: FromStr>(&self, name: &str) -> Result<T, ServerError> {
+        self.param(name)
+            .and_then(|value| value.parse().ok())
+            .ok_or_else(|| ServerError::bad_request(format!("invalid path parameter: {}", name)))
+    }
+
+    /// 获取一个Query参数的值
+    pub fn query_param(&self, name: &str) -> Option<&str> {
+        self.query.iter().find(|(key, _)| key == name).map(|(_, value)| value.as_str())
+    }
+
+    /// 将Query参数反序列化为`T`,失败时返回400错误
+    pub fn query_as<T: DeserializeOwned>(&self) -> Result<T, ServerError> {
+        serde_urlencoded::from_str(&self.raw_query)
+            .map_err(|e| ServerError::bad_request(format!("invalid query: {}", e)))
+    }
+
+    /// 获取一个请求头的文本值
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).and_then(|value| value.to_str().ok())
+    }
+
+    /// 以文本格式获取请求体,非法字符会被替换
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+
+    /// 将Json请求体反序列化为`T`,失败时返回400错误
+    pub fn json<T: DeserializeOwned>(&self) -> Result<T, ServerError> {
+        from_json_bytes(&self.body).map_err(|e| ServerError::bad_request(format!("invalid json body: {}", e)))
+    }
+}
@@ -0,0 +1,164 @@
+//! # 响应与处理函数错误
+
+use std::fmt::{Display, Formatter};
+use bytes::Bytes;
+use reqwest::header::{CONTENT_TYPE, HeaderMap, HeaderName, HeaderValue};
+use reqwest::StatusCode;
+use serde::Serialize;
+use crate::data::json::to_json_bytes;
+
+/// 处理函数的返回值
+pub type HandlerResult = Result<Response, ServerError>;
+
+/// HTTP响应
+#[derive(Debug, Clone)]
+pub struct Response {
+    // 响应状态码
+    pub status: StatusCode,
+    // 响应头
+    pub headers: HeaderMap,
+    // 响应体
+    pub body: Bytes,
+    // 构建过程中出现的第一个错误,发送时改为返回500
+    error: Option<String>,
+}
+
+impl Response {
+    /// 创建指定状态码、无响应体的响应
+    pub fn new(status: StatusCode) -> Self {
+        Response { status, headers: HeaderMap::new(), body: Bytes::new(), error: None }
+    }
+
+    /// 200空响应
+    pub fn ok() -> Self {
+        Response::new(StatusCode::OK)
+    }
+
+    /// 204空响应
+    pub fn no_content() -> Self {
+        Response::new(StatusCode::NO_CONTENT)
+    }
+
+    /// 200文本响应
+    pub fn text<S: Into<String>>(body: S) -> Self {
+        Response::ok().header("Content-Type", "text/plain; charset=utf-8").body(body.into())
+    }
+
+    /// 200 Json响应,序列化失败时返回500
+    pub fn json<T: Serialize + ?Sized>(body: &T) -> Self {
+        match to_json_bytes(body) {
+            Ok(bytes) => Response::ok().header("Content-Type", "application/json").body(bytes),
+            Err(e) => ServerError::internal(format!("failed to encode response: {}", e)).into(),
+        }
+    }
+
+    /// 设置状态码
+    pub fn status(mut self, status: StatusCode) -> Self {
+        self.status = status;
+        self
+    }
+
+    /// 设置响应头,名称或值不合法时发送500响应
+    pub fn header(mut self, key: &str, value: &str) -> Self {
+        match (HeaderName::from_bytes(key.as_bytes()), HeaderValue::from_str(value)) {
+            (Ok(name), Ok(value)) => {
+                self.headers.insert(name, value);
+            }
+            _ => {
+                self.error.get_or_insert_with(|| format!("invalid response header: {}", key));
+            }
+        }
+        self
+    }
+
+    /// 设置响应体
+    pub fn body<B: Into<Bytes>>(mut self, body: B) -> Self {
+        self.body = body.into();
+        self
+    }
+
+    /// 获取一个响应头的文本值
+    pub fn header_value(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).and_then(|value| value.to_str().ok())
+    }
+
+    /// 构建过程中出现的错误
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    // 构建过程中出现错误时替换为500响应
+    pub(crate) fn or_internal_error(self) -> Self {
+        match self.error {
+            Some(error) => ServerError::internal(error).into(),
+            None => self,
+        }
+    }
+}
+
+/// 处理函数返回的错误,转换为`{"error": message}`形式的Json响应
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerError {
+    status: StatusCode,
+    message: String,
+}
+
+impl ServerError {
+    /// 创建指定状态码的错误
+    pub fn new<S: Into<String>>(status: StatusCode, message: S) -> Self {
+        ServerError { status, message: message.into() }
+    }
+
+    /// 400错误
+    pub fn bad_request<S: Into<String>>(message: S) -> Self {
+        ServerError::new(StatusCode::BAD_REQUEST, message)
+    }
+
+    /// 401错误
+    pub fn unauthorized<S: Into<String>>(message: S) -> Self {
+        ServerError::new(StatusCode::UNAUTHORIZED, message)
+    }
+
+    /// 404错误
+    pub fn not_found<S: Into<String>>(message: S) -> Self {
+        ServerError::new(StatusCode::NOT_FOUND, message)
+    }
+
+    /// 500错误
+    pub fn internal<S: Into<String>>(message: S) -> Self {
+        ServerError::new(StatusCode::INTERNAL_SERVER_ERROR, message)
+    }
+
+    /// 响应状态码
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    /// 错误描述
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl Display for ServerError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.status, self.message)
+    }
+}
+
+impl std::error::Error for ServerError {}
+
+impl From<std::io::Error> for ServerError {
+    fn from(e: std::io::Error) -> Self {
+        ServerError::internal(e.to_string())
+    }
+}
+
+impl From<ServerError> for Response {
+    fn from(error: ServerError) -> Self {
+        let body = serde_json::json!({"error": error.message});
+        Response::new(error.status)
+            .header(CONTENT_TYPE.as_str(), "application/json")
+            .body(body.to_string())
+    }
+}
@@ -0,0 +1,278 @@
+//! # 路由与中间件
+//!
+//! 路径模板中`{name}`匹配一段路径,`{*name}`匹配剩余的全部路径(可以为空),只能位于末尾.
+//! 路由按照添加顺序匹配;路径匹配但方法不匹配时返回405,HEAD请求会交给GET路由处理.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Instant;
+use reqwest::header::{ALLOW, HeaderValue};
+use reqwest::Method;
+use crate::networks::http::middleware::BoxFuture;
+use crate::networks::http::percent;
+use crate::networks::server::files::StaticFiles;
+use crate::networks::server::request::Request;
+use crate::networks::server::response::{HandlerResult, Response, ServerError};
+
+/// 请求处理函数,`async fn(Request) -> HandlerResult`形式的函数与闭包自动实现
+pub trait Handler: Send + Sync + 'static {
+    /// 处理请求
+    fn call(&self, request: Request) -> BoxFuture<'static, HandlerResult>;
+}
+
+impl<F, Fut> Handler for F where
+    F: Fn(Request) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = HandlerResult> + Send + 'static
+{
+    fn call(&self, request: Request) -> BoxFuture<'static, HandlerResult> {
+        Box::pin(self(request))
+    }
+}
+
+/// 服务端中间件,按照添加顺序组成调用链:先添加的中间件最先处理请求、最后处理响应
+pub trait Middleware: Send + Sync + 'static {
+    /// 处理一次请求,通过`next.run(request)`将请求交给后续的中间件与路由,也可以直接返回响应
+    fn handle<'a>(&'a self, request: Request, next: Next<'a>) -> BoxFuture<'a, Response>;
+}
+
+/// 中间件调用链中剩余的部分
+#[derive(Clone, Copy)]
+pub struct Next<'a> {
+    router: &'a Router,
+    middlewares: &'a [Arc<dyn Middleware>],
+}
+
+impl<'a> Next<'a> {
+    pub(crate) fn new(router: &'a Router) -> Self {
+        Next { router, middlewares: &router.middlewares }
+    }
+
+    /// 执行后续的中间件,全部执行完后交给路由处理
+    pub fn run(self, request: Request) -> BoxFuture<'a, Response> {
+        match self.middlewares.split_first() {
+            Some((first, rest)) => first.handle(request, Next { router: self.router, middlewares: rest }),
+            None => Box::pin(self.router.dispatch(request)),
+        }
+    }
+}
+
+// 路径模板中的一段
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Static(String),
+    Param(String),
+    Wildcard(String),
+}
+
+struct Route {
+    method: Method,
+    segments: Vec<Segment>,
+    handler: Arc<dyn Handler>,
+}
+
+/// 路由表
+///
+/// # Examples
+/// ```
+/// use toys::networks::server::{Request, Response, Router, ServerError};
+/// let router = Router::new()
+///     .get("/health", |_| async { Ok(Response::text("ok")) })
+///     .get("/users/{id}", |request: Request| async move {
+///         let id: u64 = request.param_as("id")?;
+///         if id == 0 {
+///             return Err(ServerError::not_found("user not found"));
+///         }
+///         Ok(Response::json(&serde_json::json!({"id": id})))
+///     })
+///     .static_files("/assets", "./public");
+/// ```
+#[derive(Default)]
+pub struct Router {
+    routes: Vec<Route>,
+    middlewares: Vec<Arc<dyn Middleware>>,
+}
+
+impl Router {
+    /// 创建空的路由表
+    pub fn new() -> Self {
+        Router::default()
+    }
+
+    /// 添加路由,路径模板不合法时会panic
+    pub fn route<H: Handler>(mut self, method: Method, path: &str, handler: H) -> Self {
+        self.routes.push(Route { method, segments: parse_pattern(path), handler: Arc::new(handler) });
+        self
+    }
+
+    /// 添加GET路由
+    pub fn get<H: Handler>(self, path: &str, handler: H) -> Self {
+        self.route(Method::GET, path, handler)
+    }
+
+    /// 添加POST路由
+    pub fn post<H: Handler>(self, path: &str, handler: H) -> Self {
+        self.route(Method::POST, path, handler)
+    }
+
+    /// 添加PUT路由
+    pub fn put<H: Handler>(self, path: &str, handler: H) -> Self {
+        self.route(Method::PUT, path, handler)
+    }
+
+    /// 添加PATCH路由
+    pub fn patch<H: Handler>(self, path: &str, handler: H) -> Self {
+        self.route(Method::PATCH, path, handler)
+    }
+
+    /// 添加DELETE路由
+    pub fn delete<H: Handler>(self, path: &str, handler: H) -> Self {
+        self.route(Method::DELETE, path, handler)
+    }
+
+    /// 将`prefix`下的请求映射到目录`dir`中的静态文件
+    pub fn static_files<P: Into<PathBuf>>(self, prefix: &str, dir: P) -> Self {
+        let path = format!("{}/{{*path}}", prefix.trim_end_matches('/'));
+        self.get(&path, StaticFiles::new(dir))
+    }
+
+    /// 添加中间件
+    pub fn middleware<M: Middleware>(mut self, middleware: M) -> Self {
+        self.middlewares.push(Arc::new(middleware));
+        self
+    }
+
+    // 查找匹配的路由并调用处理函数
+    async fn dispatch(&self, mut request: Request) -> Response {
+        let mut allowed = Vec::new();
+        let mut found = None;
+        for route in &self.routes {
+            let Some(params) = match_path(&route.segments, &request.path) else { continue };
+            let head_as_get = request.method == Method::HEAD && route.method == Method::GET;
+            if route.method == request.method || head_as_get {
+                found = Some((route, params));
+                break;
+            }
+            allowed.push(route.method.as_str());
+        }
+        match found {
+            Some((route, params)) => {
+                request.set_params(params);
+                route.handler.call(request).await.unwrap_or_else(Response::from)
+            }
+            None if !allowed.is_empty() => {
+                let mut response = Response::from(ServerError::new(reqwest::StatusCode::METHOD_NOT_ALLOWED, "method not allowed"));
+                if let Ok(allow) = HeaderValue::from_str(&allowed.join(", ")) {
+                    response.headers.insert(ALLOW, allow);
+                }
+                response
+            }
+            None => ServerError::not_found(format!("no route for {} {}", request.method, request.path)).into(),
+        }
+    }
+}
+
+// 解析路径模板
+fn parse_pattern(path: &str) -> Vec<Segment> {
+    let parts: Vec<&str> = path.split('/').filter(|part| !part.is_empty()).collect();
+    parts.iter().enumerate().map(|(index, part)| {
+        match part.strip_prefix('{').and_then(|part| part.strip_suffix('}')) {
+            Some(name) if name.starts_with('*') => {
+                assert!(index + 1 == parts.len(), "wildcard must be the last segment in route {}", path);
+                Segment::Wildcard(name[1..].to_string())
+            }
+            Some(name) => Segment::Param(name.to_string()),
+            None => Segment::Static(part.to_string()),
+        }
+    }).collect()
+}
+
+// 匹配请求路径,返回解码后的路径参数
+fn match_path(segments: &[Segment], path: &str) -> Option<HashMap<String, String>> {
+    let parts: Vec<&str> = path.split('/').filter(|part| !part.is_empty()).collect();
+    let mut params = HashMap::new();
+    for (index, segment) in segments.iter().enumerate() {
+        match segment {
+            Segment::Wildcard(name) => {
+                let rest: Vec<String> = parts.get(index..).unwrap_or_default().iter().map(|part| percent::decode(part)).collect();
+                params.insert(name.clone(), rest.join("/"));
+                return Some(params);
+            }
+            Segment::Static(expected) => {
+                if parts.get(index) != Some(&expected.as_str()) {
+                    return None;
+                }
+            }
+            Segment::Param(name) => {
+                params.insert(name.clone(), percent::decode(parts.get(index)?));
+            }
+        }
+    }
+    (parts.len() == segments.len()).then_some(params)
+}
+
+/// 访问日志中间件,默认通过`println!`输出
+pub struct AccessLog {
+    logger: Box<dyn Fn(&str) + Send + Sync>,
+}
+
+impl Default for AccessLog {
+    fn default() -> Self {
+        AccessLog::with_logger(|line| println!("{}", line))
+    }
+}
+
+impl AccessLog {
+    /// 创建输出到标准输出的访问日志中间件
+    pub fn new() -> Self {
+        AccessLog::default()
+    }
+
+    /// 使用自定义的日志输出函数
+    pub fn with_logger<F>(logger: F) -> Self where F: Fn(&str) + Send + Sync + 'static {
+        AccessLog { logger: Box::new(logger) }
+    }
+}
+
+impl Middleware for AccessLog {
+    fn handle<'a>(&'a self, request: Request, next: Next<'a>) -> BoxFuture<'a, Response> {
+        Box::pin(async move {
+            let line = format!("{} {} {}", request.remote_addr, request.method, request.path);
+            let start = Instant::now();
+            let response = next.run(request).await;
+            (self.logger)(&format!("{} {} ({:?}, {} bytes)", line, response.status.as_u16(), start.elapsed(), response.body.len()));
+            response
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use crate::networks::server::router::{match_path, parse_pattern, Segment};
+
+    /// 测试路径模板解析与匹配
+    #[test]
+    fn test_match_path() {
+        let segments = parse_pattern("/users/{id}/files/{*path}");
+        assert_eq!(segments[1], Segment::Param("id".to_string()));
+        assert_eq!(segments[3], Segment::Wildcard("path".to_string()));
+        let params = match_path(&segments, "/users/%E5%BC%A0/files/a/b%20c.txt").unwrap();
+        assert_eq!(params, HashMap::from([("id".to_string(), "张".to_string()), ("path".to_string(), "a/b c.txt".to_string())]));
+        assert_eq!(match_path(&segments, "/users/1/files").unwrap()["path"], "");
+        assert!(match_path(&segments, "/users/1").is_none());
+
+        let segments = parse_pattern("/users/{id}");
+        assert!(match_path(&segments, "/users/1/").is_some());
+        assert!(match_path(&segments, "/users/1/extra").is_none());
+        assert!(match_path(&parse_pattern("/"), "/").unwrap().is_empty());
+    }
+
+    /// 测试通配符不在末尾时panic
+    #[test]
+    #[should_panic(expected = "wildcard must be the last segment")]
+    fn test_invalid_pattern() {
+        parse_pattern("/{*path}/edit");
+    }
+}
@@ -0,0 +1,535 @@
+//! # Webhook接收
+//!
+//! 依次校验请求签名、时间戳与投递ID,再将Json请求体反序列化后交给回调处理.
+//! 签名不符或时间戳超出容差时返回401,请求体格式不符时返回400,回调成功时返回204;
+//! 已处理完成的投递直接返回200且不会再次调用回调,正在处理中的投递返回409,
+//! 回调失败或被取消时会移除投递ID,以便服务商重试.
+//!
+//! # Examples
+//! ```no_run
+//! use serde::Deserialize;
+//! use toys::networks::server::Router;
+//! use toys::networks::server::webhook::{Delivery, Webhook};
+//!
+//! #[derive(Deserialize)]
+//! struct Push { after: String }
+//!
+//! let webhook = Webhook::github("secret").handler(|delivery: Delivery<Push>| async move {
+//!     println!("{:?} {:?} {}", delivery.id, delivery.event, delivery.payload.after);
+//!     Ok(())
+//! });
+//! let router = Router::new().post("/hooks/github", webhook);
+//! ```
+
+use std::collections::HashMap;
+use std::fmt::{Debug, Display, Formatter};
+use std::future::Future;
+use std::marker::PhantomData;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use hmac::{Hmac, Mac};
+use reqwest::StatusCode;
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use sha2::Sha256;
+use crate::data::json::from_json_bytes;
+use crate::networks::http::middleware::BoxFuture;
+use crate::networks::server::request::Request;
+use crate::networks::server::response::{HandlerResult, Response, ServerError};
+use crate::networks::server::router::Handler;
+
+/// 默认允许的时间戳误差
+pub const DEFAULT_TOLERANCE: Duration = Duration::from_secs(300);
+/// 默认记录投递ID的时长
+pub const DEFAULT_DEDUP_WINDOW: Duration = Duration::from_secs(24 * 60 * 60);
+/// 默认最多记录的投递ID数量,超过时丢弃最早的记录
+pub const DEFAULT_MAX_DELIVERIES: usize = 10_000;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// 签名方案
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Scheme {
+    /// GitHub:`X-Hub-Signature-256: sha256=<hex>`,签名内容为请求体;
+    /// 投递ID与事件类型取自`X-GitHub-Delivery`与`X-GitHub-Event`
+    GitHub,
+    /// Stripe:`Stripe-Signature: t=<时间戳>,v1=<hex>`,签名内容为`<时间戳>.<请求体>`;
+    /// 投递ID与事件类型取自请求体的`id`与`type`字段
+    Stripe,
+    /// 自定义方案:签名为请求头`signature_header`去掉前缀`prefix`后的hex值;
+    /// 设置了`timestamp_header`时签名内容为`<时间戳>.<请求体>`,否则为请求体
+    Custom {
+        signature_header: String,
+        prefix: String,
+        timestamp_header: Option<String>,
+        id_header: Option<String>,
+    },
+}
+
+/// 校验通过的一次投递
+#[derive(Debug, Clone)]
+pub struct Delivery<T> {
+    // 投递ID
+    pub id: Option<String>,
+    // 事件类型
+    pub event: Option<String>,
+    // 签名中的时间戳(秒)
+    pub timestamp: Option<i64>,
+    // 反序列化后的请求体
+    pub payload: T,
+}
+
+/// Webhook校验错误
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WebhookError {
+    /// 缺少签名相关的请求头
+    MissingHeader(String),
+    /// 签名格式错误或与所有密钥都不匹配
+    InvalidSignature,
+    /// 时间戳超出允许的误差
+    Expired { timestamp: i64, now: i64 },
+    /// 请求体反序列化失败
+    Decode(String),
+}
+
+impl Display for WebhookError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WebhookError::MissingHeader(name) => write!(f, "missing header: {}", name),
+            WebhookError::InvalidSignature => write!(f, "invalid signature"),
+            WebhookError::Expired { timestamp, now } => write!(f, "timestamp {} is outside the tolerance (now {})", timestamp, now),
+            WebhookError::Decode(e) => write!(f, "invalid payload: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for WebhookError {}
+
+impl From<WebhookError> for ServerError {
+    fn from(error: WebhookError) -> Self {
+        match error {
+            WebhookError::Decode(_) => ServerError::bad_request(error.to_string()),
+            _ => ServerError::unauthorized(error.to_string()),
+        }
+    }
+}
+
+/// Webhook校验配置,克隆后共享已处理的投递ID
+///
+/// # Examples
+/// ```
+/// use std::time::Duration;
+/// use toys::networks::server::webhook::Webhook;
+/// // 轮换密钥期间新旧密钥同时有效
+/// let webhook = Webhook::stripe("whsec_new").secret("whsec_old").tolerance(Duration::from_secs(60));
+/// ```
+#[derive(Clone)]
+pub struct Webhook {
+    scheme: Scheme,
+    secrets: Vec<Vec<u8>>,
+    tolerance: Duration,
+    dedup_window: Duration,
+    max_deliveries: usize,
+    // 投递ID及其处理状态
+    seen: Arc<Mutex<HashMap<String, DeliveryState>>>,
+}
+
+// 投递的处理状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DeliveryState {
+    // 回调正在执行
+    Processing,
+    // 回调执行成功的时间
+    Done(Instant),
+}
+
+impl Debug for Webhook {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Webhook")
+            .field("scheme", &self.scheme)
+            .field("secrets", &self.secrets.len())
+            .field("tolerance", &self.tolerance)
+            .finish()
+    }
+}
+
+impl Webhook {
+    /// 使用签名方案与密钥创建
+    pub fn new<S: AsRef<[u8]>>(scheme: Scheme, secret: S) -> Self {
+        Webhook {
+            scheme,
+            secrets: vec![secret.as_ref().to_vec()],
+            tolerance: DEFAULT_TOLERANCE,
+            dedup_window: DEFAULT_DEDUP_WINDOW,
+            max_deliveries: DEFAULT_MAX_DELIVERIES,
+            seen: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// GitHub签名方案
+    pub fn github<S: AsRef<[u8]>>(secret: S) -> Self {
+        Webhook::new(Scheme::GitHub, secret)
+    }
+
+    /// Stripe签名方案
+    pub fn stripe<S: AsRef<[u8]>>(secret: S) -> Self {
+        Webhook::new(Scheme::Stripe, secret)
+    }
+
+    /// 添加一个同样有效的密钥,用于密钥轮换
+    pub fn secret<S: AsRef<[u8]>>(mut self, secret: S) -> Self {
+        self.secrets.push(secret.as_ref().to_vec());
+        self
+    }
+
+    /// 设置允许的时间戳误差,只对带时间戳的签名方案生效
+    pub fn tolerance(mut self, tolerance: Duration) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// 设置记录投递ID的时长,期间重复的投递不会再次调用回调
+    pub fn dedup_window(mut self, window: Duration) -> Self {
+        self.dedup_window = window;
+        self
+    }
+
+    /// 设置最多记录的投递ID数量
+    pub fn max_deliveries(mut self, max: usize) -> Self {
+        self.max_deliveries = max.max(1);
+        self
+    }
+
+    /// 使用回调创建请求处理函数
+    pub fn handler<T, F, Fut>(self, callback: F) -> WebhookHandler<T, F> where
+        T: DeserializeOwned + Send + 'static,
+        F: Fn(Delivery<T>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), ServerError>> + Send + 'static
+    {
+        WebhookHandler { webhook: self, callback: Arc::new(callback), marker: PhantomData }
+    }
+
+    /// 校验签名与时间戳并反序列化请求体,不检查投递ID是否重复
+    pub fn verify<T: DeserializeOwned>(&self, request: &Request) -> Result<Delivery<T>, WebhookError> {
+        self.verify_at(request, chrono::Utc::now().timestamp())
+    }
+
+    /// 以`now`(秒)作为当前时间进行校验
+    pub fn verify_at<T: DeserializeOwned>(&self, request: &Request, now: i64) -> Result<Delivery<T>, WebhookError> {
+        let header = |name: &str| request.header(name).ok_or_else(|| WebhookError::MissingHeader(name.to_string()));
+        let body = request.body.as_ref();
+        let (timestamp, mut id, mut event) = match &self.scheme {
+            Scheme::GitHub => {
+                let signature = header("X-Hub-Signature-256")?.strip_prefix("sha256=").ok_or(WebhookError::InvalidSignature)?;
+                self.check_signature(&[body], [signature])?;
+                (None, request.header("X-GitHub-Delivery"), request.header("X-GitHub-Event"))
+            }
+            Scheme::Stripe => {
+                let (mut timestamp, mut signatures) = (None, Vec::new());
+                for (key, value) in header("Stripe-Signature")?.split(',').filter_map(|item| item.trim().split_once('=')) {
+                    match key {
+                        "t" => timestamp = Some(value),
+                        "v1" => signatures.push(value),
+                        _ => {}
+                    }
+                }
+                let timestamp = timestamp.ok_or(WebhookError::InvalidSignature)?;
+                self.check_signature(&[timestamp.as_bytes(), b".", body], signatures)?;
+                (Some(parse_timestamp(timestamp)?), None, None)
+            }
+            Scheme::Custom { signature_header, prefix, timestamp_header, id_header } => {
+                let signature = header(signature_header)?.strip_prefix(prefix.as_str()).ok_or(WebhookError::InvalidSignature)?;
+                let timestamp = match timestamp_header {
+                    Some(name) => {
+                        let timestamp = header(name)?;
+                        self.check_signature(&[timestamp.as_bytes(), b".", body], [signature])?;
+                        Some(parse_timestamp(timestamp)?)
+                    }
+                    None => {
+                        self.check_signature(&[body], [signature])?;
+                        None
+                    }
+                };
+                (timestamp, id_header.as_deref().and_then(|name| request.header(name)), None)
+            }
+        };
+        if let Some(timestamp) = timestamp {
+            if timestamp.abs_diff(now) > self.tolerance.as_secs() {
+                return Err(WebhookError::Expired { timestamp, now });
+            }
+        }
+
+        #[derive(Deserialize)]
+        struct Envelope {
+            id: Option<String>,
+            #[serde(rename = "type")]
+            kind: Option<String>,
+        }
+        let envelope;
+        if self.scheme == Scheme::Stripe {
+            envelope = from_json_bytes::<Envelope>(body).map_err(|e| WebhookError::Decode(e.to_string()))?;
+            (id, event) = (envelope.id.as_deref(), envelope.kind.as_deref());
+        }
+        let payload = from_json_bytes(body).map_err(|e| WebhookError::Decode(e.to_string()))?;
+        Ok(Delivery { id: id.map(str::to_string), event: event.map(str::to_string), timestamp, payload })
+    }
+
+    // 任意一个签名与任意一个密钥匹配即通过,使用常量时间比较
+    fn check_signature<'a, I>(&self, parts: &[&[u8]], signatures: I) -> Result<(), WebhookError> where I: IntoIterator<Item = &'a str> {
+        for signature in signatures {
+            let Ok(signature) = hex::decode(signature) else { continue };
+            for secret in &self.secrets {
+                let mut mac = HmacSha256::new_from_slice(secret).expect("hmac accepts keys of any length");
+                parts.iter().for_each(|part| mac.update(part));
+                if mac.verify_slice(&signature).is_ok() {
+                    return Ok(());
+                }
+            }
+        }
+        Err(WebhookError::InvalidSignature)
+    }
+
+    // 开始处理投递,已有相同ID的投递时返回其状态
+    fn begin(&self, id: &str) -> Option<DeliveryState> {
+        let mut seen = self.seen.lock().unwrap_or_else(|e| e.into_inner());
+        let now = Instant::now();
+        seen.retain(|_, state| match state {
+            DeliveryState::Processing => true,
+            DeliveryState::Done(done) => now.duration_since(*done) < self.dedup_window,
+        });
+        if let Some(state) = seen.get(id) {
+            return Some(*state);
+        }
+        // 超出数量时移除最早完成的投递,处理中的投递不会被移除
+        if seen.len() >= self.max_deliveries {
+            let oldest = seen.iter()
+                .filter_map(|(id, state)| match state {
+                    DeliveryState::Done(done) => Some((id, *done)),
+                    DeliveryState::Processing => None,
+                })
+                .min_by_key(|(_, done)| *done)
+                .map(|(id, _)| id.clone());
+            if let Some(oldest) = oldest {
+                seen.remove(&oldest);
+            }
+        }
+        seen.insert(id.to_string(), DeliveryState::Processing);
+        None
+    }
+
+    // 标记投递处理完成
+    fn complete(&self, id: &str) {
+        self.seen.lock().unwrap_or_else(|e| e.into_inner()).insert(id.to_string(), DeliveryState::Done(Instant::now()));
+    }
+
+    // 移除投递ID
+    fn forget(&self, id: &str) {
+        self.seen.lock().unwrap_or_else(|e| e.into_inner()).remove(id);
+    }
+}
+
+// 回调未完成(失败或请求被取消)时移除投递ID
+struct ProcessingGuard<'a> {
+    webhook: &'a Webhook,
+    id: Option<String>,
+}
+
+impl Drop for ProcessingGuard<'_> {
+    fn drop(&mut self) {
+        if let Some(id) = &self.id {
+            self.webhook.forget(id);
+        }
+    }
+}
+
+// 解析秒级时间戳
+fn parse_timestamp(value: &str) -> Result<i64, WebhookError> {
+    value.trim().parse().map_err(|_| WebhookError::InvalidSignature)
+}
+
+/// Webhook请求处理函数,通过[`Webhook::handler`]创建
+pub struct WebhookHandler<T, F> {
+    webhook: Webhook,
+    callback: Arc<F>,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T, F, Fut> Handler for WebhookHandler<T, F> where
+    T: DeserializeOwned + Send + 'static,
+    F: Fn(Delivery<T>) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<(), ServerError>> + Send + 'static
+{
+    fn call(&self, request: Request) -> BoxFuture<'static, HandlerResult> {
+        let (webhook, callback) = (self.webhook.clone(), self.callback.clone());
+        let delivery = webhook.verify::<T>(&request);
+        Box::pin(async move {
+            let delivery = delivery?;
+            let id = delivery.id.clone();
+            match id.as_deref().and_then(|id| webhook.begin(id)) {
+                Some(DeliveryState::Done(_)) => return Ok(Response::json(&serde_json::json!({"duplicate": true}))),
+                Some(DeliveryState::Processing) => {
+                    return Err(ServerError::new(StatusCode::CONFLICT, "delivery is being processed"));
+                }
+                None => {}
+            }
+            let mut guard = ProcessingGuard { webhook: &webhook, id };
+            callback(delivery).await?;
+            if let Some(id) = guard.id.take() {
+                webhook.complete(&id);
+            }
+            Ok(Response::no_content())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    use bytes::Bytes;
+    use hmac::Mac;
+    use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+    use reqwest::{Method, StatusCode};
+    use serde::Deserialize;
+    use crate::networks::http::HttpClient;
+    use crate::networks::server::{Handler, Request, Router, Server, ServerError};
+    use crate::networks::server::webhook::{Delivery, HmacSha256, Scheme, Webhook, WebhookError};
+
+    #[derive(Debug, Clone, PartialEq, Deserialize)]
+    struct Event {
+        action: String,
+    }
+
+    fn sign(secret: &str, content: &str) -> String {
+        let mut mac = HmacSha256::new_from_slice(secret.as_bytes()).unwrap();
+        mac.update(content.as_bytes());
+        hex::encode(mac.finalize().into_bytes())
+    }
+
+    fn request(headers: &[(&str, String)], body: &str) -> Request {
+        let mut map = HeaderMap::new();
+        for (name, value) in headers {
+            map.insert(HeaderName::from_bytes(name.as_bytes()).unwrap(), HeaderValue::from_str(value).unwrap());
+        }
+        Request::new(Method::POST, "/hook", "", map, Bytes::from(body.to_string()), "127.0.0.1:0".parse().unwrap())
+    }
+
+    /// 测试GitHub签名校验、投递去重以及回调失败后允许重试
+    #[test]
+    fn test_github_webhook() {
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let handler = Webhook::github("secret").handler({
+            let received = received.clone();
+            move |delivery: Delivery<Event>| {
+                let received = received.clone();
+                async move {
+                    let mut received = received.lock().unwrap();
+                    received.push((delivery.id.unwrap(), delivery.event.unwrap()));
+                    if delivery.payload.action == "fail" && received.len() == 1 {
+                        return Err(ServerError::internal("temporary failure"));
+                    }
+                    Ok(())
+                }
+            }
+        });
+        let server = Server::new(Router::new().post("/hooks/github", handler)).start("127.0.0.1:0").unwrap();
+        let client = HttpClient::new();
+        let send = |id: &str, body: &str, signature: &str| {
+            client.post(&server.url("/hooks/github"))
+                .header("X-GitHub-Delivery", id)
+                .header("X-GitHub-Event", "push")
+                .header("X-Hub-Signature-256", signature)
+                .body(body.to_string())
+                .send().unwrap().status
+        };
+
+        let failing = r#"{"action":"fail"}"#;
+        assert_eq!(send("d-1", failing, &format!("sha256={}", sign("secret", failing))), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(send("d-1", failing, &format!("sha256={}", sign("secret", failing))), StatusCode::NO_CONTENT);
+        assert_eq!(send("d-1", failing, &format!("sha256={}", sign("secret", failing))), StatusCode::OK);
+
+        let body = r#"{"action":"opened"}"#;
+        assert_eq!(send("d-2", body, &format!("sha256={}", sign("other", body))), StatusCode::UNAUTHORIZED);
+        assert_eq!(send("d-2", body, &sign("secret", body)), StatusCode::UNAUTHORIZED);
+        assert_eq!(send("d-2", "[]", &format!("sha256={}", sign("secret", "[]"))), StatusCode::BAD_REQUEST);
+        assert_eq!(send("d-2", body, &format!("sha256={}", sign("secret", body))), StatusCode::NO_CONTENT);
+        let missing = client.post(&server.url("/hooks/github")).body(body.to_string()).send().unwrap();
+        assert_eq!(missing.status, StatusCode::UNAUTHORIZED);
+        assert!(missing.text().contains("missing header: X-Hub-Signature-256"));
+
+        let expected = [("d-1", "push"), ("d-1", "push"), ("d-2", "push")].map(|(id, event)| (id.to_string(), event.to_string()));
+        assert_eq!(*received.lock().unwrap(), expected);
+        server.shutdown_blocking().unwrap();
+    }
+
+    /// 测试Stripe签名、时间戳容差、密钥轮换以及自定义方案
+    #[test]
+    fn test_timestamp_schemes() {
+        let body = r#"{"id":"evt_1","type":"invoice.paid","action":"paid"}"#;
+        let signature = format!("t=1000,v1=deadbeef,v1={}", sign("old", &format!("1000.{}", body)));
+        let stripe = Webhook::stripe("new").secret("old").tolerance(Duration::from_secs(60));
+        let delivery: Delivery<Event> = stripe.verify_at(&request(&[("Stripe-Signature", signature.clone())], body), 1030).unwrap();
+        assert_eq!((delivery.id.as_deref(), delivery.event.as_deref()), (Some("evt_1"), Some("invoice.paid")));
+        assert_eq!((delivery.timestamp, delivery.payload.action.as_str()), (Some(1000), "paid"));
+
+        let expired = stripe.verify_at::<Event>(&request(&[("Stripe-Signature", signature.clone())], body), 1061);
+        assert_eq!(expired.unwrap_err(), WebhookError::Expired { timestamp: 1000, now: 1061 });
+        let replaced = signature.replace("t=1000", "t=1050");
+        let forged = stripe.verify_at::<Event>(&request(&[("Stripe-Signature", replaced)], body), 1050);
+        assert_eq!(forged.unwrap_err(), WebhookError::InvalidSignature);
+        assert!(Webhook::stripe("new").verify_at::<Event>(&request(&[("Stripe-Signature", signature)], body), 1000).is_err());
+
+        let custom = Webhook::new(Scheme::Custom {
+            signature_header: "X-Signature".to_string(),
+            prefix: "v1=".to_string(),
+            timestamp_header: Some("X-Timestamp".to_string()),
+            id_header: Some("X-Delivery".to_string()),
+        }, "key");
+        let headers = [
+            ("X-Signature", format!("v1={}", sign("key", &format!("2000.{}", body)))),
+            ("X-Timestamp", "2000".to_string()),
+            ("X-Delivery", "abc".to_string()),
+        ];
+        let delivery: Delivery<Event> = custom.verify_at(&request(&headers, body), 2100).unwrap();
+        assert_eq!((delivery.id.as_deref(), delivery.event), (Some("abc"), None));
+        let missing = custom.verify_at::<Event>(&request(&headers[..1], body), 2000);
+        assert_eq!(missing.unwrap_err(), WebhookError::MissingHeader("X-Timestamp".to_string()));
+    }
+
+    /// 测试处理中的投递返回409,回调被取消后允许重试
+    #[tokio::test]
+    async fn test_in_flight_delivery() {
+        let (release_tx, release_rx) = tokio::sync::oneshot::channel::<()>();
+        let release = Arc::new(Mutex::new(Some(release_rx)));
+        let handler = Webhook::github("secret").handler(move |delivery: Delivery<Event>| {
+            let release = release.lock().unwrap().take();
+            async move {
+                if delivery.payload.action == "hang" {
+                    std::future::pending::<()>().await;
+                }
+                if let Some(release) = release {
+                    let _ = release.await;
+                }
+                Ok(())
+            }
+        });
+        let delivery = |id: &str, body: &str| request(&[
+            ("X-GitHub-Delivery", id.to_string()),
+            ("X-GitHub-Event", "push".to_string()),
+            ("X-Hub-Signature-256", format!("sha256={}", sign("secret", body))),
+        ], body);
+        let body = r#"{"action":"opened"}"#;
+
+        let first = tokio::spawn(handler.call(delivery("d-1", body)));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let in_flight = handler.call(delivery("d-1", body)).await.unwrap_err();
+        assert_eq!(in_flight.status(), StatusCode::CONFLICT);
+        release_tx.send(()).unwrap();
+        assert_eq!(first.await.unwrap().unwrap().status, StatusCode::NO_CONTENT);
+        assert_eq!(handler.call(delivery("d-1", body)).await.unwrap().status, StatusCode::OK);
+
+        let hang = r#"{"action":"hang"}"#;
+        assert!(tokio::time::timeout(Duration::from_millis(50), handler.call(delivery("d-2", hang))).await.is_err());
+        assert_eq!(handler.call(delivery("d-2", body)).await.unwrap().status, StatusCode::NO_CONTENT);
+    }
+}
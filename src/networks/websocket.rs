@@ -0,0 +1,566 @@
+//! # WebSocket客户端
+//!
+//! 支持ws与wss,收发文本、二进制以及Json消息.连接由后台任务维护:
+//! 自动回复服务端的Ping,定时发送Ping检测连接,断开后按照重试策略的退避时间重连.
+//! 开启`websocket`特性后可用.
+
+use std::fmt::{Debug, Display, Formatter};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::Duration;
+use futures_util::{SinkExt, StreamExt};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use tokio::net::TcpStream;
+use tokio::sync::{mpsc, oneshot};
+use tokio::time::{Instant, Interval};
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+use tokio_tungstenite::tungstenite;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::http::{HeaderName, HeaderValue};
+use crate::data::json::{from_json_bytes, from_json_str, to_json_str};
+use crate::networks::http::RetryPolicy;
+
+/// 默认的Ping间隔
+pub const DEFAULT_PING_INTERVAL: Duration = Duration::from_secs(30);
+
+/// 默认的建立连接超时时间
+pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// WebSocket客户端统一的结果类型
+pub type WsResult<T> = Result<T, WsError>;
+
+/// WebSocket客户端错误
+#[derive(Debug)]
+pub enum WsError {
+    /// 建立连接或收发数据失败
+    WebSocket(Box<tungstenite::Error>),
+    /// 建立连接超时
+    Timeout(Duration),
+    /// 在一个Ping间隔内没有收到Pong,连接可能已经断开
+    PongTimeout(Duration),
+    /// 连接已关闭
+    Closed,
+    /// 请求地址或请求头不合法
+    InvalidRequest(String),
+    /// 消息序列化失败
+    Encode(std::io::Error),
+    /// 消息反序列化失败
+    Decode(serde_json::Error),
+}
+
+impl Display for WsError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WsError::WebSocket(e) => write!(f, "websocket error: {}", e),
+            WsError::Timeout(timeout) => write!(f, "websocket connect timed out after {:?}", timeout),
+            WsError::PongTimeout(timeout) => write!(f, "no pong received within {:?}", timeout),
+            WsError::Closed => write!(f, "websocket connection closed"),
+            WsError::InvalidRequest(msg) => write!(f, "invalid websocket request: {}", msg),
+            WsError::Encode(e) => write!(f, "failed to encode message: {}", e),
+            WsError::Decode(e) => write!(f, "failed to decode message: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for WsError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            WsError::WebSocket(e) => Some(e.as_ref()),
+            WsError::Encode(e) => Some(e),
+            WsError::Decode(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<tungstenite::Error> for WsError {
+    fn from(e: tungstenite::Error) -> Self {
+        WsError::WebSocket(Box::new(e))
+    }
+}
+
+/// 数据消息,Ping、Pong与Close由客户端内部处理
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Message {
+    Text(String),
+    Binary(Vec<u8>),
+}
+
+impl Message {
+    /// 将`value`序列化为Json文本消息
+    pub fn json<T: Serialize + ?Sized>(value: &T) -> WsResult<Self> {
+        to_json_str(value).map(Message::Text).map_err(WsError::Encode)
+    }
+
+    /// 获取文本消息的内容,二进制消息返回None
+    pub fn as_text(&self) -> Option<&str> {
+        match self {
+            Message::Text(text) => Some(text),
+            Message::Binary(_) => None,
+        }
+    }
+
+    /// 获取消息的原始字节
+    pub fn as_bytes(&self) -> &[u8] {
+        match self {
+            Message::Text(text) => text.as_bytes(),
+            Message::Binary(bytes) => bytes,
+        }
+    }
+
+    /// 将文本或二进制消息反序列化为`T`
+    pub fn parse_json<T: DeserializeOwned>(&self) -> WsResult<T> {
+        match self {
+            Message::Text(text) => from_json_str(text),
+            Message::Binary(bytes) => from_json_bytes(bytes),
+        }.map_err(WsError::Decode)
+    }
+}
+
+impl From<Message> for tungstenite::Message {
+    fn from(message: Message) -> Self {
+        match message {
+            Message::Text(text) => tungstenite::Message::Text(text),
+            Message::Binary(bytes) => tungstenite::Message::Binary(bytes),
+        }
+    }
+}
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+// 每次连接成功后需要发送的消息,如重新订阅
+type ConnectHook = Arc<dyn Fn() -> Vec<Message> + Send + Sync>;
+
+/// WebSocket连接构建器
+///
+/// # Examples
+/// ```no_run
+/// use std::time::Duration;
+/// use toys::networks::http::RetryPolicy;
+/// use toys::networks::websocket::{Message, WebSocket};
+/// # async fn run() -> toys::networks::websocket::WsResult<()> {
+/// let mut socket = WebSocket::builder("wss://stream.example.com/ws")
+///     .header("Authorization", "Bearer t0ken")
+///     .ping_interval(Duration::from_secs(15))
+///     .reconnect(RetryPolicy::new(10).backoff(Duration::from_millis(500), Duration::from_secs(30)))
+///     .on_connect(|| vec![Message::Text(r#"{"op":"subscribe","channel":"trades"}"#.into())])
+///     .connect()
+///     .await?;
+/// while let Some(message) = socket.recv().await {
+///     println!("{:?}", message?);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct WebSocketBuilder {
+    url: String,
+    headers: Vec<(String, String)>,
+    connect_timeout: Duration,
+    // 为None时不发送Ping
+    ping_interval: Option<Duration>,
+    // 为None时不重连
+    reconnect: Option<RetryPolicy>,
+    on_connect: Option<ConnectHook>,
+}
+
+impl Debug for WebSocketBuilder {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WebSocketBuilder")
+            .field("url", &self.url)
+            .field("connect_timeout", &self.connect_timeout)
+            .field("ping_interval", &self.ping_interval)
+            .field("reconnect", &self.reconnect)
+            .finish_non_exhaustive()
+    }
+}
+
+impl WebSocketBuilder {
+    fn new(url: &str) -> Self {
+        WebSocketBuilder {
+            url: url.to_string(),
+            headers: Vec::new(),
+            connect_timeout: DEFAULT_CONNECT_TIMEOUT,
+            ping_interval: Some(DEFAULT_PING_INTERVAL),
+            reconnect: None,
+            on_connect: None,
+        }
+    }
+
+    /// 添加握手请求头
+    pub fn header(mut self, key: &str, value: &str) -> Self {
+        self.headers.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    /// 设置建立连接的超时时间
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = timeout;
+        self
+    }
+
+    /// 设置Ping间隔,一个间隔内没有收到Pong时视为连接断开
+    pub fn ping_interval(mut self, interval: Duration) -> Self {
+        self.ping_interval = Some(interval);
+        self
+    }
+
+    /// 不主动发送Ping
+    pub fn no_ping(mut self) -> Self {
+        self.ping_interval = None;
+        self
+    }
+
+    /// 设置断线重连策略,`max_attempts`为连续重连的最大次数,等待时间按照策略的退避时间计算
+    pub fn reconnect(mut self, policy: RetryPolicy) -> Self {
+        self.reconnect = Some(policy);
+        self
+    }
+
+    /// 设置每次连接成功(包括第一次连接)后发送的消息
+    pub fn on_connect<F>(mut self, messages: F) -> Self where F: Fn() -> Vec<Message> + Send + Sync + 'static {
+        self.on_connect = Some(Arc::new(messages));
+        self
+    }
+
+    /// 建立连接,第一次连接失败时直接返回错误
+    pub async fn connect(self) -> WsResult<WebSocket> {
+        let socket = self.open().await?;
+        let (commands, command_rx) = mpsc::unbounded_channel();
+        let (incoming_tx, incoming) = mpsc::unbounded_channel();
+        let connected = Arc::new(AtomicBool::new(true));
+        let reconnects = Arc::new(AtomicUsize::new(0));
+        let worker = Worker {
+            config: self,
+            commands: command_rx,
+            incoming: incoming_tx,
+            connected: connected.clone(),
+            reconnects: reconnects.clone(),
+        };
+        tokio::spawn(worker.run(socket));
+        Ok(WebSocket { commands, incoming, connected, reconnects })
+    }
+
+    // 建立一次连接并发送连接后的消息
+    async fn open(&self) -> WsResult<Socket> {
+        let mut request = self.url.as_str().into_client_request()?;
+        for (key, value) in &self.headers {
+            match (HeaderName::from_bytes(key.as_bytes()), HeaderValue::from_str(value)) {
+                (Ok(name), Ok(value)) => {
+                    request.headers_mut().append(name, value);
+                }
+                _ => return Err(WsError::InvalidRequest(format!("invalid header: {}: {}", key, value))),
+            }
+        }
+        let (mut socket, _) = tokio::time::timeout(self.connect_timeout, tokio_tungstenite::connect_async(request)).await
+            .map_err(|_| WsError::Timeout(self.connect_timeout))??;
+        if let Some(on_connect) = &self.on_connect {
+            for message in on_connect() {
+                socket.send(message.into()).await?;
+            }
+        }
+        Ok(socket)
+    }
+}
+
+// 发送给后台任务的命令
+enum Command {
+    Send(Message, oneshot::Sender<WsResult<()>>),
+    Close(oneshot::Sender<()>),
+}
+
+/// WebSocket连接,后台任务负责收发消息、心跳与重连
+#[derive(Debug)]
+pub struct WebSocket {
+    commands: mpsc::UnboundedSender<Command>,
+    incoming: mpsc::UnboundedReceiver<WsResult<Message>>,
+    connected: Arc<AtomicBool>,
+    reconnects: Arc<AtomicUsize>,
+}
+
+impl WebSocket {
+    /// 获取连接构建器
+    pub fn builder(url: &str) -> WebSocketBuilder {
+        WebSocketBuilder::new(url)
+    }
+
+    /// 使用默认配置建立连接,不会重连
+    pub async fn connect(url: &str) -> WsResult<Self> {
+        WebSocketBuilder::new(url).connect().await
+    }
+
+    /// 发送一条消息,重连期间会等待连接恢复后发送
+    pub async fn send(&self, message: Message) -> WsResult<()> {
+        let (tx, rx) = oneshot::channel();
+        self.commands.send(Command::Send(message, tx)).map_err(|_| WsError::Closed)?;
+        rx.await.map_err(|_| WsError::Closed)?
+    }
+
+    /// 发送文本消息
+    pub async fn send_text(&self, text: &str) -> WsResult<()> {
+        self.send(Message::Text(text.to_string())).await
+    }
+
+    /// 发送二进制消息
+    pub async fn send_binary<B: Into<Vec<u8>>>(&self, bytes: B) -> WsResult<()> {
+        self.send(Message::Binary(bytes.into())).await
+    }
+
+    /// 将`value`序列化为Json文本消息后发送
+    pub async fn send_json<T: Serialize + ?Sized>(&self, value: &T) -> WsResult<()> {
+        self.send(Message::json(value)?).await
+    }
+
+    /// 接收下一条消息,连接关闭且不再重连时返回None
+    pub async fn recv(&mut self) -> Option<WsResult<Message>> {
+        self.incoming.recv().await
+    }
+
+    /// 接收下一条消息并反序列化为`T`
+    pub async fn recv_json<T: DeserializeOwned>(&mut self) -> Option<WsResult<T>> {
+        Some(self.recv().await?.and_then(|message| message.parse_json()))
+    }
+
+    /// 当前是否已连接
+    pub fn is_connected(&self) -> bool {
+        self.connected.load(Ordering::Relaxed)
+    }
+
+    /// 重连成功的次数
+    pub fn reconnects(&self) -> usize {
+        self.reconnects.load(Ordering::Relaxed)
+    }
+
+    /// 发送Close帧并等待后台任务结束
+    pub async fn close(self) {
+        let (tx, rx) = oneshot::channel();
+        if self.commands.send(Command::Close(tx)).is_ok() {
+            let _ = rx.await;
+        }
+    }
+}
+
+// 维护连接的后台任务
+struct Worker {
+    config: WebSocketBuilder,
+    commands: mpsc::UnboundedReceiver<Command>,
+    incoming: mpsc::UnboundedSender<WsResult<Message>>,
+    connected: Arc<AtomicBool>,
+    reconnects: Arc<AtomicUsize>,
+}
+
+impl Worker {
+    async fn run(mut self, mut socket: Socket) {
+        loop {
+            let Some(error) = self.session(&mut socket).await else {
+                break;
+            };
+            self.connected.store(false, Ordering::Relaxed);
+            match self.reconnect(error).await {
+                Ok(reconnected) => {
+                    socket = reconnected;
+                    self.connected.store(true, Ordering::Relaxed);
+                    self.reconnects.fetch_add(1, Ordering::Relaxed);
+                }
+                // 服务端正常关闭且不重连时直接结束
+                Err(WsError::Closed) => break,
+                Err(error) => {
+                    let _ = self.incoming.send(Err(error));
+                    break;
+                }
+            }
+        }
+        self.connected.store(false, Ordering::Relaxed);
+    }
+
+    // 收发消息直到连接断开,返回断开的原因;主动关闭时返回None
+    async fn session(&mut self, socket: &mut Socket) -> Option<WsError> {
+        let mut ping = self.config.ping_interval
+            .map(|interval| tokio::time::interval_at(Instant::now() + interval, interval));
+        let mut awaiting_pong = false;
+        loop {
+            tokio::select! {
+                command = self.commands.recv() => match command {
+                    Some(Command::Send(message, reply)) => {
+                        let result = socket.send(message.into()).await;
+                        let failed = result.is_err();
+                        let _ = reply.send(result.map_err(WsError::from));
+                        if failed {
+                            return Some(WsError::Closed);
+                        }
+                    }
+                    Some(Command::Close(reply)) => {
+                        close(socket).await;
+                        let _ = reply.send(());
+                        return None;
+                    }
+                    None => {
+                        close(socket).await;
+                        return None;
+                    }
+                },
+                frame = socket.next() => match frame {
+                    Some(Ok(tungstenite::Message::Text(text))) => {
+                        let _ = self.incoming.send(Ok(Message::Text(text)));
+                    }
+                    Some(Ok(tungstenite::Message::Binary(bytes))) => {
+                        let _ = self.incoming.send(Ok(Message::Binary(bytes)));
+                    }
+                    Some(Ok(tungstenite::Message::Pong(_))) => awaiting_pong = false,
+                    // Ping会在下一次读写时自动回复Pong,Close会自动回复并结束读取
+                    Some(Ok(_)) => {}
+                    Some(Err(e)) => return Some(e.into()),
+                    None => return Some(WsError::Closed),
+                },
+                _ = tick(&mut ping) => {
+                    if awaiting_pong {
+                        return self.config.ping_interval.map(WsError::PongTimeout);
+                    }
+                    if let Err(e) = socket.send(tungstenite::Message::Ping(Vec::new())).await {
+                        return Some(e.into());
+                    }
+                    awaiting_pong = true;
+                }
+            }
+        }
+    }
+
+    // 按照重连策略重连,没有重连策略或超过次数时返回最后一次的错误
+    async fn reconnect(&self, mut error: WsError) -> WsResult<Socket> {
+        let Some(policy) = &self.config.reconnect else {
+            return Err(error);
+        };
+        for attempt in 1..=policy.max_attempts {
+            tokio::time::sleep(policy.backoff_for(attempt)).await;
+            match self.config.open().await {
+                Ok(socket) => return Ok(socket),
+                Err(e) => error = e,
+            }
+        }
+        Err(error)
+    }
+}
+
+// 等待下一次Ping,没有设置Ping间隔时永远等待
+async fn tick(interval: &mut Option<Interval>) {
+    match interval {
+        Some(interval) => {
+            interval.tick().await;
+        }
+        None => std::future::pending().await,
+    }
+}
+
+// 发送Close帧并等待服务端确认
+async fn close(socket: &mut Socket) {
+    if socket.close(None).await.is_ok() {
+        let _ = tokio::time::timeout(Duration::from_secs(1), async {
+            while let Some(Ok(_)) = socket.next().await {}
+        }).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+    use std::time::Duration;
+    use futures_util::{SinkExt, StreamExt};
+    use serde::{Deserialize, Serialize};
+    use tokio::net::TcpListener;
+    use tokio_tungstenite::tungstenite;
+    use crate::networks::http::RetryPolicy;
+    use crate::networks::websocket::{Message, WebSocket, WsError};
+
+    // 本地回显服务: 收到"drop"时断开连接,收到"ping me"时发送Ping并在收到Pong后回复"pong ok"
+    async fn echo_server() -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let Ok(mut socket) = tokio_tungstenite::accept_async(stream).await else { return };
+                    while let Some(Ok(message)) = socket.next().await {
+                        match message {
+                            tungstenite::Message::Text(text) if text == "drop" => return,
+                            tungstenite::Message::Text(text) if text == "ping me" => {
+                                socket.send(tungstenite::Message::Ping(b"hi".to_vec())).await.unwrap();
+                            }
+                            tungstenite::Message::Pong(payload) if payload == b"hi" => {
+                                socket.send(tungstenite::Message::Text("pong ok".into())).await.unwrap();
+                            }
+                            message @ (tungstenite::Message::Text(_) | tungstenite::Message::Binary(_)) => {
+                                socket.send(message).await.unwrap();
+                            }
+                            _ => {}
+                        }
+                    }
+                });
+            }
+        });
+        addr
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Trade {
+        symbol: String,
+        price: f64,
+    }
+
+    /// 测试收发文本、二进制与Json消息以及回复服务端的Ping
+    #[tokio::test]
+    async fn test_echo() {
+        let addr = echo_server().await;
+        let mut socket = WebSocket::connect(&format!("ws://{}/", addr)).await.unwrap();
+        socket.send_text("你好").await.unwrap();
+        assert_eq!(socket.recv().await.unwrap().unwrap(), Message::Text("你好".into()));
+        socket.send_binary(vec![0u8, 1, 2]).await.unwrap();
+        assert_eq!(socket.recv().await.unwrap().unwrap().as_bytes(), [0, 1, 2]);
+        let trade = Trade { symbol: "BTC".into(), price: 65000.5 };
+        socket.send_json(&trade).await.unwrap();
+        assert_eq!(socket.recv_json::<Trade>().await.unwrap().unwrap(), trade);
+        socket.send_text("not json").await.unwrap();
+        assert!(matches!(socket.recv_json::<Trade>().await, Some(Err(WsError::Decode(_)))));
+
+        socket.send_text("ping me").await.unwrap();
+        assert_eq!(socket.recv().await.unwrap().unwrap().as_text(), Some("pong ok"));
+        assert!(socket.is_connected());
+        socket.close().await;
+    }
+
+    /// 测试断线后按照退避时间重连并重新发送连接消息
+    #[tokio::test]
+    async fn test_reconnect() {
+        let addr = echo_server().await;
+        let mut socket = WebSocket::builder(&format!("ws://{}/", addr))
+            .ping_interval(Duration::from_millis(200))
+            .reconnect(RetryPolicy::new(3).backoff(Duration::from_millis(10), Duration::from_millis(50)))
+            .on_connect(|| vec![Message::Text("subscribe".into())])
+            .connect()
+            .await
+            .unwrap();
+        assert_eq!(socket.recv().await.unwrap().unwrap().as_text(), Some("subscribe"));
+        socket.send_text("drop").await.unwrap();
+        assert_eq!(socket.recv().await.unwrap().unwrap().as_text(), Some("subscribe"));
+        assert_eq!(socket.reconnects(), 1);
+        socket.send_text("after").await.unwrap();
+        assert_eq!(socket.recv().await.unwrap().unwrap().as_text(), Some("after"));
+
+        // 不重连时服务端断开后结束
+        let mut socket = WebSocket::connect(&format!("ws://{}/", addr)).await.unwrap();
+        socket.send_text("drop").await.unwrap();
+        assert!(socket.recv().await.is_none_or(|result| result.is_err()));
+        assert!(!socket.is_connected());
+    }
+
+    /// 测试连接失败
+    #[tokio::test]
+    async fn test_connect_errors() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        drop(listener);
+        assert!(matches!(WebSocket::connect(&format!("ws://{}/", addr)).await, Err(WsError::WebSocket(_))));
+        assert!(WebSocket::connect("http://127.0.0.1/").await.is_err());
+        let result = WebSocket::builder("ws://127.0.0.1/").header("bad header", "v").connect().await;
+        assert!(matches!(result, Err(WsError::InvalidRequest(_))));
+    }
+}
@@ -16,7 +16,6 @@ pub fn is_blank(s: String) -> bool {
 ///use toys::strings::is_not_blank;
 ///assert_eq!(is_not_blank(String::from("123")),true)
 ///```
-
 pub fn is_not_blank(s: String) -> bool{
     !s.is_empty()
 }
\ No newline at end of file
@@ -0,0 +1,288 @@
+//! # toys过程宏
+//!
+//! 为`toys`提供声明式REST客户端宏[`rest_client`],通过`toys`的`macros`特性使用,
+//! 生成的代码依赖`toys::networks::http::rest`中的运行时支持.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, FnArg, GenericArgument, Ident, ItemTrait, LitStr, Pat, PathArguments, ReturnType, TraitItem, TraitItemFn, Type};
+
+/// 支持的请求方法属性
+const HTTP_METHODS: [&str; 6] = ["get", "post", "put", "patch", "delete", "head"];
+
+/// 将带有请求方法属性的trait转换为REST客户端.
+///
+/// 生成名为`<Trait>Client`的结构体(也可以通过`#[rest_client(Name)]`指定),实现该trait:
+/// - 方法必须是`async fn`,第一个参数为`&self`,返回值为`Result<T, E>`且`E: From<HttpError>`
+/// - 方法属性:`#[get("/users/{id}")]`、`#[post(..)]`、`#[put(..)]`、`#[patch(..)]`、`#[delete(..)]`、`#[head(..)]`
+/// - 没有属性的参数用于填充路径中的同名占位符,会进行百分号编码
+/// - `#[query]`/`#[query("name")]`:Query参数,值为`None`时省略
+/// - `#[header("Name")]`:请求头,取值使用`Display`
+/// - `#[body]`:Json请求体;`#[form]`:表单请求体
+/// - `T`为`()`时只检查状态码,为`String`时返回文本,为`HttpResponse`时返回原始响应且不检查状态码,其余按Json反序列化
+#[proc_macro_attribute]
+pub fn rest_client(args: TokenStream, input: TokenStream) -> TokenStream {
+    let item = parse_macro_input!(input as ItemTrait);
+    let client = if args.is_empty() {
+        format_ident!("{}Client", item.ident)
+    } else {
+        parse_macro_input!(args as Ident)
+    };
+    expand(client, item).unwrap_or_else(syn::Error::into_compile_error).into()
+}
+
+// 生成trait声明、客户端结构体与实现
+fn expand(client: Ident, mut item: ItemTrait) -> syn::Result<TokenStream2> {
+    if !item.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(&item.generics, "rest client traits cannot be generic"));
+    }
+    let mut methods = Vec::new();
+    for trait_item in &mut item.items {
+        match trait_item {
+            TraitItem::Fn(method) => methods.push(expand_method(method)?),
+            other => return Err(syn::Error::new_spanned(other, "only methods are supported in a rest client trait")),
+        }
+    }
+    let vis = &item.vis;
+    let name = &item.ident;
+    let doc = format!("`{}`的HTTP客户端实现,由`rest_client`宏生成", name);
+    Ok(quote! {
+        #[allow(async_fn_in_trait)]
+        #item
+
+        #[doc = #doc]
+        #[derive(Debug, Clone)]
+        #vis struct #client {
+            inner: ::toys::networks::http::rest::RestClient,
+        }
+
+        impl #client {
+            /// 使用默认异步客户端创建,请求地址为`base_url`与方法路径的拼接
+            #vis fn new(base_url: &str) -> Self {
+                #client { inner: ::toys::networks::http::rest::RestClient::new(base_url) }
+            }
+
+            /// 使用指定的HTTP客户端创建,可以复用客户端的中间件、认证与超时配置
+            #vis fn with_client(client: ::toys::networks::http::AsyncHttpClient, base_url: &str) -> Self {
+                #client { inner: ::toys::networks::http::rest::RestClient::with_client(client, base_url) }
+            }
+
+            /// 获取内部使用的REST客户端
+            #vis fn rest(&self) -> &::toys::networks::http::rest::RestClient {
+                &self.inner
+            }
+        }
+
+        impl #name for #client {
+            #(#methods)*
+        }
+    })
+}
+
+// 参数的用途
+enum ParamKind {
+    Path,
+    Query(String),
+    Header(String),
+    Body,
+    Form,
+}
+
+// 响应的处理方式
+enum Output {
+    Unit,
+    Text,
+    Raw,
+    Json,
+}
+
+// 去掉方法与参数上的辅助属性,生成方法实现
+fn expand_method(method: &mut TraitItemFn) -> syn::Result<TokenStream2> {
+    let span_target = method.sig.ident.clone();
+    let position = method.attrs.iter().position(|attr| HTTP_METHODS.iter().any(|name| attr.path().is_ident(name)));
+    let Some(position) = position else {
+        return Err(syn::Error::new_spanned(span_target, "missing http method attribute such as #[get(\"/path\")]"));
+    };
+    let attr = method.attrs.remove(position);
+    let http_method = format_ident!("{}", attr.path().get_ident().unwrap().to_string().to_uppercase());
+    let template: LitStr = attr.parse_args()?;
+    if method.default.is_some() {
+        return Err(syn::Error::new_spanned(span_target, "rest client methods cannot have a default body"));
+    }
+    if method.sig.asyncness.is_none() {
+        return Err(syn::Error::new_spanned(method.sig.fn_token, "rest client methods must be async"));
+    }
+    if !matches!(method.sig.inputs.first(), Some(FnArg::Receiver(receiver)) if receiver.reference.is_some() && receiver.mutability.is_none()) {
+        return Err(syn::Error::new_spanned(span_target, "rest client methods must take &self"));
+    }
+
+    let mut params = Vec::new();
+    for input in method.sig.inputs.iter_mut().skip(1) {
+        let FnArg::Typed(arg) = input else { continue };
+        let Pat::Ident(pat) = arg.pat.as_ref() else {
+            return Err(syn::Error::new_spanned(&arg.pat, "rest client parameters must be plain identifiers"));
+        };
+        let ident = pat.ident.clone();
+        let mut kind = ParamKind::Path;
+        let mut remaining = Vec::new();
+        for attr in arg.attrs.drain(..) {
+            let name = || -> syn::Result<String> {
+                match &attr.meta {
+                    syn::Meta::Path(_) => Ok(ident.to_string()),
+                    _ => attr.parse_args::<LitStr>().map(|name| name.value()),
+                }
+            };
+            kind = if attr.path().is_ident("query") {
+                ParamKind::Query(name()?)
+            } else if attr.path().is_ident("header") {
+                ParamKind::Header(attr.parse_args::<LitStr>()?.value())
+            } else if attr.path().is_ident("body") {
+                ParamKind::Body
+            } else if attr.path().is_ident("form") {
+                ParamKind::Form
+            } else {
+                remaining.push(attr);
+                continue;
+            };
+        }
+        arg.attrs = remaining;
+        params.push((ident, kind));
+    }
+
+    let segments = parse_path(&template.value()).map_err(|message| syn::Error::new_spanned(&template, message))?;
+    let mut format = String::new();
+    let mut path_args = Vec::new();
+    for segment in &segments {
+        match segment {
+            Segment::Literal(literal) => format.push_str(literal),
+            Segment::Param(name) => {
+                let ident = params.iter()
+                    .find(|(ident, kind)| matches!(kind, ParamKind::Path) && ident == name)
+                    .map(|(ident, _)| ident)
+                    .ok_or_else(|| syn::Error::new_spanned(&template, format!("no parameter named `{}` for path placeholder", name)))?;
+                format.push_str("{}");
+                path_args.push(quote!(::toys::networks::http::rest::encode_path(&#ident.to_string())));
+            }
+        }
+    }
+
+    let mut builders = Vec::new();
+    let mut has_body = false;
+    for (ident, kind) in &params {
+        let builder = match kind {
+            ParamKind::Path => {
+                if !segments.iter().any(|segment| matches!(segment, Segment::Param(name) if ident == name)) {
+                    return Err(syn::Error::new_spanned(ident, format!(
+                        "parameter `{}` is not used in the path, annotate it with #[query], #[header], #[body] or #[form]", ident)));
+                }
+                continue;
+            }
+            ParamKind::Query(name) => quote!(let __request = __request.query(&[(#name, &#ident)]);),
+            ParamKind::Header(name) => quote!(let __request = __request.header(#name, &#ident.to_string());),
+            ParamKind::Body | ParamKind::Form if has_body => {
+                return Err(syn::Error::new_spanned(ident, "only one #[body] or #[form] parameter is allowed"));
+            }
+            ParamKind::Body => quote!(let __request = __request.json(&#ident);),
+            ParamKind::Form => quote!(let __request = __request.form(&#ident);),
+        };
+        has_body |= matches!(kind, ParamKind::Body | ParamKind::Form);
+        builders.push(builder);
+    }
+
+    let send = match output(&method.sig.output) {
+        Output::Unit => quote! {
+            __request.send().await?.error_for_status()?;
+            ::std::result::Result::Ok(())
+        },
+        Output::Text => quote!(::std::result::Result::Ok(__request.send_text().await?.body)),
+        Output::Raw => quote!(__request.send().await.map_err(::std::convert::From::from)),
+        Output::Json => quote!(::std::result::Result::Ok(__request.send_json().await?.body)),
+    };
+    let sig = &method.sig;
+    Ok(quote! {
+        #sig {
+            let __path = format!(#format, #(#path_args),*);
+            let __request = self.inner.request(::toys::networks::http::Method::#http_method, &__path);
+            #(#builders)*
+            #send
+        }
+    })
+}
+
+// 根据返回值`Result<T, E>`中的`T`确定响应的处理方式
+fn output(output: &ReturnType) -> Output {
+    let ReturnType::Type(_, ty) = output else { return Output::Unit };
+    let ok = match ty.as_ref() {
+        Type::Path(path) => path.path.segments.last().and_then(|segment| match &segment.arguments {
+            PathArguments::AngleBracketed(args) => args.args.iter().find_map(|arg| match arg {
+                GenericArgument::Type(ty) => Some(ty),
+                _ => None,
+            }),
+            _ => None,
+        }),
+        _ => None,
+    };
+    match ok {
+        Some(Type::Tuple(tuple)) if tuple.elems.is_empty() => Output::Unit,
+        Some(Type::Path(path)) => match path.path.segments.last().map(|segment| segment.ident.to_string()).as_deref() {
+            Some("String") => Output::Text,
+            Some("HttpResponse") => Output::Raw,
+            _ => Output::Json,
+        },
+        _ => Output::Json,
+    }
+}
+
+// 路径模板中的一段
+#[derive(Debug, PartialEq)]
+enum Segment {
+    Literal(String),
+    Param(String),
+}
+
+// 解析`/users/{id}/repos`形式的路径模板
+fn parse_path(template: &str) -> Result<Vec<Segment>, String> {
+    let mut segments = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find(['{', '}']) {
+        if rest[start..].starts_with('}') {
+            return Err(format!("unmatched `}}` in path `{}`", template));
+        }
+        let end = rest[start..].find('}').ok_or_else(|| format!("unclosed `{{` in path `{}`", template))? + start;
+        let name = rest[start + 1..end].trim();
+        if name.is_empty() || !name.chars().all(|c| c == '_' || c.is_alphanumeric()) {
+            return Err(format!("invalid placeholder `{{{}}}` in path `{}`", name, template));
+        }
+        if start > 0 {
+            segments.push(Segment::Literal(rest[..start].to_string()));
+        }
+        segments.push(Segment::Param(name.to_string()));
+        rest = &rest[end + 1..];
+    }
+    if !rest.is_empty() {
+        segments.push(Segment::Literal(rest.to_string()));
+    }
+    Ok(segments)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{parse_path, Segment};
+
+    /// 测试路径模板解析
+    #[test]
+    fn test_parse_path() {
+        let segments = parse_path("/users/{id}/repos/{repo_name}").unwrap();
+        assert_eq!(segments, vec![
+            Segment::Literal("/users/".to_string()),
+            Segment::Param("id".to_string()),
+            Segment::Literal("/repos/".to_string()),
+            Segment::Param("repo_name".to_string()),
+        ]);
+        assert_eq!(parse_path("/health").unwrap(), vec![Segment::Literal("/health".to_string())]);
+        assert!(parse_path("/users/{id").is_err());
+        assert!(parse_path("/users/id}").is_err());
+        assert!(parse_path("/users/{a-b}").is_err());
+    }
+}